@@ -59,6 +59,22 @@ def copy_lt_files(out_dir, lt_dir, lang_code):
         canonicalize(out_dir / xmlfile)
 
 
+def write_lt_version(out_path, lt_dir):
+    # the version is stored in the manifest of languagetool-core.jar
+    manifest = (
+        ZipFile(lt_dir / "libs" / "languagetool-core.jar")
+        .read("META-INF/MANIFEST.MF")
+        .decode("utf-8")
+    )
+
+    version = "unknown"
+    for line in manifest.splitlines():
+        if line.startswith("Implementation-Version:"):
+            version = line.split(":", 1)[1].strip()
+
+    open(out_path, "w").write(version)
+
+
 def dump_dictionary(out_path, lt_dir, tag_dict_path, tag_info_path):
     # dump dictionary, see https://dev.languagetool.org/developing-a-tagger-dictionary
     os.system(
@@ -168,5 +184,6 @@ Only needed if the language requires a chunker (e. g. English).
         )
 
     open(args.out_dir / "lang_code.txt", "w").write(args.lang_code)
+    write_lt_version(args.out_dir / "lt_version.txt", args.lt_dir)
 
     print("Success!")
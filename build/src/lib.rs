@@ -4,7 +4,7 @@
 use flate2::bufread::GzDecoder;
 use fs::File;
 use fs_err as fs;
-use nlprule::{compile, rules_filename, tokenizer_filename};
+use nlprule::{bundle_filename, compile, rules_filename, tokenizer_filename};
 use std::fs::Permissions;
 use std::{
     io::{self, BufReader, BufWriter, Cursor, Read},
@@ -31,6 +31,8 @@ pub enum Error {
     PostprocessingError(#[source] OtherError),
     #[error("error transforming binaries: {0}")]
     TransformError(#[source] OtherError),
+    #[error("Failed to bundle binaries for lang {0}")]
+    BundlingFailed(String, #[source] nlprule::Error),
     #[error("Collation failed")]
     CollationFailed(#[source] nlprule::compile::Error),
 }
@@ -478,6 +480,38 @@ impl BinaryBuilder {
         Ok(())
     }
 
+    /// Combines the tokenizer and rules binaries of each language into a single bundle binary
+    /// which can be loaded with [nlprule::Language::load]. The bundles are placed next to the other binaries.
+    ///
+    /// Must be called after [build][BinaryBuilder::build] and before [postprocess][BinaryBuilder::postprocess].
+    /// Does not work together with [transform][BinaryBuilder::transform] since the binaries have to be readable by nlprule.
+    pub fn bundle(mut self) -> Result<Self> {
+        for lang_code in self.language_codes.clone() {
+            let tokenizer_out = self.out_dir.join(tokenizer_filename(&lang_code));
+            let rules_out = self.out_dir.join(rules_filename(&lang_code));
+            let bundle_out = self.out_dir.join(bundle_filename(&lang_code));
+
+            let tokenizer = nlprule::Tokenizer::new(tokenizer_out)
+                .map_err(|e| Error::BundlingFailed(lang_code.clone(), e))?;
+            let rules = nlprule::Rules::new(rules_out)
+                .map_err(|e| Error::BundlingFailed(lang_code.clone(), e))?;
+
+            let metadata = tokenizer
+                .header()
+                .map(|header| header.metadata.clone())
+                .unwrap_or_default();
+            let language = nlprule::Language::new(tokenizer, rules, metadata)
+                .map_err(|e| Error::BundlingFailed(lang_code.clone(), e))?;
+
+            language
+                .write(BufWriter::new(File::create(&bundle_out)?))
+                .map_err(|e| Error::BundlingFailed(lang_code.clone(), e))?;
+            self.outputs.push(bundle_out);
+        }
+
+        Ok(self)
+    }
+
     /// Gets the paths to all files this builder created.
     pub fn outputs(&self) -> &[PathBuf] {
         &self.outputs
@@ -589,6 +623,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn compiling_is_deterministic() -> Result<()> {
+        let tempdir = tempdir::TempDir::new("build_dir_test")?;
+        let tempdir = tempdir.path();
+
+        get_build_dir("en", &tempdir)?;
+
+        let compile = || -> Result<(Vec<u8>, Vec<u8>)> {
+            let mut rules = Vec::new();
+            let mut tokenizer = Vec::new();
+            compile::compile(tempdir, &mut rules, &mut tokenizer)
+                .map_err(Error::CollationFailed)?;
+            Ok((rules, tokenizer))
+        };
+
+        let (rules, tokenizer) = compile()?;
+        let (rules_again, tokenizer_again) = compile()?;
+
+        assert!(rules == rules_again, "rules binaries differ");
+        assert!(tokenizer == tokenizer_again, "tokenizer binaries differ");
+
+        Ok(())
+    }
+
     #[test]
     fn binary_builder_works() -> Result<()> {
         let tempdir = tempdir::TempDir::new("builder_test")?;
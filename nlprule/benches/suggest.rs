@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use nlprule::{Rules, Tokenizer};
+
+const TOKENIZER_PATH: &str = "../storage/en_tokenizer.bin";
+const RULES_PATH: &str = "../storage/en_rules.bin";
+
+/// Creates one long sentence by joining `n` clauses with commas.
+/// Matching cost grows with sentence length, especially for rules with antipatterns.
+fn long_sentence(n: usize) -> String {
+    let clause = "the man who was not been here since Monday said that he can due his homework";
+    let mut sentence = vec![clause; n].join(", and ");
+    sentence.push('.');
+    sentence
+}
+
+fn suggest_long_sentences(c: &mut Criterion) {
+    let tokenizer = Tokenizer::new(TOKENIZER_PATH).unwrap();
+    let rules = Rules::new(RULES_PATH).unwrap();
+
+    let mut group = c.benchmark_group("suggest_long_sentence");
+    group.sample_size(10);
+
+    for n in [1, 10, 50].iter() {
+        let text = long_sentence(*n);
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &text, |b, text| {
+            b.iter(|| rules.suggest(black_box(text), &tokenizer))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, suggest_long_sentences);
+criterion_main!(benches);
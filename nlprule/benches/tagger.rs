@@ -0,0 +1,93 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use nlprule::{
+    tokenizer::{tag::TaggerRepresentation, TokenizerOptions},
+    Tokenizer,
+};
+
+const TOKENIZER_PATH: &str = "../storage/en_tokenizer.bin";
+const WORDS: &[&str] = &[
+    "the",
+    "Houses",
+    "was",
+    "going",
+    "homework",
+    "nonexistentword",
+];
+
+/// Counts the bytes currently allocated on the heap to compare memory use of the representations.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn load(representation: TaggerRepresentation) -> Tokenizer {
+    let options = TokenizerOptions {
+        tagger: representation,
+        ..TokenizerOptions::default()
+    };
+    Tokenizer::new_with_options(TOKENIZER_PATH, options).unwrap()
+}
+
+fn tagger_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tagger_lookup");
+
+    for representation in [TaggerRepresentation::Heap, TaggerRepresentation::Fst].iter() {
+        let before = ALLOCATED.load(Ordering::SeqCst);
+        let tokenizer = load(*representation);
+        let after = ALLOCATED.load(Ordering::SeqCst);
+
+        println!(
+            "{:?}: tokenizer uses {:.1} MB of heap memory",
+            representation,
+            after.saturating_sub(before) as f64 / 1e6
+        );
+
+        let tagger = tokenizer.tagger();
+        group.bench_with_input(
+            BenchmarkId::new("get_tags", format!("{:?}", representation)),
+            &WORDS,
+            |b, words| {
+                b.iter(|| {
+                    for word in words.iter() {
+                        black_box(tagger.get_tags(black_box(word)));
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("get_group_members", format!("{:?}", representation)),
+            &WORDS,
+            |b, words| {
+                b.iter(|| {
+                    for word in words.iter() {
+                        black_box(tagger.get_group_members(black_box(word)));
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, tagger_lookup);
+criterion_main!(benches);
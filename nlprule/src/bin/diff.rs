@@ -0,0 +1,74 @@
+use clap::Clap;
+use fs_err as fs;
+use nlprule::{diff, rules::Rules, tokenizer::Tokenizer};
+use std::path::Path;
+
+#[derive(Clap)]
+#[clap(
+    version = "1.0",
+    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+)]
+struct Opts {
+    #[clap(long)]
+    old_tokenizer: String,
+    #[clap(long)]
+    old_rules: String,
+    #[clap(long)]
+    new_tokenizer: String,
+    #[clap(long)]
+    new_rules: String,
+    /// A text file with one document per line or a directory where each file is one document.
+    corpus: String,
+    /// The maximum number of examples per rule and per tagging difference. Defaults to the one of `DiffOptions`.
+    #[clap(long)]
+    examples: Option<usize>,
+}
+
+fn read_corpus<P: AsRef<Path>>(path: P) -> Vec<String> {
+    let path = path.as_ref();
+
+    if path.is_dir() {
+        let mut paths: Vec<_> = fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect()
+    } else {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.to_owned())
+            .collect()
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opts::parse();
+
+    let old = (
+        Tokenizer::new(opts.old_tokenizer).unwrap(),
+        Rules::new(opts.old_rules).unwrap(),
+    );
+    let new = (
+        Tokenizer::new(opts.new_tokenizer).unwrap(),
+        Rules::new(opts.new_rules).unwrap(),
+    );
+    let documents = read_corpus(opts.corpus);
+
+    let mut options = diff::DiffOptions::default();
+    if let Some(examples) = opts.examples {
+        options.max_examples = examples;
+    }
+
+    let report = diff::diff((&old.0, &old.1), (&new.0, &new.1), &documents, &options).unwrap();
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
@@ -0,0 +1,95 @@
+use clap::Clap;
+use fs_err as fs;
+use nlprule::{
+    evaluate::{self, EvaluationOptions, Scores},
+    rules::Rules,
+    tokenizer::Tokenizer,
+};
+
+#[derive(Clap)]
+#[clap(
+    version = "1.0",
+    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+)]
+struct Opts {
+    #[clap(long, short)]
+    tokenizer: String,
+    #[clap(long, short)]
+    rules: String,
+    /// A file with gold edits in M2 format.
+    corpus: String,
+    /// The number of replacements of each suggestion to compare with the gold edits.
+    #[clap(long, default_value = "1")]
+    replacements: usize,
+    /// Passes the tokens of the M2 sentences to the rules joined with spaces instead of as natural text.
+    #[clap(long)]
+    keep_tokenization: bool,
+    /// The number of rules and error types to print.
+    #[clap(long, default_value = "20")]
+    top: usize,
+    /// Writes the scores as JSON to this path.
+    #[clap(long)]
+    json: Option<String>,
+}
+
+fn print_scores(name: &str, scores: &Scores) {
+    println!(
+        "{:<48} {:>6} {:>6} {:>6} {:>7.4} {:>7.4} {:>7.4}",
+        name,
+        scores.true_positives,
+        scores.false_positives,
+        scores.false_negatives,
+        scores.precision(),
+        scores.recall(),
+        scores.f05()
+    );
+}
+
+fn print_header(name: &str) {
+    println!(
+        "\n{:<48} {:>6} {:>6} {:>6} {:>7} {:>7} {:>7}",
+        name, "TP", "FP", "FN", "P", "R", "F0.5"
+    );
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opts::parse();
+
+    let tokenizer = Tokenizer::new(opts.tokenizer).unwrap();
+    let rules = Rules::new(opts.rules).unwrap();
+    let sentences = evaluate::parse_m2(&fs::read_to_string(opts.corpus).unwrap()).unwrap();
+
+    let evaluation = evaluate::evaluate(
+        &sentences,
+        &rules,
+        &tokenizer,
+        &EvaluationOptions {
+            replacements: opts.replacements,
+            detokenize: !opts.keep_tokenization,
+        },
+    )
+    .unwrap();
+
+    println!("Sentences: {}", evaluation.sentences);
+    print_header("");
+    print_scores("Overall", &evaluation.overall);
+
+    let mut rules: Vec<_> = evaluation.rules.iter().collect();
+    rules.sort_by_key(|(_, x)| std::cmp::Reverse(x.true_positives + x.false_positives));
+    print_header("Rule");
+    for (id, scores) in rules.into_iter().take(opts.top) {
+        print_scores(id, scores);
+    }
+
+    let mut error_types: Vec<_> = evaluation.error_types.iter().collect();
+    error_types.sort_by_key(|(_, x)| std::cmp::Reverse(x.true_positives + x.false_negatives));
+    print_header("Error type");
+    for (kind, scores) in error_types.into_iter().take(opts.top) {
+        print_scores(kind, scores);
+    }
+
+    if let Some(path) = opts.json {
+        fs::write(path, serde_json::to_string_pretty(&evaluation).unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+use clap::Clap;
+use nlprule::{export, rules::Rules, tokenizer::Tokenizer};
+use std::io::{self, Write};
+
+#[derive(Clap)]
+#[clap(
+    version = "1.0",
+    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+)]
+struct Opts {
+    #[clap(long, short)]
+    tokenizer: String,
+    /// Exports the rules as grammar XML instead of the disambiguation rules of the tokenizer if set.
+    #[clap(long, short)]
+    rules: Option<String>,
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opts::parse();
+
+    let tokenizer = Tokenizer::new(opts.tokenizer).unwrap();
+
+    let xml = if let Some(rules) = opts.rules {
+        let rules = Rules::new(rules).unwrap();
+        export::export_rules(&rules, &tokenizer).unwrap()
+    } else {
+        export::export_disambiguation_rules(&tokenizer).unwrap()
+    };
+
+    io::stdout().write_all(xml.as_bytes()).unwrap();
+}
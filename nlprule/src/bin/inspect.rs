@@ -0,0 +1,48 @@
+use clap::Clap;
+use nlprule::{inspect, rules::Rules, tokenizer::Tokenizer};
+use std::io::{self, Write};
+
+#[derive(Clap)]
+#[clap(
+    version = "1.0",
+    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+)]
+struct Opts {
+    #[clap(long, short)]
+    tokenizer: String,
+    /// Dumps the rules instead of the tokenizer if set.
+    #[clap(long, short)]
+    rules: Option<String>,
+    /// Only dumps the rules with these IDs.
+    #[clap(long, short)]
+    ids: Vec<String>,
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opts::parse();
+    let ids = opts.ids;
+
+    let tokenizer = Tokenizer::new(opts.tokenizer).unwrap();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    if let Some(rules) = opts.rules {
+        let rules = Rules::new(rules).unwrap();
+        let mut dump = inspect::dump_rules(&rules, &tokenizer).unwrap();
+        if !ids.is_empty() {
+            dump.rules.retain(|rule| ids.contains(&rule.id));
+        }
+
+        serde_json::to_writer_pretty(&mut stdout, &dump).unwrap();
+    } else {
+        let mut dump = inspect::dump_tokenizer(&tokenizer);
+        if !ids.is_empty() {
+            dump.rules.retain(|rule| ids.contains(&rule.id));
+        }
+
+        serde_json::to_writer_pretty(&mut stdout, &dump).unwrap();
+    }
+
+    writeln!(stdout).unwrap();
+}
@@ -0,0 +1,83 @@
+use clap::Clap;
+use fs_err as fs;
+use nlprule::{
+    rules::Rules,
+    tokenizer::Tokenizer,
+    types::{Profile, RuleProfile},
+};
+use std::time::{Duration, Instant};
+
+#[derive(Clap)]
+#[clap(
+    version = "1.0",
+    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+)]
+struct Opts {
+    #[clap(long, short)]
+    tokenizer: String,
+    #[clap(long, short)]
+    rules: String,
+    /// A text file with one document per line.
+    corpus: String,
+    /// The number of rules to print in each ranking.
+    #[clap(long, default_value = "20")]
+    top: usize,
+    /// Writes the statistics of all rules as JSON to this path.
+    #[clap(long)]
+    json: Option<String>,
+}
+
+fn print_ranking<K: Ord>(
+    title: &str,
+    profile: &Profile,
+    top: usize,
+    key: impl Fn(&RuleProfile) -> K,
+) {
+    let mut rules: Vec<_> = profile.rules.iter().collect();
+    rules.sort_by(|(_, a), (_, b)| key(b).cmp(&key(a)));
+
+    println!(
+        "\n{:<48} {:>10} {:>11} {:>9} {:>11}",
+        title, "Time (ms)", "Evaluations", "Matches", "Suggestions"
+    );
+    for (id, rule) in rules.into_iter().take(top) {
+        println!(
+            "{:<48} {:>10.2} {:>11} {:>9} {:>11}",
+            id,
+            rule.time.as_secs_f64() * 1000.,
+            rule.evaluations,
+            rule.matches,
+            rule.suggestions
+        );
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opts::parse();
+
+    let tokenizer = Tokenizer::new(opts.tokenizer).unwrap();
+    let rules = Rules::new(opts.rules).unwrap();
+    let corpus = fs::read_to_string(opts.corpus).unwrap();
+
+    let mut profile = Profile::default();
+    let start = Instant::now();
+    let mut n_documents = 0;
+
+    for line in corpus.lines().filter(|line| !line.trim().is_empty()) {
+        rules.suggest_with_profile(line, &tokenizer, &mut profile);
+        n_documents += 1;
+    }
+
+    let rule_time: Duration = profile.rules.values().map(|x| x.time).sum();
+    println!("Documents: {}", n_documents);
+    println!("Total time: {:.2?}", start.elapsed());
+    println!("Time in rules: {:.2?}", rule_time);
+
+    print_ranking("Rule (by time)", &profile, opts.top, |x| x.time);
+    print_ranking("Rule (by hits)", &profile, opts.top, |x| x.suggestions);
+
+    if let Some(path) = opts.json {
+        fs::write(path, serde_json::to_string_pretty(&profile).unwrap()).unwrap();
+    }
+}
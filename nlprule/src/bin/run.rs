@@ -1,5 +1,6 @@
 use clap::Clap;
-use nlprule::{rules::Rules, tokenizer::Tokenizer};
+use nlprule::{rule::id::Selector, rules::Rules, tokenizer::Tokenizer};
+use std::convert::TryFrom;
 
 #[derive(Clap)]
 #[clap(
@@ -12,17 +13,35 @@ struct Opts {
     tokenizer: String,
     #[clap(long, short)]
     rules: String,
+    /// Prints explanations of how the rules matching this selector (e. g. "grammar/DOSNT") are applied as JSON
+    /// instead of the tokens and suggestions.
+    #[clap(long, short)]
+    explain: Option<String>,
 }
 
 fn main() {
     env_logger::init();
     let opts = Opts::parse();
+    let text = &opts.text;
 
     let tokenizer = Tokenizer::new(opts.tokenizer).unwrap();
     let rules = Rules::new(opts.rules).unwrap();
 
-    let tokens = tokenizer.pipe(&opts.text);
+    if let Some(selector) = opts.explain {
+        let selector = Selector::try_from(selector).unwrap();
+        let explanations: Vec<_> = rules
+            .rules()
+            .iter()
+            .filter(|rule| selector.is_match(rule.id()))
+            .flat_map(|rule| rule.explain(text, &tokenizer))
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&explanations).unwrap());
+        return;
+    }
+
+    let tokens = tokenizer.pipe(text);
 
     println!("Tokens: {:#?}", tokens);
-    println!("Suggestions: {:#?}", rules.suggest(&opts.text, &tokenizer));
+    println!("Suggestions: {:#?}", rules.suggest(text, &tokenizer));
 }
@@ -1,5 +1,6 @@
 use clap::Clap;
-use nlprule::{rules::Rules, tokenizer::Tokenizer};
+use fs_err as fs;
+use nlprule::{rule::report, rules::Rules, tokenizer::Tokenizer};
 
 #[derive(Clap)]
 #[clap(
@@ -13,6 +14,12 @@ struct Opts {
     rules: String,
     #[clap(long, short)]
     ids: Vec<String>,
+    /// Writes the result of each example as JSON to this path.
+    #[clap(long)]
+    json: Option<String>,
+    /// Writes the result of each example as JUnit XML to this path.
+    #[clap(long)]
+    junit: Option<String>,
 }
 
 fn main() {
@@ -26,13 +33,23 @@ fn main() {
     println!("Runnable rules: {}", rules.len());
 
     let mut passes = 0;
+    let mut reports = Vec::new();
     for rule in rules {
         if opts.ids.is_empty() || opts.ids.contains(&rule.id().to_string()) {
-            passes += rule.test(&tokenizer) as usize;
+            let rule_reports = rule.test_with_report(&tokenizer);
+            passes += rule_reports.iter().all(|x| x.passed) as usize;
+            reports.extend(rule_reports);
         }
     }
 
     println!("Rules passing tests: {}", passes);
+
+    if let Some(path) = opts.json {
+        fs::write(path, serde_json::to_string_pretty(&reports).unwrap()).unwrap();
+    }
+    if let Some(path) = opts.junit {
+        fs::write(path, report::to_junit("rules", &reports)).unwrap();
+    }
     if passes == rules.len() {
         std::process::exit(0);
     } else {
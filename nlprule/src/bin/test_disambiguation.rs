@@ -1,5 +1,6 @@
 use clap::Clap;
-use nlprule::tokenizer::Tokenizer;
+use fs_err as fs;
+use nlprule::{rule::report, tokenizer::Tokenizer};
 
 #[derive(Clap)]
 #[clap(
@@ -11,6 +12,12 @@ struct Opts {
     stop_at_error: bool,
     #[clap(long, short)]
     tokenizer: String,
+    /// Writes the result of each example as JSON to this path.
+    #[clap(long)]
+    json: Option<String>,
+    /// Writes the result of each example as JUnit XML to this path.
+    #[clap(long)]
+    junit: Option<String>,
 }
 
 fn main() {
@@ -24,9 +31,14 @@ fn main() {
     println!("Runnable rules: {}", rules.len());
 
     let mut passes = 0;
+    let mut reports = Vec::new();
 
     for rule in rules {
-        if rule.test(&tokenizer) {
+        let rule_reports = rule.test_with_report(&tokenizer);
+        let pass = rule_reports.iter().all(|x| x.passed);
+        reports.extend(rule_reports);
+
+        if pass {
             passes += 1;
         } else if opts.stop_at_error {
             break;
@@ -34,6 +46,13 @@ fn main() {
     }
 
     println!("Rules passing tests: {}", passes);
+
+    if let Some(path) = opts.json {
+        fs::write(path, serde_json::to_string_pretty(&reports).unwrap()).unwrap();
+    }
+    if let Some(path) = opts.junit {
+        fs::write(path, report::to_junit("disambiguation", &reports)).unwrap();
+    }
     if passes == rules.len() {
         std::process::exit(0);
     } else {
@@ -0,0 +1,238 @@
+//! The binary format of tokenizers and rule sets.
+//!
+//! A binary starts with a magic number, the format version and the version of nlprule which created it.
+//! This prefix never changes between format versions so incompatible binaries can always be reported.
+//! It is followed by the rest of the [Header] and the bincode-serialized payload.
+
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{Read, Write},
+};
+
+/// The magic number at the start of every binary.
+pub(crate) const MAGIC: &[u8; 8] = b"NLPRULE\0";
+
+/// The version of the binary format. Must be incremented on every change to the serialized structures.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The kind of structure stored in a binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryKind {
+    /// A [Tokenizer][crate::Tokenizer].
+    Tokenizer,
+    /// A [Rules][crate::Rules] set.
+    Rules,
+    /// A [Language][crate::Language] bundle of a tokenizer and a rule set.
+    Bundle,
+}
+
+impl fmt::Display for BinaryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryKind::Tokenizer => write!(f, "tokenizer"),
+            BinaryKind::Rules => write!(f, "rules"),
+            BinaryKind::Bundle => write!(f, "bundle"),
+        }
+    }
+}
+
+/// Information about the sources a binary was built from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The language code in ISO 639-1 (two-letter) format.
+    pub lang_code: String,
+    /// The version of LanguageTool the rules and dictionaries were taken from.
+    pub lt_version: String,
+}
+
+/// The header of a binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// The version of the binary format.
+    pub format_version: u32,
+    /// The version of nlprule which created the binary.
+    pub crate_version: String,
+    /// The kind of structure stored in the binary.
+    pub kind: BinaryKind,
+    /// Information about the sources of the binary.
+    pub metadata: Metadata,
+    /// A checksum of the payload.
+    pub checksum: u64,
+}
+
+/// The part of the header after the prefix. Can change between format versions.
+#[derive(Serialize, Deserialize)]
+struct HeaderFields {
+    kind: BinaryKind,
+    metadata: Metadata,
+    checksum: u64,
+}
+
+/// Computes the 64-bit FNV-1a hash of the bytes. Unlike the std hashers this is guaranteed to be stable.
+pub(crate) fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Serializes the value with a header into the writer.
+pub(crate) fn write<W: Write, T: Serialize>(
+    mut writer: W,
+    kind: BinaryKind,
+    metadata: &Metadata,
+    value: &T,
+) -> Result<(), bincode::Error> {
+    let payload = bincode::serialize(value)?;
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, env!("CARGO_PKG_VERSION"))?;
+    bincode::serialize_into(
+        &mut writer,
+        &HeaderFields {
+            kind,
+            metadata: metadata.clone(),
+            checksum: checksum(&payload),
+        },
+    )?;
+    writer.write_all(&payload)?;
+
+    Ok(())
+}
+
+/// Reads and validates the header of a binary of the given kind. The reader is left at the start of the payload.
+pub(crate) fn read_header<R: Read>(reader: &mut R, kind: BinaryKind) -> Result<Header, Error> {
+    let mut magic = [0u8; 8];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| Error::NotABinary)?;
+    if &magic != MAGIC {
+        return Err(Error::NotABinary);
+    }
+
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let format_version = u32::from_le_bytes(version);
+    let crate_version: String = bincode::deserialize_from(&mut *reader)?;
+
+    if format_version != FORMAT_VERSION {
+        return Err(Error::IncompatibleFormat {
+            found: format_version,
+            expected: FORMAT_VERSION,
+            crate_version,
+        });
+    }
+
+    let fields: HeaderFields = bincode::deserialize_from(&mut *reader)?;
+    if fields.kind != kind {
+        return Err(Error::WrongBinaryKind {
+            expected: kind,
+            found: fields.kind,
+        });
+    }
+
+    Ok(Header {
+        format_version,
+        crate_version,
+        kind: fields.kind,
+        metadata: fields.metadata,
+        checksum: fields.checksum,
+    })
+}
+
+/// Checks that the payload matches the checksum in the header.
+pub(crate) fn verify(header: &Header, payload: &[u8]) -> Result<(), Error> {
+    let found = checksum(payload);
+
+    if found != header.checksum {
+        return Err(Error::ChecksumMismatch {
+            expected: header.checksum,
+            found,
+        });
+    }
+
+    Ok(())
+}
+
+/// Reads a binary of the given kind. Returns the validated header and the payload.
+pub(crate) fn read<R: Read>(mut reader: R, kind: BinaryKind) -> Result<(Header, Vec<u8>), Error> {
+    let header = read_header(&mut reader, kind)?;
+
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload)?;
+    verify(&header, &payload)?;
+
+    Ok((header, payload))
+}
+
+/// Splits a binary of the given kind in memory into the validated header and the offset of the payload.
+/// The payload is not checked against the checksum: that would read every page of a memory-mapped binary
+/// and defeat loading the parts which are used on demand.
+pub(crate) fn split(bytes: &[u8], kind: BinaryKind) -> Result<(Header, usize), Error> {
+    let mut rest = bytes;
+    let header = read_header(&mut rest, kind)?;
+
+    Ok((header, bytes.len() - rest.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(kind: BinaryKind) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let metadata = Metadata {
+            lang_code: "en".into(),
+            lt_version: "5.2".into(),
+        };
+        write(&mut bytes, kind, &metadata, &vec![1u32, 2, 3]).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn binaries_can_be_read() {
+        let (header, payload) = read(&binary(BinaryKind::Rules)[..], BinaryKind::Rules).unwrap();
+
+        assert_eq!(header.format_version, FORMAT_VERSION);
+        assert_eq!(header.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(header.metadata.lang_code, "en");
+        assert_eq!(
+            bincode::deserialize::<Vec<u32>>(&payload).unwrap(),
+            vec![1, 2, 3]
+        );
+
+        let bytes = binary(BinaryKind::Rules);
+        let (_, offset) = split(&bytes, BinaryKind::Rules).unwrap();
+        assert_eq!(&bytes[offset..], &payload[..]);
+    }
+
+    #[test]
+    fn invalid_binaries_are_rejected() {
+        assert!(matches!(
+            read(&b"not a binary"[..], BinaryKind::Rules),
+            Err(Error::NotABinary)
+        ));
+        assert!(matches!(
+            read(&binary(BinaryKind::Tokenizer)[..], BinaryKind::Rules),
+            Err(Error::WrongBinaryKind { .. })
+        ));
+
+        let mut bytes = binary(BinaryKind::Rules);
+        bytes[8] += 1;
+        assert!(matches!(
+            read(&bytes[..], BinaryKind::Rules),
+            Err(Error::IncompatibleFormat { .. })
+        ));
+
+        let mut bytes = binary(BinaryKind::Rules);
+        *bytes.last_mut().unwrap() += 1;
+        assert!(matches!(
+            read(&bytes[..], BinaryKind::Rules),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        // the checksum is only verified when the payload is read as a whole
+        assert!(split(&bytes, BinaryKind::Rules).is_ok());
+    }
+}
@@ -5,6 +5,7 @@ use log::warn;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
+    convert::TryFrom,
     hash::{Hash, Hasher},
     io::{self, BufRead, BufReader},
     path::Path,
@@ -20,15 +21,15 @@ use crate::{
         id::Category,
         DisambiguationRule, MatchGraph, Rule,
     },
-    rules::{Rules, RulesLangOptions, RulesOptions},
+    rules::{Rules, RulesFields, RulesLangOptions, RulesOptions},
     tokenizer::{
         chunk,
         multiword::{MultiwordTagger, MultiwordTaggerFields},
-        tag::{Tagger, TaggerLangOptions},
-        Tokenizer, TokenizerLangOptions,
+        tag::{HeapData, Tagger, TaggerData, TaggerLangOptions},
+        Tokenizer, TokenizerLangOptions, TokenizerOptions,
     },
     types::*,
-    utils::{parallelism::MaybeParallelIterator, regex::Regex},
+    utils::{lazy::Lazy, parallelism::MaybeParallelIterator, regex::Regex},
 };
 
 use super::{parse_structure::BuildInfo, Error};
@@ -161,12 +162,21 @@ impl Tagger {
                 .push(*pos_id);
         }
 
+        let mut hasher = DefaultHasher::default();
+        let mut sorted_words = word_store.iter().collect::<Vec<_>>();
+        sorted_words.sort_by(|a, b| a.1.cmp(b.1));
+        sorted_words.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+
         Ok(Tagger {
-            tags,
-            groups,
-            word_store,
+            data: TaggerData::Heap(HeapData {
+                tags,
+                word_store,
+                groups,
+            }),
             tag_store,
             lang_options,
+            fingerprint: Some(fingerprint),
         })
     }
 }
@@ -205,16 +215,7 @@ impl TextMatcher {
     pub(in crate::compile) fn new(matcher: Matcher, info: &mut BuildInfo) -> Self {
         let graph = MatchGraph::default();
 
-        // can not cache a matcher that depends on the graph
-        let set = if matcher.graph_id().is_some() {
-            None
-        } else if let either::Right(regex) = &matcher.matcher {
-            let mut hasher = DefaultHasher::default();
-            regex.hash(&mut hasher);
-            matcher.negate.hash(&mut hasher);
-            matcher.empty_always_false.hash(&mut hasher);
-            let matcher_hash = hasher.finish();
-
+        let set = if let Some(matcher_hash) = TextMatcher::cache_key(&matcher) {
             if let Some(set) = info.mut_regex_cache().get(&matcher_hash) {
                 set.clone()
             } else {
@@ -244,6 +245,25 @@ impl TextMatcher {
 
         TextMatcher { matcher, set }
     }
+
+    /// Gets the key of the set of words a matcher matches in the regex cache.
+    /// `None` if the matcher can not be cached.
+    pub(in crate::compile) fn cache_key(matcher: &Matcher) -> Option<u64> {
+        // can not cache a matcher that depends on the graph
+        if matcher.graph_id().is_some() {
+            return None;
+        }
+
+        if let either::Right(regex) = &matcher.matcher {
+            let mut hasher = DefaultHasher::default();
+            regex.hash(&mut hasher);
+            matcher.negate.hash(&mut hasher);
+            matcher.empty_always_false.hash(&mut hasher);
+            Some(hasher.finish())
+        } else {
+            None
+        }
+    }
 }
 
 impl PosMatcher {
@@ -320,9 +340,10 @@ impl Rules {
                                 && !options.ignore_ids.iter().any(|x| x.is_match(&id))
                             {
                                 rule.id = id;
-                                rule.name = name;
-                                rule.category_name = category.name;
-                                rule.category_type = category.kind;
+                                let details = rule.details.get_mut();
+                                details.name = name;
+                                details.category_name = category.name;
+                                details.category_type = category.kind;
                                 rule.enabled = category_on && group_on && rule_on;
                                 Some(rule)
                             } else {
@@ -355,10 +376,15 @@ impl Rules {
             );
         }
 
-        Rules {
-            rules,
+        Rules::try_from(RulesFields {
+            rules: rules
+                .into_iter()
+                .map(|rule| (rule.id().clone(), Lazy::new(rule)))
+                .collect(),
             options: RulesOptions::default(),
-        }
+            tagger_fingerprint: build_info.tagger().fingerprint(),
+        })
+        .expect("compiled rules are already deserialized")
     }
 }
 
@@ -440,7 +466,9 @@ impl Tokenizer {
             chunker,
             multiword_tagger,
             rules,
+            options: TokenizerOptions::default(),
             lang_options,
+            header: None,
         })
     }
 }
@@ -727,6 +755,8 @@ mod composition {
             let can_stop_mask = (0..parts.len())
                 .map(|i| parts[i..].iter().all(|x| x.quantifier.min == 0))
                 .collect();
+            let uses_graph = parts.iter().any(|x| x.atom.uses_graph());
+            let tag_windows = parts.iter().map(|x| x.atom.tag_window()).collect();
 
             for (i, part) in parts.iter_mut().enumerate() {
                 for id in part.atom.mut_graph_ids() {
@@ -749,6 +779,8 @@ mod composition {
                 parts,
                 id_to_idx,
                 can_stop_mask,
+                uses_graph,
+                tag_windows,
             })
         }
     }
@@ -4,7 +4,6 @@ use fs::File;
 use fs_err as fs;
 
 use std::{
-    hash::{Hash, Hasher},
     io::{self, BufReader, BufWriter},
     num::ParseIntError,
     path::{Path, PathBuf},
@@ -13,9 +12,9 @@ use std::{
 };
 
 use crate::{
+    binary::{self, BinaryKind, Metadata},
     rules::Rules,
     tokenizer::{chunk::Chunker, multiword::MultiwordTagger, tag::Tagger, Tokenizer},
-    types::DefaultHasher,
 };
 use log::info;
 
@@ -29,6 +28,7 @@ mod utils;
 
 struct BuildFilePaths {
     lang_code_path: PathBuf,
+    lt_version_path: PathBuf,
     tag_paths: Vec<PathBuf>,
     tag_remove_paths: Vec<PathBuf>,
     chunker_path: PathBuf,
@@ -45,6 +45,7 @@ impl BuildFilePaths {
         let p = build_dir.as_ref();
         BuildFilePaths {
             lang_code_path: p.join("lang_code.txt"),
+            lt_version_path: p.join("lt_version.txt"),
             tag_paths: vec![p.join("tags/output.dump"), p.join("tags/added.txt")],
             tag_remove_paths: vec![p.join("tags/removed.txt")],
             chunker_path: p.join("chunker.json"),
@@ -86,6 +87,7 @@ pub enum Error {
 }
 
 /// Compiles the binaries from a build directory.
+/// The output is deterministic: compiling the same build directory again yields the same bytes.
 pub fn compile(
     build_dir: impl AsRef<Path>,
     mut rules_dest: impl io::Write,
@@ -94,6 +96,18 @@ pub fn compile(
     let paths = BuildFilePaths::new(&build_dir);
 
     let lang_code = fs::read_to_string(paths.lang_code_path)?;
+    // older build directories do not record the LanguageTool version
+    let lt_version = if paths.lt_version_path.exists() {
+        fs::read_to_string(paths.lt_version_path)?
+            .trim()
+            .to_string()
+    } else {
+        "unknown".to_string()
+    };
+    let metadata = Metadata {
+        lang_code: lang_code.clone(),
+        lt_version,
+    };
 
     info!(
         "Reading common words from {}.",
@@ -128,11 +142,9 @@ pub fn compile(
         tagger_lang_options,
     )?;
 
-    let mut hasher = DefaultHasher::default();
-    let mut word_store = tagger.word_store().iter().collect::<Vec<_>>();
-    word_store.sort_by(|a, b| a.1.cmp(b.1));
-    word_store.hash(&mut hasher);
-    let word_store_hash = hasher.finish();
+    let word_store_hash = tagger
+        .fingerprint()
+        .expect("compiled taggers have a fingerprint");
 
     let regex_cache = if let Ok(file) = File::open(&paths.regex_cache_path) {
         let cache: RegexCache = bincode::deserialize_from(BufReader::new(file))?;
@@ -186,11 +198,16 @@ pub fn compile(
         tokenizer_lang_options,
     )?;
 
-    bincode::serialize_into(&mut tokenizer_dest, &tokenizer)?;
+    binary::write(
+        &mut tokenizer_dest,
+        BinaryKind::Tokenizer,
+        &metadata,
+        &tokenizer,
+    )?;
 
     info!("Creating grammar rules.");
     let rules = Rules::from_xml(&paths.grammar_path, &mut build_info, rules_lang_options);
-    bincode::serialize_into(&mut rules_dest, &rules)?;
+    binary::write(&mut rules_dest, BinaryKind::Rules, &metadata, &rules)?;
 
     // we need to write the regex cache after building the rules, otherwise it isn't fully populated
     let f = BufWriter::new(File::create(&paths.regex_cache_path)?);
@@ -198,3 +215,163 @@ pub fn compile(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export,
+        rule::{
+            disambiguation::DisambiguationExample,
+            engine::{
+                composition::{Atom, Composition, TextMatcher},
+                Engine,
+            },
+            Rule,
+        },
+        tokenizer::finalize,
+        utils::budget::Budget,
+    };
+
+    const TOKENIZER_PATH: &str = "../storage/en_tokenizer.bin";
+    const RULES_PATH: &str = "../storage/en_rules.bin";
+
+    fn seed_atom(atom: &Atom, cache: &mut RegexCache) {
+        let mut seed = |matcher: &TextMatcher| {
+            if let Some(key) = TextMatcher::cache_key(&matcher.matcher) {
+                cache.insert(key, matcher.set.clone());
+            }
+        };
+
+        match atom {
+            Atom::TextAtom(x) => seed(&x.matcher),
+            Atom::WordDataAtom(x) => {
+                if let Some(matcher) = &x.matcher.inflect_matcher {
+                    seed(matcher);
+                }
+            }
+            Atom::AndAtom(x) => x.atoms.iter().for_each(|x| seed_atom(x, cache)),
+            Atom::OrAtom(x) => x.atoms.iter().for_each(|x| seed_atom(x, cache)),
+            Atom::NotAtom(x) => seed_atom(&x.atom, cache),
+            Atom::OffsetAtom(x) => seed_atom(&x.atom, cache),
+            _ => {}
+        }
+    }
+
+    fn seed_engine(engine: &Engine, cache: &mut RegexCache) {
+        if let Engine::Token(engine) = engine {
+            std::iter::once(&engine.composition)
+                .chain(&engine.antipatterns)
+                .flat_map(|x: &Composition| &x.parts)
+                .for_each(|part| seed_atom(&part.atom, cache));
+        }
+    }
+
+    /// Creates build info which reuses the word sets of the compiled matchers.
+    /// Otherwise every regex would have to be matched against all words again.
+    fn build_info(tokenizer: &Tokenizer, rules: &Rules) -> BuildInfo {
+        let tagger = tokenizer.tagger().clone();
+        let mut cache = RegexCache::new(tagger.fingerprint().expect("tagger has a fingerprint"));
+
+        for rule in tokenizer.rules() {
+            seed_engine(&rule.engine, &mut cache);
+        }
+        for rule in rules.rules() {
+            seed_engine(&rule.engine, &mut cache);
+        }
+
+        BuildInfo::new(tagger, cache)
+    }
+
+    #[test]
+    fn exported_rules_compile_to_same_behavior() -> Result<(), Box<dyn std::error::Error>> {
+        let tokenizer = Tokenizer::new(TOKENIZER_PATH)?;
+        let rules = Rules::new(RULES_PATH)?;
+        let mut build_info = build_info(&tokenizer, &rules);
+
+        let dir = std::env::temp_dir().join(format!("nlprule-export-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let grammar_path = dir.join("grammar.xml");
+        let disambiguation_path = dir.join("disambiguation.xml");
+        fs::write(&grammar_path, export::export_rules(&rules, &tokenizer)?)?;
+        fs::write(
+            &disambiguation_path,
+            export::export_disambiguation_rules(&tokenizer)?,
+        )?;
+
+        // the exported files only contain the selected rules
+        let mut rules_options = utils::rules_lang_options("en").unwrap();
+        rules_options.ids.clear();
+        rules_options.ignore_ids.clear();
+        let mut tokenizer_options = utils::tokenizer_lang_options("en").unwrap();
+        tokenizer_options.ids.clear();
+        tokenizer_options.ignore_ids.clear();
+
+        let recompiled_rules = Rules::from_xml(&grammar_path, &mut build_info, rules_options);
+        let mut recompiled_tokenizer = Tokenizer::new(TOKENIZER_PATH)?;
+        recompiled_tokenizer.rules = Tokenizer::from_xml(
+            &disambiguation_path,
+            &mut build_info,
+            None,
+            None,
+            Default::default(),
+            tokenizer_options,
+        )?
+        .rules;
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(tokenizer.rules().len(), recompiled_tokenizer.rules().len());
+        for (rule, recompiled) in tokenizer.rules().iter().zip(recompiled_tokenizer.rules()) {
+            assert_eq!(
+                rule.examples.len(),
+                recompiled.examples.len(),
+                "{}",
+                rule.id()
+            );
+
+            for example in &rule.examples {
+                let text = match example {
+                    DisambiguationExample::Unchanged(text) => text,
+                    DisambiguationExample::Changed(change) => &change.text,
+                };
+
+                assert_eq!(
+                    format!("{:?}", tokenizer.pipe(text)),
+                    format!("{:?}", recompiled_tokenizer.pipe(text)),
+                    "{}",
+                    rule.id()
+                );
+            }
+            assert_eq!(
+                rule.test(&tokenizer),
+                recompiled.test(&recompiled_tokenizer),
+                "{}",
+                rule.id()
+            );
+        }
+
+        assert_eq!(rules.rules().len(), recompiled_rules.rules().len());
+        for (rule, recompiled) in rules.rules().iter().zip(recompiled_rules.rules()) {
+            assert_eq!(rule.name(), recompiled.name());
+            assert_eq!(rule.enabled(), recompiled.enabled(), "{}", rule.id());
+
+            let texts: Vec<_> = rule.examples().iter().map(|x| x.text()).collect();
+            let recompiled_texts: Vec<_> = recompiled.examples().iter().map(|x| x.text()).collect();
+            assert_eq!(texts, recompiled_texts, "{}", rule.id());
+
+            for text in texts {
+                let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(text)));
+                // the source differs if rules of a group were left out when compiling
+                let key = |rule: &Rule| -> Vec<_> {
+                    rule.apply(&tokens, &tokenizer, &Budget::default())
+                        .map(|x| (x.start, x.end, x.replacements, x.message))
+                        .collect()
+                };
+
+                assert_eq!(key(rule), key(recompiled), "{}: {}", rule.id(), text);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use super::{structure, Error};
 use crate::{tokenizer::tag::Tagger, types::*};
-use crate::{utils, utils::regex::Regex};
+use crate::{utils, utils::lazy::Lazy, utils::regex::Regex};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
@@ -13,7 +13,7 @@ use crate::rule::engine::composition::concrete::*;
 use crate::rule::engine::composition::*;
 use crate::rule::engine::*;
 use crate::rule::grammar::*;
-use crate::rule::{id::Index, DisambiguationRule, Rule, Unification};
+use crate::rule::{id::Index, DisambiguationRule, Rule, RuleDetails, Unification};
 
 // this is set arbitrarily at the moment, could be an option
 #[inline]
@@ -885,19 +885,22 @@ impl Rule {
             end: engine.to_graph_id(end)?,
             engine,
             unification,
-            examples,
-            suggesters,
-            message: Synthesizer {
-                parts: message_parts,
-                use_titlecase_adjust: true,
-            },
-            url: data.url.map(|x| x.to_string()),
-            short: data.short.map(|x| x.to_string()),
+            details: Lazy::new(RuleDetails {
+                examples,
+                suggesters,
+                message: Synthesizer {
+                    parts: message_parts,
+                    use_titlecase_adjust: true,
+                },
+                url: data.url.map(|x| x.to_string()),
+                short: data.short.map(|x| x.to_string()),
+                // fields below need information from rule group / category, so are set later
+                name: String::new(),
+                category_name: String::new(),
+                category_type: None,
+            }),
             // fields below need information from rule group / category, so are set later
             id: Index::default(),
-            name: String::new(),
-            category_name: String::new(),
-            category_type: None,
             enabled: true,
         })
     }
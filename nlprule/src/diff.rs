@@ -0,0 +1,324 @@
+//! Behavioral differences between two tokenizer and rule set pairs on a corpus.
+//!
+//! Meant to find out what changes in practice when upgrading nlprule or the LanguageTool sources the binaries
+//! are compiled from. Suggestions are compared by rule ID and char span: a suggestion is *changed* if both pairs
+//! return it with different replacements or messages. Tokens are compared by char span: a token differs if its
+//! readings differ or if it only exists in the tokenization of one pair.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{diff, Rules, Tokenizer};
+//!
+//! let old = (Tokenizer::new("old/en_tokenizer.bin")?, Rules::new("old/en_rules.bin")?);
+//! let new = (Tokenizer::new("new/en_tokenizer.bin")?, Rules::new("new/en_rules.bin")?);
+//!
+//! let report = diff::diff(
+//!     (&old.0, &old.1),
+//!     (&new.0, &new.1),
+//!     &["She was not been here since Monday."],
+//!     &diff::DiffOptions::default(),
+//! )?;
+//!
+//! for rule in report.rules {
+//!     println!("{}: +{} -{} ~{}", rule.rule, rule.added, rule.removed, rule.changed);
+//! }
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{types::*, Error, Rules, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+};
+
+/// Options for [diff].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffOptions {
+    /// The maximum number of examples stored per rule and per tagging difference.
+    pub max_examples: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions { max_examples: 5 }
+    }
+}
+
+/// How a suggestion differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Only the new pair returns the suggestion.
+    Added,
+    /// Only the old pair returns the suggestion.
+    Removed,
+    /// Both pairs return the suggestion, but with different replacements or messages.
+    Changed,
+}
+
+/// An example of a differing suggestion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestionDiff {
+    /// How the suggestion differs.
+    pub kind: ChangeKind,
+    /// The index of the document.
+    pub document: usize,
+    /// The sentence containing the suggestion.
+    pub sentence: String,
+    /// The suggestion of the old pair. Char indices are relative to the document.
+    pub before: Option<Suggestion>,
+    /// The suggestion of the new pair. Char indices are relative to the document.
+    pub after: Option<Suggestion>,
+}
+
+/// The differences of the suggestions of one rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleDiff {
+    /// The ID of the rule.
+    pub rule: String,
+    /// The number of added suggestions.
+    pub added: usize,
+    /// The number of removed suggestions.
+    pub removed: usize,
+    /// The number of changed suggestions.
+    pub changed: usize,
+    /// Examples of the differences, at most [DiffOptions::max_examples].
+    pub examples: Vec<SuggestionDiff>,
+}
+
+/// A difference in the tokenization or tagging of a word. Equal differences of the same word are counted together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagDiff {
+    /// The text of the token.
+    pub text: String,
+    /// The readings assigned by the old pair. `None` if the old pair does not have a token with the same char span.
+    pub before: Option<Vec<Reading>>,
+    /// The readings assigned by the new pair. `None` if the new pair does not have a token with the same char span.
+    pub after: Option<Vec<Reading>>,
+    /// How often this difference occurs.
+    pub count: usize,
+    /// Sentences in which this difference occurs, at most [DiffOptions::max_examples].
+    pub examples: Vec<String>,
+}
+
+/// The differences between two tokenizer and rule set pairs on a corpus.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiffReport {
+    /// The number of documents in the corpus.
+    pub documents: usize,
+    /// The number of suggestions of the old pair.
+    pub suggestions_before: usize,
+    /// The number of suggestions of the new pair.
+    pub suggestions_after: usize,
+    /// The differences of the suggestions, ordered by rule ID.
+    pub rules: Vec<RuleDiff>,
+    /// The differences in tokenization and tagging, most frequent first.
+    pub tags: Vec<TagDiff>,
+}
+
+/// The output of one pair on one document.
+struct Output {
+    /// The char offset and text of each sentence.
+    sentences: Vec<(usize, String)>,
+    suggestions: Vec<Suggestion>,
+    /// The text and sorted readings of each token by char span.
+    tokens: BTreeMap<(usize, usize), (String, Vec<Reading>)>,
+}
+
+impl Output {
+    fn new(tokenizer: &Tokenizer, rules: &Rules, text: &str) -> Self {
+        let mut output = Output {
+            sentences: Vec::new(),
+            suggestions: Vec::new(),
+            tokens: BTreeMap::new(),
+        };
+        let mut char_offset = 0;
+
+        for tokens in tokenizer.pipe(text) {
+            if tokens.is_empty() {
+                continue;
+            }
+
+            output
+                .suggestions
+                .extend(rules.apply(&tokens, tokenizer).into_iter().map(|mut x| {
+                    x.rshift(char_offset);
+                    x
+                }));
+
+            // the first token is the special sentence start token
+            for token in &tokens[1..] {
+                let readings: BTreeSet<_> = token.word.tags.iter().map(Reading::from).collect();
+
+                output.tokens.insert(
+                    (
+                        token.char_span.0 + char_offset,
+                        token.char_span.1 + char_offset,
+                    ),
+                    (
+                        token.word.text.as_ref().to_owned(),
+                        readings.into_iter().collect(),
+                    ),
+                );
+            }
+
+            let sentence = tokens[0].sentence;
+            output.sentences.push((char_offset, sentence.to_owned()));
+            char_offset += sentence.chars().count();
+        }
+
+        output
+    }
+
+    /// Gets the sentence containing the char index.
+    fn sentence(&self, index: usize) -> &str {
+        self.sentences
+            .iter()
+            .rev()
+            .find(|(offset, _)| *offset <= index)
+            .map_or("", |(_, sentence)| sentence.as_str())
+    }
+}
+
+/// Runs both tokenizer and rule set pairs over the documents and reports the differences.
+///
+/// # Errors
+/// If one of the rule sets is not compatible with its tokenizer, see [Rules::check_compatible].
+pub fn diff<S: AsRef<str>>(
+    old: (&Tokenizer, &Rules),
+    new: (&Tokenizer, &Rules),
+    documents: &[S],
+    options: &DiffOptions,
+) -> Result<DiffReport, Error> {
+    old.1.check_compatible(old.0)?;
+    new.1.check_compatible(new.0)?;
+
+    let mut report = DiffReport {
+        documents: documents.len(),
+        ..DiffReport::default()
+    };
+    let mut rules: BTreeMap<String, RuleDiff> = BTreeMap::new();
+    let mut tags: BTreeMap<(String, Option<Vec<Reading>>, Option<Vec<Reading>>), TagDiff> =
+        BTreeMap::new();
+
+    for (i, document) in documents.iter().enumerate() {
+        let text = document.as_ref();
+        let before = Output::new(old.0, old.1, text);
+        let after = Output::new(new.0, new.1, text);
+
+        report.suggestions_before += before.suggestions.len();
+        report.suggestions_after += after.suggestions.len();
+
+        let mut diffs = Vec::new();
+        let mut unmatched: Vec<_> = after.suggestions.iter().map(Some).collect();
+
+        for suggestion in &before.suggestions {
+            let matching = unmatched.iter_mut().find(|x| {
+                x.map_or(false, |x| {
+                    x.source == suggestion.source
+                        && x.start == suggestion.start
+                        && x.end == suggestion.end
+                })
+            });
+
+            match matching.and_then(Option::take) {
+                Some(other) => {
+                    if other.replacements != suggestion.replacements
+                        || other.message != suggestion.message
+                    {
+                        diffs.push((ChangeKind::Changed, Some(suggestion), Some(other)));
+                    }
+                }
+                None => diffs.push((ChangeKind::Removed, Some(suggestion), None)),
+            }
+        }
+
+        diffs.extend(
+            unmatched
+                .into_iter()
+                .flatten()
+                .map(|x| (ChangeKind::Added, None, Some(x))),
+        );
+
+        for (kind, suggestion_before, suggestion_after) in diffs {
+            let (source, sentence) = match (suggestion_before, suggestion_after) {
+                (Some(x), _) => (&x.source, before.sentence(x.start)),
+                (None, Some(x)) => (&x.source, after.sentence(x.start)),
+                (None, None) => unreachable!("a difference has at least one suggestion"),
+            };
+
+            let rule = rules.entry(source.clone()).or_insert_with(|| RuleDiff {
+                rule: source.clone(),
+                added: 0,
+                removed: 0,
+                changed: 0,
+                examples: Vec::new(),
+            });
+
+            match kind {
+                ChangeKind::Added => rule.added += 1,
+                ChangeKind::Removed => rule.removed += 1,
+                ChangeKind::Changed => rule.changed += 1,
+            }
+
+            if rule.examples.len() < options.max_examples {
+                rule.examples.push(SuggestionDiff {
+                    kind,
+                    document: i,
+                    sentence: sentence.to_owned(),
+                    before: suggestion_before.cloned(),
+                    after: suggestion_after.cloned(),
+                });
+            }
+        }
+
+        let spans: BTreeSet<_> = before.tokens.keys().chain(after.tokens.keys()).collect();
+
+        for span in spans {
+            let token_before = before.tokens.get(span);
+            let token_after = after.tokens.get(span);
+
+            if token_before == token_after {
+                continue;
+            }
+
+            let (text, sentence) = match (token_before, token_after) {
+                (Some((text, _)), _) => (text, before.sentence(span.0)),
+                (None, Some((text, _))) => (text, after.sentence(span.0)),
+                (None, None) => unreachable!("span is from one of the outputs"),
+            };
+            let readings_before = token_before.map(|(_, readings)| readings.clone());
+            let readings_after = token_after.map(|(_, readings)| readings.clone());
+
+            let diff = tags
+                .entry((
+                    text.clone(),
+                    readings_before.clone(),
+                    readings_after.clone(),
+                ))
+                .or_insert_with(|| TagDiff {
+                    text: text.clone(),
+                    before: readings_before,
+                    after: readings_after,
+                    count: 0,
+                    examples: Vec::new(),
+                });
+
+            diff.count += 1;
+            if diff.examples.len() < options.max_examples
+                && !diff.examples.iter().any(|x| x == sentence)
+            {
+                diff.examples.push(sentence.to_owned());
+            }
+        }
+    }
+
+    report.rules = rules.into_iter().map(|(_, x)| x).collect();
+    report.tags = tags.into_iter().map(|(_, x)| x).collect();
+    // the sort is stable so ties stay ordered by text and readings
+    report.tags.sort_by_key(|x| Reverse(x.count));
+
+    Ok(report)
+}
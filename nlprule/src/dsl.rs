@@ -0,0 +1,944 @@
+//! A compact format to define grammar rules in code or configuration files instead of LanguageTool XML.
+//!
+//! A [RuleSpec] implements [Deserialize] so rules can be kept in any format supported by serde, e. g. YAML or JSON.
+//! Specifications are compiled to [Rule]s at runtime against a [Tokenizer], no build directory is needed.
+//!
+//! # Format
+//!
+//! - Each token of the `pattern` is a [TokenPatternSpec]. String values of the form `/.../` are regular expressions
+//!   which have to match the full value, other strings are compared exactly.
+//! - Patterns and antipatterns can also be written in a compact syntax, either as one string or as a list which mixes
+//!   strings and token objects. See [parse_pattern] for the syntax.
+//! - `repeat` quantifies a token like in regular expressions: `?`, `*`, `+`, `{n}`, `{m,n}` or `{m,}`.
+//! - Tokens with `marker: true` are the part of the match which is replaced by the suggestions. They have to be consecutive.
+//!   If no token is marked, the whole match is replaced.
+//! - `message` and `suggestions` are templates. `\1` to `\9` insert the text matched by the token at this (1-based) position,
+//!   `\{name}` or `\{12}` insert the text of a named or numbered token and `\{name:upper}` converts the case of the text.
+//!   Conversions are `lower`, `upper`, `startlower` and `startupper`. `\\` is a literal backslash.
+//! - Examples mark the expected match with `<marker>...</marker>` and list the expected replacements in `correction`.
+//!   An example without a marker must not trigger the rule.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{dsl::RuleSpec, Rules, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+//!
+//! let spec: RuleSpec = serde_json::from_str(
+//!     r#"{
+//!         "id": "VERY_HAPPY",
+//!         "pattern": "[lemma=be] <very [pos=/JJ.*/ name=adjective]>",
+//!         "message": "Consider a stronger word than 'very \\{adjective}'.",
+//!         "suggestions": ["delighted"],
+//!         "examples": [
+//!             { "text": "The cat is <marker>very happy</marker>.", "correction": ["delighted"] },
+//!             { "text": "The cat is happy." }
+//!         ]
+//!     }"#,
+//! )
+//! .unwrap();
+//!
+//! let rules = Rules::from_rules(vec![spec.compile(&tokenizer)?], &tokenizer);
+//! assert_eq!(rules.correct("She is very happy.", &tokenizer), "She is delighted.");
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{
+    pattern::{PatternBuilder, TokenSpec},
+    rule::{
+        engine::{composition::GraphId, Engine, TokenEngine},
+        grammar::{Conversion, Match, Synthesizer, SynthesizerPart},
+        id::Category,
+        Example, Rule, RuleDetails,
+    },
+    types::*,
+    utils::{self, lazy::Lazy},
+    Error, Tokenizer,
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+fn default_category() -> String {
+    "CUSTOM".into()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// The specification of one token in a pattern.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TokenPatternSpec {
+    /// The text of the token.
+    pub text: Option<String>,
+    /// The lemma of some reading of the token.
+    pub lemma: Option<String>,
+    /// The part-of-speech tag of some reading of the token. Has to match in the same reading as the lemma.
+    pub pos: Option<String>,
+    /// A chunk of the token.
+    pub chunk: Option<String>,
+    /// Whether the token must or must not be preceded by whitespace.
+    pub space_before: Option<bool>,
+    /// Whether the text and lemma are compared case-sensitively.
+    pub case_sensitive: bool,
+    /// Inverts the specification.
+    pub negate: bool,
+    /// The token has to match one of these specifications.
+    pub any_of: Vec<TokenPatternSpec>,
+    /// The token must not match any of these specifications.
+    pub except: Vec<TokenPatternSpec>,
+    /// How often the token is repeated, e. g. `?` or `{1,3}`. Only allowed in patterns, not in `any_of` or `except`.
+    pub repeat: Option<String>,
+    /// Whether the token is part of the marker. Only allowed in patterns.
+    pub marker: bool,
+    /// A name to refer to the token in templates. Only allowed in patterns.
+    pub name: Option<String>,
+}
+
+/// A condition on a string: `/.../` is a regex, anything else an exact string.
+fn as_regex(value: &str) -> Option<&str> {
+    if value.len() >= 2 {
+        value.strip_prefix('/').and_then(|x| x.strip_suffix('/'))
+    } else {
+        None
+    }
+}
+
+fn split_once<'a>(text: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    text.find(separator)
+        .map(|i| (&text[..i], &text[i + separator.len()..]))
+}
+
+/// Parses a quantifier to the minimum and maximum number of repetitions.
+fn parse_repeat(repeat: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("invalid repetition `{}`", repeat);
+
+    Ok(match repeat {
+        "?" => (0, 1),
+        "*" => (0, usize::MAX),
+        "+" => (1, usize::MAX),
+        _ => {
+            let inner = repeat
+                .strip_prefix('{')
+                .and_then(|x| x.strip_suffix('}'))
+                .ok_or_else(invalid)?;
+            let parse = |x: &str| x.trim().parse::<usize>().map_err(|_| invalid());
+
+            match split_once(inner, ",") {
+                None => {
+                    let n = parse(inner)?;
+                    (n, n)
+                }
+                Some((min, max)) if max.trim().is_empty() => (parse(min)?, usize::MAX),
+                Some((min, max)) => (parse(min)?, parse(max)?),
+            }
+        }
+    })
+}
+
+/// Reads a regex delimited by slashes starting at `chars[*i]`. Returns the regex including the delimiters.
+fn read_regex(chars: &[char], i: &mut usize) -> Result<String, String> {
+    let start = *i;
+    *i += 1;
+
+    while *i < chars.len() {
+        match chars[*i] {
+            '\\' => *i += 2,
+            '/' => {
+                *i += 1;
+                return Ok(chars[start..*i].iter().collect());
+            }
+            _ => *i += 1,
+        }
+    }
+
+    Err(format!(
+        "regex `{}` is not closed",
+        chars[start..].iter().collect::<String>()
+    ))
+}
+
+/// Reads characters starting at `chars[*i]` until whitespace or one of the `stop` characters.
+fn read_until(chars: &[char], i: &mut usize, stop: &[char]) -> String {
+    let start = *i;
+    while *i < chars.len() && !chars[*i].is_whitespace() && !stop.contains(&chars[*i]) {
+        *i += 1;
+    }
+
+    chars[start..*i].iter().collect()
+}
+
+/// Reads the constraints of a token in brackets, `chars[*i]` is the character after the opening bracket.
+fn read_constraints(
+    chars: &[char],
+    i: &mut usize,
+    token: &mut TokenPatternSpec,
+) -> Result<(), String> {
+    loop {
+        while *i < chars.len() && chars[*i].is_whitespace() {
+            *i += 1;
+        }
+
+        match chars.get(*i) {
+            None => return Err("`[` is not closed".into()),
+            Some(']') => {
+                *i += 1;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let key = read_until(chars, i, &['=', ']']);
+        if key == "case" {
+            token.case_sensitive = true;
+            continue;
+        }
+
+        if chars.get(*i) != Some(&'=') {
+            return Err(format!("expected `=` after `{}`", key));
+        }
+        *i += 1;
+
+        let value = if chars.get(*i) == Some(&'/') {
+            read_regex(chars, i)?
+        } else {
+            read_until(chars, i, &[']'])
+        };
+        if value.is_empty() {
+            return Err(format!("`{}` must have a value", key));
+        }
+
+        let field = match key.as_str() {
+            "text" => &mut token.text,
+            "lemma" => &mut token.lemma,
+            "pos" => &mut token.pos,
+            "chunk" => &mut token.chunk,
+            "name" => &mut token.name,
+            "space" => {
+                token.space_before = Some(match value.as_str() {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(format!("`space` must be `yes` or `no`, not `{}`", value)),
+                });
+                continue;
+            }
+            _ => return Err(format!("unknown constraint `{}`", key)),
+        };
+
+        if field.replace(value).is_some() {
+            return Err(format!("duplicate constraint `{}`", key));
+        }
+    }
+}
+
+/// Parses tokens written in the compact pattern syntax. Tokens are separated by whitespace:
+///
+/// - `word` matches the text of a token, `/regex/` matches the text with a regex.
+/// - `[lemma=be pos=/VB.*/]` combines constraints on `text`, `lemma`, `pos` and `chunk`, each an exact string
+///   or a `/regex/`. `name=...` names the token for templates, `space=yes` or `space=no` requires or forbids whitespace
+///   before the token and `case` compares text and lemma case-sensitively. `[]` matches any token.
+/// - `!` before a token negates it.
+/// - `?`, `*`, `+` or `{m,n}` directly after a token quantifies it like [TokenPatternSpec::repeat].
+/// - `<` before the first and `>` after the last marked token enclose the marker.
+///
+/// Words can not start with `[`, `/`, `!` or `<` and end at a quantifier or `>`, use e. g. `[text=?]` for such tokens.
+///
+/// ```
+/// use nlprule::dsl::parse_pattern;
+///
+/// let tokens = parse_pattern("[lemma=be] <very [pos=/JJ.*/ name=adjective]>")?;
+///
+/// assert_eq!(tokens.len(), 3);
+/// assert_eq!(tokens[0].lemma.as_deref(), Some("be"));
+/// assert!(tokens[1].marker && tokens[2].marker);
+/// assert_eq!(tokens[2].name.as_deref(), Some("adjective"));
+/// # Ok::<(), String>(())
+/// ```
+///
+/// # Errors
+/// If the pattern is empty or does not follow the syntax.
+pub fn parse_pattern(pattern: &str) -> Result<Vec<TokenPatternSpec>, String> {
+    let chars: Vec<_> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut marker: Option<bool> = None;
+
+    loop {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i == chars.len() {
+            break;
+        }
+
+        let mut token = TokenPatternSpec::default();
+
+        if chars[i] == '<' {
+            if marker.is_some() {
+                return Err("a pattern can only have one marker".into());
+            }
+            marker = Some(true);
+            i += 1;
+        }
+        if chars.get(i) == Some(&'!') {
+            token.negate = true;
+            i += 1;
+        }
+
+        match chars.get(i) {
+            Some('[') => {
+                i += 1;
+                read_constraints(&chars, &mut i, &mut token)?;
+            }
+            Some('/') => token.text = Some(read_regex(&chars, &mut i)?),
+            _ => {
+                let word = read_until(&chars, &mut i, &['?', '*', '+', '{', '>']);
+                if word.is_empty() {
+                    return Err(format!("expected a token in `{}`", pattern));
+                }
+                token.text = Some(word);
+            }
+        }
+
+        match chars.get(i) {
+            Some(c @ '?') | Some(c @ '*') | Some(c @ '+') => {
+                token.repeat = Some(c.to_string());
+                i += 1;
+            }
+            Some('{') => {
+                let start = i;
+                while i < chars.len() && chars[i] != '}' {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(format!("repetition in `{}` is not closed", pattern));
+                }
+                i += 1;
+                token.repeat = Some(chars[start..i].iter().collect());
+            }
+            _ => {}
+        }
+
+        token.marker = marker == Some(true);
+        if chars.get(i) == Some(&'>') {
+            if marker != Some(true) {
+                return Err("`>` without a preceding `<`".into());
+            }
+            marker = Some(false);
+            i += 1;
+        }
+
+        if i < chars.len() && !chars[i].is_whitespace() {
+            return Err(format!(
+                "unexpected `{}` in `{}`, tokens must be separated by whitespace",
+                chars[i], pattern
+            ));
+        }
+
+        tokens.push(token);
+    }
+
+    if marker == Some(true) {
+        return Err("marker is not closed".into());
+    }
+    if tokens.is_empty() {
+        return Err("pattern must not be empty".into());
+    }
+
+    Ok(tokens)
+}
+
+/// A pattern token in either syntax.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenRepr {
+    Compact(String),
+    Full(TokenPatternSpec),
+}
+
+/// A pattern as a string in the compact syntax or as a list of tokens in either syntax.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternRepr {
+    Compact(String),
+    Tokens(Vec<TokenRepr>),
+}
+
+impl PatternRepr {
+    fn into_tokens(self) -> Result<Vec<TokenPatternSpec>, String> {
+        match self {
+            PatternRepr::Compact(pattern) => parse_pattern(&pattern),
+            PatternRepr::Tokens(tokens) => {
+                let mut output = Vec::new();
+                for token in tokens {
+                    match token {
+                        TokenRepr::Compact(pattern) => output.extend(parse_pattern(&pattern)?),
+                        TokenRepr::Full(token) => output.push(token),
+                    }
+                }
+
+                Ok(output)
+            }
+        }
+    }
+}
+
+fn deserialize_pattern<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TokenPatternSpec>, D::Error> {
+    PatternRepr::deserialize(deserializer)?
+        .into_tokens()
+        .map_err(de::Error::custom)
+}
+
+fn deserialize_patterns<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Vec<TokenPatternSpec>>, D::Error> {
+    Vec::<PatternRepr>::deserialize(deserializer)?
+        .into_iter()
+        .map(PatternRepr::into_tokens)
+        .collect::<Result<_, _>>()
+        .map_err(de::Error::custom)
+}
+
+impl TokenPatternSpec {
+    fn to_token_spec(&self, nested: bool) -> Result<TokenSpec, String> {
+        if nested && (self.repeat.is_some() || self.marker || self.name.is_some()) {
+            return Err("`repeat`, `marker` and `name` are only allowed on pattern tokens".into());
+        }
+
+        let mut spec = TokenSpec::any_of(
+            self.any_of
+                .iter()
+                .map(|x| x.to_token_spec(true))
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+        .case_sensitive(self.case_sensitive);
+
+        if let Some(text) = &self.text {
+            spec = match as_regex(text) {
+                Some(regex) => spec.text_regex(regex),
+                None => spec.text(text.as_str()),
+            };
+        }
+        if let Some(lemma) = &self.lemma {
+            spec = match as_regex(lemma) {
+                Some(regex) => spec.lemma_regex(regex),
+                None => spec.lemma(lemma.as_str()),
+            };
+        }
+        if let Some(pos) = &self.pos {
+            spec = match as_regex(pos) {
+                Some(regex) => spec.pos_regex(regex),
+                None => spec.pos(pos.as_str()),
+            };
+        }
+        if let Some(chunk) = &self.chunk {
+            spec = match as_regex(chunk) {
+                Some(regex) => spec.chunk_regex(regex),
+                None => spec.chunk(chunk.as_str()),
+            };
+        }
+        if let Some(space_before) = self.space_before {
+            spec = spec.space_before(space_before);
+        }
+
+        for exception in &self.except {
+            spec = spec.except(exception.to_token_spec(true)?);
+        }
+
+        Ok(if self.negate { spec.negate() } else { spec })
+    }
+}
+
+/// An example of a [RuleSpec].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExampleSpec {
+    /// The text of the example. The expected match is marked with `<marker>...</marker>`.
+    pub text: String,
+    /// The expected replacements for the marked text.
+    #[serde(default)]
+    pub correction: Vec<String>,
+}
+
+impl ExampleSpec {
+    fn to_example(&self, source: &str) -> Result<Example, String> {
+        const START: &str = "<marker>";
+        const END: &str = "</marker>";
+
+        let (before, rest) = match split_once(&self.text, START) {
+            Some(x) => x,
+            None => {
+                if self.text.contains(END) || !self.correction.is_empty() {
+                    return Err(format!("example `{}` must have a marker", self.text));
+                }
+
+                return Ok(Example {
+                    text: self.text.clone(),
+                    suggestion: None,
+                });
+            }
+        };
+        let (marked, after) = split_once(rest, END)
+            .ok_or_else(|| format!("marker in example `{}` is not closed", self.text))?;
+
+        if after.contains(START) || before.contains(END) || marked.contains(START) {
+            return Err(format!("example `{}` must have one marker", self.text));
+        }
+        if self.correction.is_empty() {
+            return Err(format!(
+                "example `{}` with a marker must have a correction",
+                self.text
+            ));
+        }
+
+        let start = before.chars().count();
+        let replacements = if start == 0 {
+            // title case if at start, like the examples of LanguageTool rules
+            self.correction
+                .iter()
+                .map(|x| utils::apply_to_first(x, |c| c.to_uppercase().collect()))
+                .collect()
+        } else {
+            self.correction.clone()
+        };
+
+        Ok(Example {
+            text: format!("{}{}{}", before, marked, after),
+            suggestion: Some(Suggestion {
+                source: source.to_string(),
+                message: String::new(),
+                start,
+                end: start + marked.chars().count(),
+                replacements,
+            }),
+        })
+    }
+}
+
+/// Parses a template to the parts of a synthesizer. `names` are the names of the pattern tokens.
+fn parse_template(
+    template: &str,
+    names: &[Option<String>],
+) -> Result<Vec<SynthesizerPart>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars();
+
+    let resolve = |reference: &str| -> Result<GraphId, String> {
+        let id = match reference.parse::<usize>() {
+            Ok(number) => number,
+            Err(_) => {
+                names
+                    .iter()
+                    .position(|x| x.as_deref() == Some(reference))
+                    .ok_or_else(|| format!("unknown token name `{}`", reference))?
+                    + 1
+            }
+        };
+
+        if id == 0 || id > names.len() {
+            return Err(format!(
+                "reference \\{} to a token which does not exist",
+                reference
+            ));
+        }
+
+        Ok(GraphId(id))
+    };
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+
+        let (id, conversion) = match chars.next() {
+            Some('\\') => {
+                text.push('\\');
+                continue;
+            }
+            Some(digit) if digit.is_ascii_digit() => {
+                (resolve(&digit.to_string())?, Conversion::Nop)
+            }
+            Some('{') => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => return Err(format!("unclosed reference in `{}`", template)),
+                    }
+                }
+
+                let (reference, conversion) = match split_once(&inner, ":") {
+                    Some((reference, conversion)) => (
+                        reference,
+                        match conversion {
+                            "lower" => Conversion::AllLower,
+                            "upper" => Conversion::AllUpper,
+                            "startlower" => Conversion::StartLower,
+                            "startupper" => Conversion::StartUpper,
+                            _ => return Err(format!("unknown case conversion `{}`", conversion)),
+                        },
+                    ),
+                    None => (inner.as_str(), Conversion::Nop),
+                };
+
+                (resolve(reference)?, conversion)
+            }
+            _ => return Err(format!("invalid escape in `{}`", template)),
+        };
+
+        if !text.is_empty() {
+            parts.push(SynthesizerPart::Text(std::mem::take(&mut text)));
+        }
+        parts.push(SynthesizerPart::Match(Box::new(Match {
+            id,
+            conversion,
+            pos_replacer: None,
+            regex_replacer: None,
+        })));
+    }
+
+    if !text.is_empty() {
+        parts.push(SynthesizerPart::Text(text));
+    }
+
+    Ok(parts)
+}
+
+/// The specification of a grammar rule. See the [module documentation][self] for the format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSpec {
+    /// The identifier of the rule. The rule is the only rule of the group with this ID.
+    pub id: String,
+    /// The category of the rule. Defaults to `CUSTOM`.
+    #[serde(default = "default_category")]
+    pub category: String,
+    /// A human-readable name. Defaults to the ID.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Whether the rule is enabled. Defaults to `true`.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// The tokens to match.
+    #[serde(deserialize_with = "deserialize_pattern")]
+    pub pattern: Vec<TokenPatternSpec>,
+    /// Patterns which prevent the rule from matching text they overlap with.
+    #[serde(default, deserialize_with = "deserialize_patterns")]
+    pub antipatterns: Vec<Vec<TokenPatternSpec>>,
+    /// The template of the message.
+    pub message: String,
+    /// The templates of the replacements.
+    pub suggestions: Vec<String>,
+    /// A short message.
+    #[serde(default)]
+    pub short: Option<String>,
+    /// A URL with more information.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Examples to [test][Rule::test] the rule with.
+    #[serde(default)]
+    pub examples: Vec<ExampleSpec>,
+}
+
+/// Creates the pattern builder for tokens of a pattern.
+fn pattern_builder(tokens: &[TokenPatternSpec]) -> Result<PatternBuilder, String> {
+    let mut builder = PatternBuilder::default();
+
+    for token in tokens {
+        let (min, max) = match &token.repeat {
+            Some(repeat) => parse_repeat(repeat)?,
+            None => (1, 1),
+        };
+
+        builder = builder.repeat(token.to_token_spec(false)?, min, max);
+    }
+
+    Ok(builder)
+}
+
+impl RuleSpec {
+    /// Gets the marked range of the pattern.
+    fn marker(&self) -> Result<(GraphId, GraphId), String> {
+        let marked: Vec<_> = (1..=self.pattern.len())
+            .filter(|i| self.pattern[i - 1].marker)
+            .collect();
+
+        match (marked.first(), marked.last()) {
+            (Some(start), Some(end)) => {
+                if end - start + 1 != marked.len() {
+                    return Err("marked tokens must be consecutive".into());
+                }
+
+                Ok((GraphId(*start), GraphId(*end)))
+            }
+            _ => Ok((GraphId(1), GraphId(self.pattern.len()))),
+        }
+    }
+
+    fn compile_inner(&self, tokenizer: &Tokenizer) -> Result<Rule, String> {
+        let tagger = tokenizer.tagger();
+        let names: Vec<_> = self.pattern.iter().map(|x| x.name.clone()).collect();
+
+        for (i, name) in names.iter().enumerate() {
+            if let Some(name) = name {
+                if name.parse::<usize>().is_ok() || names[..i].contains(&Some(name.clone())) {
+                    return Err(format!("invalid or duplicate token name `{}`", name));
+                }
+            }
+        }
+
+        let composition = pattern_builder(&self.pattern)?
+            .composition(tagger)
+            .map_err(|x| x.to_string())?;
+        let antipatterns = self
+            .antipatterns
+            .iter()
+            .map(|x| {
+                pattern_builder(x)?
+                    .composition(tagger)
+                    .map_err(|x| x.to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (start, end) = self.marker()?;
+        let id = Category::new(self.category.as_str())
+            .join(self.id.as_str())
+            .join(0);
+
+        if self.suggestions.is_empty() {
+            return Err("rule must have at least one suggestion".into());
+        }
+        let synthesizer = |template: &str| -> Result<Synthesizer, String> {
+            Ok(Synthesizer {
+                parts: parse_template(template, &names)?,
+                use_titlecase_adjust: true,
+            })
+        };
+
+        let examples = self
+            .examples
+            .iter()
+            .map(|x| x.to_example(&id.to_string()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Rule {
+            id,
+            engine: Engine::Token(TokenEngine {
+                composition,
+                antipatterns,
+            }),
+            start,
+            end,
+            unification: None,
+            enabled: self.enabled,
+            details: Lazy::new(RuleDetails {
+                examples,
+                suggesters: self
+                    .suggestions
+                    .iter()
+                    .map(|x| synthesizer(x))
+                    .collect::<Result<_, _>>()?,
+                message: synthesizer(&self.message)?,
+                url: self.url.clone(),
+                short: self.short.clone(),
+                name: self.name.clone().unwrap_or_else(|| self.id.clone()),
+                category_name: self.category.clone(),
+                category_type: None,
+            }),
+        })
+    }
+
+    /// Compiles the specification to a rule for the tokenizer.
+    ///
+    /// # Errors
+    /// If the specification is invalid, e. g. contains an invalid regex, an unknown part-of-speech tag or a reference to
+    /// a token which does not exist.
+    pub fn compile(&self, tokenizer: &Tokenizer) -> Result<Rule, Error> {
+        self.compile_inner(tokenizer)
+            .map_err(|reason| Error::InvalidRule {
+                id: self.id.clone(),
+                reason,
+            })
+    }
+}
+
+/// Compiles multiple specifications to a rule set for the tokenizer. See [RuleSpec::compile].
+pub fn compile_rules(specs: &[RuleSpec], tokenizer: &Tokenizer) -> Result<crate::Rules, Error> {
+    let rules = specs
+        .iter()
+        .map(|x| x.compile(tokenizer))
+        .collect::<Result<_, _>>()?;
+
+    Ok(crate::Rules::from_rules(rules, tokenizer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(parts: &[SynthesizerPart]) -> Vec<String> {
+        parts
+            .iter()
+            .map(|x| match x {
+                SynthesizerPart::Text(text) => text.clone(),
+                SynthesizerPart::Match(m) => format!("{}:{:?}", m.id.0, m.conversion),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repetitions_are_parsed() {
+        assert_eq!(parse_repeat("?"), Ok((0, 1)));
+        assert_eq!(parse_repeat("+"), Ok((1, usize::MAX)));
+        assert_eq!(parse_repeat("{2}"), Ok((2, 2)));
+        assert_eq!(parse_repeat("{1, 3}"), Ok((1, 3)));
+        assert_eq!(parse_repeat("{2,}"), Ok((2, usize::MAX)));
+        assert!(parse_repeat("{a}").is_err());
+        assert!(parse_repeat("2").is_err());
+    }
+
+    #[test]
+    fn templates_are_parsed() {
+        let names = vec![None, Some("adj".to_string())];
+
+        assert_eq!(
+            texts(&parse_template(r"very \2 \{adj:upper}\\", &names).unwrap()),
+            vec!["very ", "2:Nop", " ", "2:AllUpper", "\\"]
+        );
+        assert!(parse_template(r"\3", &names).is_err());
+        assert!(parse_template(r"\{noun}", &names).is_err());
+        assert!(parse_template(r"\{adj:title}", &names).is_err());
+        assert!(parse_template(r"\{adj", &names).is_err());
+        assert!(parse_template(r"\x", &names).is_err());
+    }
+
+    #[test]
+    fn examples_are_parsed() {
+        let example = ExampleSpec {
+            text: "<marker>Teh</marker> cat.".into(),
+            correction: vec!["the".into()],
+        }
+        .to_example("ID")
+        .unwrap();
+        let suggestion = example.suggestion().unwrap();
+
+        assert_eq!(example.text(), "Teh cat.");
+        assert_eq!((suggestion.start, suggestion.end), (0, 3));
+        assert_eq!(suggestion.replacements, vec!["The".to_string()]);
+
+        let invalid = |text: &str, correction: &[&str]| {
+            ExampleSpec {
+                text: text.into(),
+                correction: correction.iter().map(|x| x.to_string()).collect(),
+            }
+            .to_example("ID")
+            .is_err()
+        };
+        assert!(invalid("No marker.", &["x"]));
+        assert!(invalid("<marker>a</marker> <marker>b</marker>", &["x"]));
+        assert!(invalid("<marker>Open", &["x"]));
+        assert!(invalid("<marker>a</marker>", &[]));
+    }
+
+    #[test]
+    fn compact_patterns_are_parsed() {
+        let tokens =
+            parse_pattern(r"!the [lemma=be pos=/VB[DZ]/ case]{1,2} <[] /a\/b/? [text=? space=no]>")
+                .unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                TokenPatternSpec {
+                    text: Some("the".into()),
+                    negate: true,
+                    ..TokenPatternSpec::default()
+                },
+                TokenPatternSpec {
+                    lemma: Some("be".into()),
+                    pos: Some("/VB[DZ]/".into()),
+                    case_sensitive: true,
+                    repeat: Some("{1,2}".into()),
+                    ..TokenPatternSpec::default()
+                },
+                TokenPatternSpec {
+                    marker: true,
+                    ..TokenPatternSpec::default()
+                },
+                TokenPatternSpec {
+                    text: Some(r"/a\/b/".into()),
+                    repeat: Some("?".into()),
+                    marker: true,
+                    ..TokenPatternSpec::default()
+                },
+                TokenPatternSpec {
+                    text: Some("?".into()),
+                    space_before: Some(false),
+                    marker: true,
+                    ..TokenPatternSpec::default()
+                },
+            ]
+        );
+
+        for invalid in &[
+            "",
+            "a <b> <c>",
+            "<a b",
+            "a>",
+            "[lemma=be",
+            "[lemma]",
+            "[lemma=a lemma=b]",
+            "[tag=NN]",
+            "/a",
+            "a{1,",
+            "a?b",
+            "?",
+        ] {
+            assert!(parse_pattern(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn patterns_can_mix_syntaxes() {
+        let spec = serde_json::from_str::<RuleSpec>(
+            r#"{
+                "id": "ID",
+                "pattern": ["a <b", { "lemma": "c", "marker": true }, "d>"],
+                "antipatterns": ["a b", [{ "text": "a" }, "c"]],
+                "message": "",
+                "suggestions": []
+            }"#,
+        );
+        // markers can not span strings
+        assert!(spec.is_err());
+
+        let spec: RuleSpec = serde_json::from_str(
+            r#"{
+                "id": "ID",
+                "pattern": ["a", { "lemma": "c", "marker": true }, "<d>"],
+                "antipatterns": ["a b", [{ "text": "a" }, "c"]],
+                "message": "",
+                "suggestions": []
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(spec.pattern.len(), 3);
+        assert!(spec.pattern[1].marker && spec.pattern[2].marker);
+        assert_eq!(spec.antipatterns.len(), 2);
+        assert_eq!(spec.antipatterns[1][1].text.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn regexes_are_delimited_by_slashes() {
+        assert_eq!(as_regex("/JJ.*/"), Some("JJ.*"));
+        assert_eq!(as_regex("/"), None);
+        assert_eq!(as_regex("JJ"), None);
+    }
+}
@@ -0,0 +1,516 @@
+//! Evaluation of rules against gold corrections in the M2 format used by the CoNLL-2014 and BEA-2019 shared tasks
+//! on grammatical error correction.
+//!
+//! M2 sentences are tokenized. By default, the tokens are joined into natural text (no space before punctuation
+//! and contractions, see [EvaluationOptions::detokenize]) before passing them to [Rules::suggest].
+//!
+//! A suggestion is a true positive if applying it to the sentence yields the same text as applying one of the
+//! gold edits, ignoring whitespace. This makes the comparison independent of the span a rule chooses, e.g. inserting
+//! a missing article by replacing the following word still counts as the gold insertion. Each gold edit is matched
+//! at most once. If a sentence has multiple annotators, the annotator with the most true positives (and the fewest
+//! false negatives among those) is used, as in the M2 scorer.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{evaluate, Rules, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+//! let rules = Rules::new("path/to/en_rules.bin")?;
+//!
+//! let sentences = evaluate::parse_m2(
+//!     "S She see him .\nA 1 2|||R:VERB:SVA|||sees|||REQUIRED|||-NONE-|||0\n",
+//! )?;
+//! let evaluation = evaluate::evaluate(
+//!     &sentences,
+//!     &rules,
+//!     &tokenizer,
+//!     &evaluate::EvaluationOptions::default(),
+//! )?;
+//!
+//! println!("F0.5: {:.4}", evaluation.overall.f05());
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{types::*, Error, Rules, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A gold edit of an M2 annotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edit {
+    /// The index of the first token (inclusive).
+    pub start: usize,
+    /// The index of the last token (exclusive). Equal to `start` for insertions.
+    pub end: usize,
+    /// The error type e.g. `R:PREP`.
+    pub kind: String,
+    /// The alternative corrections. An empty correction deletes the tokens.
+    pub corrections: Vec<String>,
+    /// The ID of the annotator.
+    pub annotator: usize,
+}
+
+/// A tokenized sentence with its gold edits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sentence {
+    /// The tokens of the sentence.
+    pub tokens: Vec<String>,
+    /// The gold edits of all annotators.
+    pub edits: Vec<Edit>,
+    /// The IDs of the annotators, including those who did not make any edits.
+    pub annotators: Vec<usize>,
+}
+
+/// Parses sentences and gold edits in M2 format.
+///
+/// # Errors
+/// If a line does not conform to the M2 format.
+pub fn parse_m2(text: &str) -> Result<Vec<Sentence>, Error> {
+    let mut sentences = Vec::new();
+    let mut current: Option<Sentence> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let error = |reason: &str| Error::InvalidM2 {
+            line: i + 1,
+            reason: reason.to_owned(),
+        };
+
+        if line.trim().is_empty() {
+            sentences.extend(current.take());
+        } else if let Some(source) = line.strip_prefix("S ") {
+            sentences.extend(current.take());
+            current = Some(Sentence {
+                tokens: source.split_whitespace().map(|x| x.to_owned()).collect(),
+                edits: Vec::new(),
+                annotators: Vec::new(),
+            });
+        } else if let Some(annotation) = line.strip_prefix("A ") {
+            let sentence = current
+                .as_mut()
+                .ok_or_else(|| error("annotation without a source sentence"))?;
+
+            let fields: Vec<_> = annotation.split("|||").collect();
+            if fields.len() != 6 {
+                return Err(error("expected 6 fields separated by `|||`"));
+            }
+
+            let mut span = fields[0].split_whitespace().map(|x| x.parse::<isize>());
+            let (start, end) = match (span.next(), span.next(), span.next()) {
+                (Some(Ok(start)), Some(Ok(end)), None) => (start, end),
+                _ => return Err(error("expected a token span")),
+            };
+            let annotator = fields[5]
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| error("expected an annotator ID"))?;
+
+            if !sentence.annotators.contains(&annotator) {
+                sentence.annotators.push(annotator);
+            }
+
+            // the annotator has seen the sentence but did not make any edits
+            if fields[1] == "noop" || start < 0 {
+                continue;
+            }
+
+            if start > end || end as usize > sentence.tokens.len() {
+                return Err(error("token span out of bounds"));
+            }
+
+            sentence.edits.push(Edit {
+                start: start as usize,
+                end: end as usize,
+                kind: fields[1].to_owned(),
+                corrections: fields[2]
+                    .split("||")
+                    .map(|x| match x.trim() {
+                        "-NONE-" => String::new(),
+                        x => x.to_owned(),
+                    })
+                    .collect(),
+                annotator,
+            });
+        } else {
+            return Err(error("expected a line starting with `S ` or `A `"));
+        }
+    }
+
+    sentences.extend(current);
+    Ok(sentences)
+}
+
+/// Options for [evaluate].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationOptions {
+    /// The number of replacements of each suggestion to compare with the gold edits.
+    /// A suggestion is a true positive if any of them matches.
+    pub replacements: usize,
+    /// Whether to join the tokens into natural text. If false, the tokens are joined with single spaces,
+    /// which makes rules about whitespace around punctuation fire on most sentences.
+    pub detokenize: bool,
+}
+
+impl Default for EvaluationOptions {
+    fn default() -> Self {
+        EvaluationOptions {
+            replacements: 1,
+            detokenize: true,
+        }
+    }
+}
+
+/// True positive, false positive and false negative counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scores {
+    /// The number of suggestions matching a gold edit.
+    pub true_positives: usize,
+    /// The number of suggestions not matching any gold edit.
+    pub false_positives: usize,
+    /// The number of gold edits not matched by any suggestion.
+    pub false_negatives: usize,
+}
+
+impl Scores {
+    /// The precision. 1 if there are no suggestions.
+    pub fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// The recall. 1 if there are no gold edits.
+    pub fn recall(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    /// The F-score weighting recall `beta` times as much as precision.
+    pub fn f_score(&self, beta: f64) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        let beta2 = beta * beta;
+
+        if precision + recall == 0. {
+            0.
+        } else {
+            (1. + beta2) * precision * recall / (beta2 * precision + recall)
+        }
+    }
+
+    /// The F0.5 score, weighting precision twice as much as recall.
+    pub fn f05(&self) -> f64 {
+        self.f_score(0.5)
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        1.
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// The result of evaluating rules on an M2 corpus.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Evaluation {
+    /// The number of sentences.
+    pub sentences: usize,
+    /// The scores of all suggestions.
+    pub overall: Scores,
+    /// The scores of each rule which made at least one suggestion by rule ID.
+    /// The false negatives are all gold edits the rule did not match, so the recall of a rule is the share of all
+    /// gold edits it corrects.
+    pub rules: BTreeMap<String, Scores>,
+    /// The scores of each error type by M2 error type. Since false positives do not have an error type,
+    /// only the true positives and false negatives are counted.
+    pub error_types: BTreeMap<String, Scores>,
+}
+
+/// Gets the text of the sentence and the char span of each token in the text.
+fn layout(tokens: &[String], detokenize: bool) -> (String, Vec<(usize, usize)>) {
+    const NO_SPACE_BEFORE: &[&str] = &[
+        ".", ",", ";", ":", "!", "?", ")", "]", "}", "%", "n't", "'s", "'re", "'ve", "'ll", "'d",
+        "'m",
+    ];
+    const NO_SPACE_AFTER: &[&str] = &["(", "[", "{", "$"];
+
+    let mut text = String::new();
+    let mut spans = Vec::new();
+    let mut length = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let space = i > 0
+            && !(detokenize
+                && (NO_SPACE_BEFORE.contains(&token.to_lowercase().as_str())
+                    || NO_SPACE_AFTER.contains(&tokens[i - 1].as_str())));
+
+        if space {
+            text.push(' ');
+            length += 1;
+        }
+
+        let token_length = token.chars().count();
+        spans.push((length, length + token_length));
+        text.push_str(token);
+        length += token_length;
+    }
+
+    (text, spans)
+}
+
+/// Replaces the chars in the span and removes all whitespace.
+fn corrected(text: &str, (start, end): (usize, usize), replacement: &str) -> String {
+    text.chars()
+        .take(start)
+        .chain(replacement.chars())
+        .chain(text.chars().skip(end))
+        .filter(|x| !x.is_whitespace())
+        .collect()
+}
+
+/// Matches the suggestions with the edits. Returns the index of the matched edit for each suggestion.
+fn align(
+    text: &str,
+    spans: &[(usize, usize)],
+    suggestions: &[Suggestion],
+    edits: &[&Edit],
+    replacements: usize,
+) -> Vec<Option<usize>> {
+    let targets: Vec<Vec<String>> = edits
+        .iter()
+        .map(|edit| {
+            let span = if edit.start < edit.end {
+                (spans[edit.start].0, spans[edit.end - 1].1)
+            } else {
+                let position = spans
+                    .get(edit.start)
+                    .map_or_else(|| text.chars().count(), |x| x.0);
+                (position, position)
+            };
+
+            edit.corrections
+                .iter()
+                .map(|correction| corrected(text, span, correction))
+                .collect()
+        })
+        .collect();
+    let mut matched = vec![false; edits.len()];
+
+    suggestions
+        .iter()
+        .map(|suggestion| {
+            let outputs: Vec<_> = suggestion
+                .replacements
+                .iter()
+                .take(replacements)
+                .map(|replacement| corrected(text, (suggestion.start, suggestion.end), replacement))
+                .collect();
+
+            let index = targets.iter().enumerate().position(|(i, target)| {
+                !matched[i] && target.iter().any(|x| outputs.contains(x))
+            })?;
+            matched[index] = true;
+            Some(index)
+        })
+        .collect()
+}
+
+/// Runs the rules on the sentences and compares their suggestions with the gold edits.
+///
+/// # Errors
+/// If the rules are not compatible with the tokenizer, see [Rules::check_compatible].
+pub fn evaluate(
+    sentences: &[Sentence],
+    rules: &Rules,
+    tokenizer: &Tokenizer,
+    options: &EvaluationOptions,
+) -> Result<Evaluation, Error> {
+    rules.check_compatible(tokenizer)?;
+
+    let layouts: Vec<_> = sentences
+        .iter()
+        .map(|x| layout(&x.tokens, options.detokenize))
+        .collect();
+    let texts: Vec<_> = layouts.iter().map(|(text, _)| text.as_str()).collect();
+    let suggestions = rules.suggest_batch(&texts, tokenizer);
+
+    let mut evaluation = Evaluation {
+        sentences: sentences.len(),
+        ..Evaluation::default()
+    };
+    let mut n_edits = 0;
+
+    for ((sentence, (text, spans)), suggestions) in sentences.iter().zip(&layouts).zip(&suggestions)
+    {
+        let annotators = if sentence.annotators.is_empty() {
+            vec![0]
+        } else {
+            sentence.annotators.clone()
+        };
+
+        let (edits, matches) = annotators
+            .into_iter()
+            .map(|annotator| {
+                let edits: Vec<_> = sentence
+                    .edits
+                    .iter()
+                    .filter(|x| x.annotator == annotator)
+                    .collect();
+                let matches = align(text, spans, suggestions, &edits, options.replacements);
+                (edits, matches)
+            })
+            .min_by_key(|(edits, matches)| {
+                let true_positives = matches.iter().flatten().count();
+                (
+                    std::cmp::Reverse(true_positives),
+                    edits.len() - true_positives,
+                )
+            })
+            .expect("sentence has at least one annotator");
+
+        for (suggestion, index) in suggestions.iter().zip(&matches) {
+            let scores = evaluation
+                .rules
+                .entry(suggestion.source.clone())
+                .or_default();
+
+            if let Some(index) = index {
+                evaluation.overall.true_positives += 1;
+                scores.true_positives += 1;
+                evaluation
+                    .error_types
+                    .entry(edits[*index].kind.clone())
+                    .or_default()
+                    .true_positives += 1;
+            } else {
+                evaluation.overall.false_positives += 1;
+                scores.false_positives += 1;
+            }
+        }
+
+        for (i, edit) in edits.iter().enumerate() {
+            if !matches.contains(&Some(i)) {
+                evaluation.overall.false_negatives += 1;
+                evaluation
+                    .error_types
+                    .entry(edit.kind.clone())
+                    .or_default()
+                    .false_negatives += 1;
+            }
+        }
+
+        n_edits += edits.len();
+    }
+
+    for scores in evaluation.rules.values_mut() {
+        scores.false_negatives = n_edits - scores.true_positives;
+    }
+
+    Ok(evaluation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const M2: &str = "S This are a example .
+A 1 2|||R:VERB:SVA|||is|||REQUIRED|||-NONE-|||0
+A 2 3|||R:DET|||an|||REQUIRED|||-NONE-|||0
+A 2 3|||R:DET|||an||the|||REQUIRED|||-NONE-|||1
+
+S It is fine .
+A -1 -1|||noop|||-NONE-|||REQUIRED|||-NONE-|||0
+";
+
+    fn suggestion(start: usize, end: usize, replacement: &str) -> Suggestion {
+        Suggestion {
+            source: "RULE".into(),
+            message: String::new(),
+            start,
+            end,
+            replacements: vec![replacement.into()],
+        }
+    }
+
+    #[test]
+    fn m2_can_be_parsed() {
+        let sentences = parse_m2(M2).unwrap();
+
+        assert_eq!(sentences.len(), 2);
+        assert_eq!(
+            sentences[0].tokens,
+            vec!["This", "are", "a", "example", "."]
+        );
+        assert_eq!(sentences[0].annotators, vec![0, 1]);
+        assert_eq!(sentences[0].edits[2].corrections, vec!["an", "the"]);
+        assert!(sentences[1].edits.is_empty());
+        assert_eq!(sentences[1].annotators, vec![0]);
+
+        assert!(matches!(
+            parse_m2("A 1 2|||R:DET|||an|||REQUIRED|||-NONE-|||0"),
+            Err(Error::InvalidM2 { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_m2("S A b .\nA 1 4|||R:DET|||an|||REQUIRED|||-NONE-|||0"),
+            Err(Error::InvalidM2 { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn suggestions_are_aligned_by_output() {
+        let tokens: Vec<_> = ["I", "do", "n't", "like", "cat", "."]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let (text, spans) = layout(&tokens, true);
+        assert_eq!(text, "I don't like cat.");
+
+        let insertion = Edit {
+            start: 4,
+            end: 4,
+            kind: "M:DET".into(),
+            corrections: vec!["the".into()],
+            annotator: 0,
+        };
+        let replacement = Edit {
+            start: 4,
+            end: 5,
+            kind: "R:NOUN:NUM".into(),
+            corrections: vec!["cats".into()],
+            annotator: 0,
+        };
+
+        let matches = align(
+            &text,
+            &spans,
+            &[
+                suggestion(13, 16, "the cat"),
+                suggestion(13, 16, "cats"),
+                suggestion(13, 16, "cats"),
+                suggestion(0, 1, "We"),
+            ],
+            &[&insertion, &replacement],
+            1,
+        );
+        assert_eq!(matches, vec![Some(0), Some(1), None, None]);
+    }
+
+    #[test]
+    fn scores_follow_m2_conventions() {
+        let scores = Scores {
+            true_positives: 1,
+            false_positives: 1,
+            false_negatives: 3,
+        };
+        assert_eq!(scores.precision(), 0.5);
+        assert_eq!(scores.recall(), 0.25);
+        assert!((scores.f05() - 5. / 12.).abs() < 1e-9);
+
+        assert_eq!(Scores::default().precision(), 1.);
+        assert_eq!(Scores::default().recall(), 1.);
+    }
+}
@@ -0,0 +1,1409 @@
+//! Export of compiled rules back to the LanguageTool XML format.
+//!
+//! The exported XML is not the original source of the rules, but it compiles to rules with the same behavior:
+//! Word and part-of-speech tag IDs are resolved with the tagger, part-of-speech matchers are written as
+//! alternations of the tags they match and regular expressions are written in the (already converted) form
+//! they are stored in. Rules in a group are numbered by their position in the XML, so IDs are only
+//! preserved if no rule of the group was left out when compiling.
+//!
+//! Not every compiled structure has an XML equivalent (e. g. patterns constructed programmatically);
+//! exporting such a rule fails with [Error::Export].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{export, Rules, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+//! let rules = Rules::new("path/to/en_rules.bin")?;
+//!
+//! std::fs::write("grammar.xml", export::export_rules(&rules, &tokenizer)?)?;
+//! std::fs::write("disambiguation.xml", export::export_disambiguation_rules(&tokenizer)?)?;
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{
+    filter::Filter,
+    rule::{
+        disambiguation::{Disambiguation, DisambiguationExample, POSFilter},
+        engine::{
+            composition::{Atom, Composition, GraphId, Matcher, Part, PosMatcher},
+            Engine,
+        },
+        grammar::{Conversion, Synthesizer, SynthesizerPart},
+        id::Group,
+        DisambiguationRule, Example, Rule, Unification,
+    },
+    tokenizer::tag::Tagger,
+    types::*,
+    utils::regex::Regex,
+    Error, Rules, Tokenizer,
+};
+use std::{collections::HashSet, fmt::Write};
+
+/// Exports a rule set to a LanguageTool grammar XML document.
+/// The tokenizer is needed to resolve word and part-of-speech tag IDs.
+///
+/// # Errors
+/// - If the rule set is not compatible with the tokenizer, see [Rules::check_compatible].
+/// - If any rule can not be expressed in XML.
+pub fn export_rules(rules: &Rules, tokenizer: &Tokenizer) -> Result<String, Error> {
+    rules.check_compatible(tokenizer)?;
+
+    grammar_document(
+        rules.rules(),
+        tokenizer.tagger(),
+        rules
+            .header()
+            .map(|header| header.metadata.lang_code.as_str()),
+    )
+}
+
+/// Exports a single grammatical error correction rule to a LanguageTool grammar XML document containing only this rule.
+/// The tagger must be the one of the tokenizer the rule was compiled for.
+///
+/// # Errors
+/// If the rule can not be expressed in XML.
+pub fn export_rule(rule: &Rule, tagger: &Tagger) -> Result<String, Error> {
+    grammar_document(std::slice::from_ref(rule), tagger, None)
+}
+
+/// Exports the disambiguation rules of a tokenizer to a LanguageTool disambiguation XML document.
+///
+/// # Errors
+/// If any rule can not be expressed in XML.
+pub fn export_disambiguation_rules(tokenizer: &Tokenizer) -> Result<String, Error> {
+    disambiguation_document(
+        tokenizer.rules(),
+        tokenizer.tagger(),
+        tokenizer
+            .header()
+            .map(|header| header.metadata.lang_code.as_str()),
+    )
+}
+
+/// Exports a single disambiguation rule to a LanguageTool disambiguation XML document containing only this rule.
+/// The tagger must be the one of the tokenizer the rule belongs to.
+///
+/// # Errors
+/// If the rule can not be expressed in XML.
+pub fn export_disambiguation_rule(
+    rule: &DisambiguationRule,
+    tagger: &Tagger,
+) -> Result<String, Error> {
+    disambiguation_document(std::slice::from_ref(rule), tagger, None)
+}
+
+fn grammar_document(rules: &[Rule], tagger: &Tagger, lang: Option<&str>) -> Result<String, Error> {
+    let mut exporter = Exporter::new(tagger);
+    let mut categories: Vec<Element> = Vec::new();
+    let mut groups = Groups::default();
+
+    for rule in rules {
+        let to_error = |reason| Error::Export {
+            id: rule.id().to_string(),
+            reason,
+        };
+        let element = exporter.rule(rule).map_err(to_error)?;
+
+        let group = rule.id().parent();
+        let category = group.parent();
+        if groups.last.map(Group::parent) != Some(category) {
+            categories.push(
+                Element::new("category")
+                    .attr("id", category.to_string())
+                    .attr("name", rule.category_name())
+                    .opt_attr("type", rule.category_type()),
+            );
+        }
+
+        let category_element = categories.last_mut().expect("category was pushed");
+        if groups.enter(group).map_err(to_error)? {
+            category_element.push(
+                Element::new("rulegroup")
+                    .attr("id", group.inner())
+                    .attr("name", rule.name()),
+            );
+        }
+        category_element.last_child_mut().push(element);
+    }
+
+    Ok(exporter.document(lang, categories))
+}
+
+fn disambiguation_document(
+    rules: &[DisambiguationRule],
+    tagger: &Tagger,
+    lang: Option<&str>,
+) -> Result<String, Error> {
+    let mut exporter = Exporter::new(tagger);
+    let mut rulegroups: Vec<Element> = Vec::new();
+    let mut groups = Groups::default();
+
+    for rule in rules {
+        let to_error = |reason| Error::Export {
+            id: rule.id().to_string(),
+            reason,
+        };
+        let element = exporter.disambiguation_rule(rule).map_err(to_error)?;
+
+        let group = rule.id().parent();
+        if groups.enter(group).map_err(to_error)? {
+            rulegroups.push(
+                Element::new("rulegroup")
+                    .attr("id", group.inner())
+                    .attr("name", group.inner()),
+            );
+        }
+        rulegroups
+            .last_mut()
+            .expect("rule group was pushed")
+            .push(element);
+    }
+
+    Ok(exporter.document(lang, rulegroups))
+}
+
+/// Tracks the rule groups while the rules are written in order.
+#[derive(Default)]
+struct Groups<'a> {
+    last: Option<&'a Group>,
+    seen: HashSet<&'a Group>,
+}
+
+impl<'a> Groups<'a> {
+    /// Returns whether a new `<rulegroup>` has to be started for a rule in this group.
+    fn enter(&mut self, group: &'a Group) -> Result<bool, String> {
+        if self.last == Some(group) {
+            return Ok(false);
+        }
+
+        // a second group with the same ID would restart the numbering of its rules
+        if !self.seen.insert(group) {
+            return Err(format!("the rules of group {} are not contiguous", group));
+        }
+
+        self.last = Some(group);
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl From<Element> for Node {
+    fn from(element: Element) -> Self {
+        Node::Element(element)
+    }
+}
+
+/// A minimal XML element. Only as much as needed to write rules.
+#[derive(Debug, Clone)]
+struct Element {
+    name: &'static str,
+    attributes: Vec<(&'static str, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &'static str) -> Self {
+        Element {
+            name,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn attr<S: Into<String>>(mut self, key: &'static str, value: S) -> Self {
+        self.attributes.push((key, value.into()));
+        self
+    }
+
+    fn opt_attr<S: Into<String>>(self, key: &'static str, value: Option<S>) -> Self {
+        match value {
+            Some(value) => self.attr(key, value),
+            None => self,
+        }
+    }
+
+    fn has_attr(&self, key: &str) -> bool {
+        self.attributes.iter().any(|(x, _)| *x == key)
+    }
+
+    fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.children.push(Node::Text(text.into()));
+        self
+    }
+
+    fn child<N: Into<Node>>(mut self, node: N) -> Self {
+        self.push(node);
+        self
+    }
+
+    fn push<N: Into<Node>>(&mut self, node: N) {
+        self.children.push(node.into());
+    }
+
+    fn last_child_mut(&mut self) -> &mut Element {
+        match self.children.last_mut() {
+            Some(Node::Element(element)) => element,
+            _ => panic!("last child must be an element"),
+        }
+    }
+
+    /// Whether the children have to be written without any whitespace in between.
+    /// Text is significant and the content of suggestions is whitespace sensitive.
+    fn is_inline(&self) -> bool {
+        self.name == "suggestion"
+            || self.name == "message"
+            || self.children.iter().any(|x| matches!(x, Node::Text(_)))
+    }
+
+    fn write(&self, out: &mut String, indent: Option<usize>) {
+        if let Some(level) = indent {
+            out.push_str(&"  ".repeat(level));
+        }
+
+        out.push('<');
+        out.push_str(self.name);
+        for (key, value) in &self.attributes {
+            write!(out, " {}=\"{}\"", key, escape(value)).expect("writing to string can not fail");
+        }
+
+        if self.children.is_empty() {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+
+        let indent = indent.filter(|_| !self.is_inline());
+        for child in &self.children {
+            match child {
+                Node::Element(element) => {
+                    if indent.is_some() {
+                        out.push('\n');
+                    }
+                    element.write(out, indent.map(|level| level + 1));
+                }
+                Node::Text(text) => out.push_str(&escape(text)),
+            }
+        }
+
+        if let Some(level) = indent {
+            out.push('\n');
+            out.push_str(&"  ".repeat(level));
+        }
+        write!(out, "</{}>", self.name).expect("writing to string can not fail");
+    }
+
+    fn to_xml(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, None);
+        out
+    }
+}
+
+pub(crate) fn escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // would be normalized to spaces in attributes
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' => out.push_str("&#9;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn escape_regex(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+/// Gets the pattern of a regex which was compiled to match the full input, i. e. without the `^(?:...)$` wrapper.
+fn full_match_pattern(regex: &Regex) -> Result<&str, String> {
+    regex
+        .as_str()
+        .strip_prefix("^(?:")
+        .and_then(|x| x.strip_suffix(")$"))
+        .ok_or_else(|| format!("regex `{}` does not match the full input", regex.as_str()))
+}
+
+/// Collects the conjunction of an atom.
+fn conjuncts<'r>(atom: &'r Atom, out: &mut Vec<&'r Atom>) {
+    match atom {
+        Atom::AndAtom(x) => x.atoms.iter().for_each(|x| conjuncts(x, out)),
+        Atom::TrueAtom(_) => {}
+        x => out.push(x),
+    }
+}
+
+/// The attributes of a `<token>` or `<exception>`.
+#[derive(Default)]
+struct MatchSpec<'r> {
+    text: Option<&'r Matcher>,
+    inflected: bool,
+    case_sensitive: bool,
+    word_data: bool,
+    pos: Option<&'r PosMatcher>,
+    chunk: Option<&'r Matcher>,
+    space_before: Option<bool>,
+}
+
+impl<'r> MatchSpec<'r> {
+    /// Adds the condition of an atom. Returns `false` if another atom already uses the same attributes.
+    fn add(&mut self, atom: &'r Atom) -> Result<bool, String> {
+        match atom {
+            Atom::TextAtom(atom) => {
+                if self.text.is_some() {
+                    return Ok(false);
+                }
+
+                let matcher = &atom.matcher.matcher;
+                if matcher.empty_always_false {
+                    return Err("text matcher which does not match empty text".into());
+                }
+
+                self.text = Some(matcher);
+                self.case_sensitive = matcher.case_sensitive;
+            }
+            Atom::WordDataAtom(atom) => {
+                let inflect_matcher = atom.matcher.inflect_matcher.as_ref();
+                if self.word_data || (inflect_matcher.is_some() && self.text.is_some()) {
+                    return Ok(false);
+                }
+
+                if atom.matcher.pos_matcher.is_none() && inflect_matcher.is_none() {
+                    return Err("word data matcher without conditions".into());
+                }
+
+                if let Some(inflect_matcher) = inflect_matcher {
+                    if !inflect_matcher.matcher.empty_always_false {
+                        return Err("lemma matcher which matches empty text".into());
+                    }
+
+                    self.text = Some(&inflect_matcher.matcher);
+                    self.inflected = true;
+                    self.case_sensitive = atom.case_sensitive;
+                }
+
+                self.word_data = true;
+                self.pos = atom.matcher.pos_matcher.as_ref();
+            }
+            Atom::ChunkAtom(atom) => {
+                let matcher = &atom.matcher;
+                if matcher.negate || !matcher.case_sensitive || !matcher.empty_always_false {
+                    return Err("chunk matcher with unsupported options".into());
+                }
+                if matches!(matcher.matcher, either::Left(either::Right(_))) {
+                    return Err("chunk matcher which references a match".into());
+                }
+
+                if let Some(chunk) = self.chunk {
+                    // the compiler adds string chunk matchers twice
+                    return Ok(chunk_pattern(chunk) == chunk_pattern(matcher));
+                }
+
+                self.chunk = Some(matcher);
+            }
+            Atom::SpaceBeforeAtom(atom) => {
+                if let Some(value) = self.space_before {
+                    return Ok(value == atom.value);
+                }
+
+                self.space_before = Some(atom.value);
+            }
+            _ => return Err("atom can not be expressed as attributes of a token".into()),
+        }
+
+        Ok(true)
+    }
+}
+
+fn chunk_pattern(matcher: &Matcher) -> (bool, &str) {
+    match &matcher.matcher {
+        either::Left(either::Left(string)) => (false, string.as_str()),
+        either::Right(regex) => (true, regex.as_str()),
+        either::Left(either::Right(_)) => unreachable!("checked when adding the chunk matcher"),
+    }
+}
+
+/// The XML of one visible part of a pattern.
+struct PartXml {
+    element: Element,
+    /// The part-of-speech filter the compiler derives from the first token, see [Exporter::postag_attributes].
+    filter: Option<Vec<bool>>,
+}
+
+struct Exporter<'a> {
+    tagger: &'a Tagger,
+    unifications: Vec<Element>,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(tagger: &'a Tagger) -> Self {
+        Exporter {
+            tagger,
+            unifications: Vec::new(),
+        }
+    }
+
+    fn document(self, lang: Option<&str>, elements: Vec<Element>) -> String {
+        let mut root = Element::new("rules").opt_attr("lang", lang);
+        for element in self.unifications.into_iter().chain(elements) {
+            root.push(element);
+        }
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        root.write(&mut out, Some(0));
+        out.push('\n');
+        out
+    }
+
+    fn pos_tags(&self, mask: &[bool]) -> Result<Vec<&str>, String> {
+        let mut tags = Vec::new();
+
+        for (i, is_match) in mask.iter().enumerate() {
+            if *is_match {
+                let tag = self.tagger.str_for_pos_id(&PosIdInt(i as u16));
+                if tag.is_empty() {
+                    return Err("part-of-speech matcher matches the empty tag".into());
+                }
+                tags.push(tag);
+            }
+        }
+
+        Ok(tags)
+    }
+
+    /// Gets a case-sensitive regex matching exactly the tags in the mask.
+    fn pos_regex(&self, mask: &[bool]) -> Result<String, String> {
+        let tags = self.pos_tags(mask)?;
+
+        Ok(if tags.is_empty() {
+            "(?!)".into()
+        } else {
+            tags.iter()
+                .map(|x| escape_regex(x))
+                .collect::<Vec<_>>()
+                .join("|")
+        })
+    }
+
+    fn is_complement(&self, a: &[bool], b: &[bool]) -> bool {
+        a.len() == b.len()
+            && a.iter().zip(b).enumerate().all(|(i, (a, b))| {
+                // the empty tag never matches, even if negated
+                a != b || self.tagger.str_for_pos_id(&PosIdInt(i as u16)).is_empty()
+            })
+    }
+
+    /// Writes the part-of-speech matcher of a token.
+    /// The compiler derives part-of-speech filters for some disambiguations from the `postag` attribute of the tokens,
+    /// ignoring `negate_pos`. If such a filter is given, the attributes are chosen such that it is derived correctly.
+    /// Returns the filter the compiler will derive.
+    fn postag_attributes(
+        &self,
+        element: Element,
+        matcher: &PosMatcher,
+        filter: Option<&PosMatcher>,
+    ) -> Result<(Element, Vec<bool>), String> {
+        match filter {
+            Some(filter)
+                if filter.mask != matcher.mask
+                    && self.is_complement(&filter.mask, &matcher.mask) =>
+            {
+                Ok((
+                    element
+                        .attr("postag", self.pos_regex(&filter.mask)?)
+                        .attr("postag_regexp", "yes")
+                        .attr("negate_pos", "yes"),
+                    filter.mask.clone(),
+                ))
+            }
+            _ => Ok((
+                element
+                    .attr("postag", self.pos_regex(&matcher.mask)?)
+                    .attr("postag_regexp", "yes"),
+                matcher.mask.clone(),
+            )),
+        }
+    }
+
+    /// Writes the attributes (and text) of a match specification to a `<token>` or `<exception>`.
+    fn match_spec(
+        &self,
+        mut element: Element,
+        spec: &MatchSpec,
+        filter: Option<&PosMatcher>,
+    ) -> Result<(Element, Option<Vec<bool>>), String> {
+        let is_exception = element.name == "exception";
+
+        if let Some(matcher) = spec.text {
+            match &matcher.matcher {
+                either::Left(either::Left(string)) => {
+                    element = element
+                        .attr(
+                            "case_sensitive",
+                            if spec.case_sensitive { "yes" } else { "no" },
+                        )
+                        .text(string.as_str());
+                }
+                either::Left(either::Right(id)) => {
+                    if is_exception || id.0 == 0 {
+                        return Err("unsupported reference to a match".into());
+                    }
+
+                    element = element
+                        .attr(
+                            "case_sensitive",
+                            if spec.case_sensitive { "yes" } else { "no" },
+                        )
+                        .child(Element::new("match").attr("no", (id.0 - 1).to_string()));
+                }
+                either::Right(regex) => {
+                    // case insensitivity is already part of the converted regex
+                    element = element
+                        .attr("regexp", "yes")
+                        .attr("case_sensitive", "yes")
+                        .text(full_match_pattern(regex)?);
+                }
+            }
+
+            if matcher.negate {
+                element = element.attr("negate", "yes");
+            }
+            if spec.inflected {
+                element = element.attr("inflected", "yes");
+            }
+        }
+
+        let mut derived_filter = None;
+        if let Some(pos) = spec.pos {
+            let (new_element, derived) = self.postag_attributes(element, pos, filter)?;
+            element = new_element;
+            derived_filter = Some(derived);
+        }
+
+        if let Some(chunk) = spec.chunk {
+            element = match &chunk.matcher {
+                either::Right(regex) => element.attr("chunk_re", full_match_pattern(regex)?),
+                _ => element.attr("chunk", chunk_pattern(chunk).1),
+            };
+        }
+
+        if let Some(value) = spec.space_before {
+            element = element.attr("spacebefore", if value { "yes" } else { "no" });
+        }
+
+        Ok((element, derived_filter))
+    }
+
+    /// Gets the `<exception>`s of a token from the atom of a `NotAtom`.
+    fn exceptions(&self, atom: &Atom) -> Result<Vec<Element>, String> {
+        let alternatives: Vec<&Atom> = match atom {
+            Atom::OrAtom(x) => x.atoms.iter().collect(),
+            x => vec![x],
+        };
+
+        alternatives
+            .into_iter()
+            .map(|atom| {
+                let (atom, scope) = match atom {
+                    Atom::OffsetAtom(x) => (
+                        x.atom.as_ref(),
+                        Some(match x.offset {
+                            1 => "next",
+                            -1 => "previous",
+                            _ => return Err(format!("exception with offset {}", x.offset)),
+                        }),
+                    ),
+                    x => (x, None),
+                };
+
+                let mut atoms = Vec::new();
+                conjuncts(atom, &mut atoms);
+
+                let mut spec = MatchSpec::default();
+                for atom in atoms {
+                    if !spec.add(atom)? {
+                        return Err("exception with conflicting conditions".into());
+                    }
+                }
+
+                let (element, _) = self.match_spec(Element::new("exception"), &spec, None)?;
+                Ok(element.opt_attr("scope", scope))
+            })
+            .collect()
+    }
+
+    /// Gets `<token>`s whose conjunction is equivalent to the atom.
+    /// Usually one token, but constraints which can not be combined in one token are split into multiple tokens.
+    fn tokens(
+        &self,
+        atom: &Atom,
+        filter: Option<&PosMatcher>,
+    ) -> Result<(Vec<Element>, Option<Vec<bool>>), String> {
+        let mut atoms = Vec::new();
+        conjuncts(atom, &mut atoms);
+        // the compiler derives filters from the first token so part-of-speech matchers go first
+        atoms.sort_by_key(|x| !matches!(x, Atom::WordDataAtom(_)));
+
+        let mut specs = vec![MatchSpec::default()];
+        let mut exceptions = Vec::new();
+
+        for atom in atoms {
+            match atom {
+                Atom::NotAtom(x) => exceptions.extend(self.exceptions(&x.atom)?),
+                // an exception without conditions excludes every token
+                Atom::FalseAtom(_) => exceptions.push(Element::new("exception")),
+                atom => {
+                    let mut added = false;
+                    for spec in specs.iter_mut() {
+                        if spec.add(atom)? {
+                            added = true;
+                            break;
+                        }
+                    }
+
+                    if !added {
+                        let mut spec = MatchSpec::default();
+                        spec.add(atom)?;
+                        specs.push(spec);
+                    }
+                }
+            }
+        }
+
+        let mut tokens = Vec::new();
+        let mut first_filter = None;
+
+        for (i, spec) in specs.iter().enumerate() {
+            let (mut token, derived) = self.match_spec(
+                Element::new("token"),
+                spec,
+                if i == 0 { filter } else { None },
+            )?;
+
+            if i == 0 {
+                first_filter = derived;
+                for exception in exceptions.drain(..) {
+                    token.push(exception);
+                }
+            }
+
+            tokens.push(token);
+        }
+
+        Ok((tokens, first_filter))
+    }
+
+    /// Gets the XML of a visible part. `skip` is the invisible part following it, if any.
+    fn part(
+        &self,
+        part: &Part,
+        skip: Option<&Part>,
+        filter: Option<&PosMatcher>,
+    ) -> Result<PartXml, String> {
+        if !part.greedy {
+            return Err("non-greedy token".into());
+        }
+
+        let (mut elements, first_filter, combination) = match &part.atom {
+            Atom::OrAtom(x) => {
+                let mut elements = Vec::new();
+                let mut first_filter = None;
+
+                for (i, atom) in x.atoms.iter().enumerate() {
+                    let (mut tokens, derived) =
+                        self.tokens(atom, if i == 0 { filter } else { None })?;
+                    if tokens.len() != 1 {
+                        return Err("alternative which can not be expressed as one token".into());
+                    }
+
+                    if i == 0 {
+                        first_filter = derived;
+                    }
+                    elements.push(tokens.remove(0));
+                }
+
+                (elements, first_filter, Some("or"))
+            }
+            atom => {
+                let (tokens, derived) = self.tokens(atom, filter)?;
+                let combination = if tokens.len() > 1 { Some("and") } else { None };
+
+                (tokens, derived, combination)
+            }
+        };
+
+        let quantifier = &part.quantifier;
+        if combination.is_some() && ((quantifier.min, quantifier.max) != (1, 1) || skip.is_some()) {
+            return Err("quantifier or skip on a combination of tokens".into());
+        }
+        if quantifier.min > quantifier.max {
+            return Err("quantifier with min > max".into());
+        }
+
+        // `unify` of combinations is read from the first token
+        let mut first = elements.remove(0);
+        if let Some(unify) = part.unify {
+            first = first.attr("unify", if unify { "yes" } else { "negate" });
+        }
+
+        if quantifier.min != 1 {
+            first = first.attr("min", quantifier.min.to_string());
+        }
+        if quantifier.max != 1 {
+            first = first.attr("max", quantifier.max.to_string());
+        }
+
+        if let Some(skip) = skip {
+            if skip.greedy || skip.quantifier.min != 0 || skip.unify.is_some() {
+                return Err("skip with unsupported options".into());
+            }
+
+            // the compiler only keeps the exceptions which are not in the scope of the current token for skipped tokens
+            let expected: Vec<_> = first
+                .children
+                .iter()
+                .filter_map(|x| match x {
+                    Node::Element(x) if x.name == "exception" && x.has_attr("scope") => {
+                        Some(x.to_xml())
+                    }
+                    _ => None,
+                })
+                .collect();
+            let found: Vec<_> = match &skip.atom {
+                Atom::TrueAtom(_) => Vec::new(),
+                Atom::NotAtom(x) => self
+                    .exceptions(&x.atom)?
+                    .iter()
+                    .map(Element::to_xml)
+                    .collect(),
+                _ => return Err("skip with unsupported condition".into()),
+            };
+
+            if expected != found {
+                return Err("skip with conditions which differ from the token".into());
+            }
+
+            first = first.attr("skip", skip.quantifier.max.to_string());
+        }
+
+        elements.insert(0, first);
+
+        let element = match combination {
+            Some(name) => {
+                let mut element = Element::new(name);
+                for token in elements {
+                    element.push(token);
+                }
+                element
+            }
+            None => elements.remove(0),
+        };
+
+        Ok(PartXml {
+            element,
+            filter: first_filter,
+        })
+    }
+
+    /// Gets a `<pattern>` or `<antipattern>`.
+    /// `marker` is the range of visible part IDs to mark, `filters` are the expected part-of-speech filters of the visible parts.
+    /// Returns the element and the part-of-speech filters the compiler derives for each visible part.
+    fn pattern(
+        &self,
+        name: &'static str,
+        composition: &Composition,
+        marker: Option<(usize, usize)>,
+        filters: &[Option<&PosMatcher>],
+        features: Vec<Element>,
+    ) -> Result<(Element, Vec<Option<Vec<bool>>>), String> {
+        let mut pattern = Element::new(name);
+        for feature in features {
+            pattern.push(feature);
+        }
+
+        let parts = &composition.parts;
+        let mut marker_element: Option<Element> = None;
+        let mut derived_filters = Vec::new();
+        let mut i = 0;
+
+        while i < parts.len() {
+            if !parts[i].visible {
+                return Err("skipped tokens which do not follow a token".into());
+            }
+
+            let id = derived_filters.len() + 1;
+            let skip = parts.get(i + 1).filter(|x| !x.visible);
+            let part = self.part(&parts[i], skip, filters.get(id - 1).copied().flatten())?;
+            derived_filters.push(part.filter);
+            i += if skip.is_some() { 2 } else { 1 };
+
+            match marker {
+                Some((start, end)) if id >= start && id <= end => {
+                    marker_element
+                        .get_or_insert_with(|| Element::new("marker"))
+                        .push(part.element);
+
+                    if id == end {
+                        pattern.push(marker_element.take().expect("marker has tokens"));
+                    }
+                }
+                _ => pattern.push(part.element),
+            }
+        }
+
+        Ok((pattern, derived_filters))
+    }
+
+    /// Gets the marker of a pattern from the start and end ID of a rule. `None` if the whole pattern is marked.
+    fn marker(
+        composition: &Composition,
+        start: GraphId,
+        end: GraphId,
+    ) -> Result<Option<(usize, usize)>, String> {
+        let n_visible = composition.parts.iter().filter(|x| x.visible).count();
+
+        if start.0 == 0 || start.0 > end.0 || end.0 > n_visible {
+            return Err(format!(
+                "marker from {} to {} in pattern with {} tokens",
+                start.0, end.0, n_visible
+            ));
+        }
+
+        Ok(if (start.0, end.0) == (1, n_visible) {
+            None
+        } else {
+            Some((start.0, end.0))
+        })
+    }
+
+    /// Registers the `<unification>`s of a pattern. Returns the `<feature>`s referencing them.
+    fn unification(
+        &mut self,
+        unification: &Unification,
+        composition: &Composition,
+    ) -> Result<Vec<Element>, String> {
+        let mask: Vec<_> = composition.parts.iter().map(|x| x.unify).collect();
+        if mask != unification.mask {
+            return Err("unification mask differs from the tokens".into());
+        }
+
+        let mut features = Vec::new();
+
+        for filters in &unification.filters {
+            if filters.is_empty() {
+                return Err("unification feature without equivalences".into());
+            }
+
+            let feature = format!("nlprule_{}", self.unifications.len());
+            let mut element = Element::new("unification").attr("feature", feature.as_str());
+
+            for (i, filter) in filters.iter().enumerate() {
+                element.push(
+                    Element::new("equivalence")
+                        .attr("type", i.to_string())
+                        .child(
+                            Element::new("token")
+                                .attr("postag", self.pos_regex(&filter.matcher.mask)?)
+                                .attr("postag_regexp", "yes"),
+                        ),
+                );
+            }
+
+            self.unifications.push(element);
+            features.push(Element::new("feature").attr("id", feature));
+        }
+
+        Ok(features)
+    }
+
+    fn synthesizer_nodes(&self, parts: &[SynthesizerPart]) -> Result<Vec<Node>, String> {
+        parts
+            .iter()
+            .map(|part| match part {
+                SynthesizerPart::Text(text) => {
+                    let chars: Vec<_> = text.chars().collect();
+                    if chars
+                        .windows(2)
+                        .any(|x| x[0] == '\\' && x[1].is_ascii_digit())
+                        || chars.last() == Some(&'\\')
+                    {
+                        return Err("text which would be read as a reference to a match".into());
+                    }
+
+                    Ok(Node::Text(text.clone()))
+                }
+                SynthesizerPart::Match(m) => {
+                    if m.pos_replacer.is_some() {
+                        return Err("match with part-of-speech replacement".into());
+                    }
+
+                    let conversion = match m.conversion {
+                        Conversion::Nop => None,
+                        Conversion::AllLower => Some("alllower"),
+                        Conversion::StartLower => Some("startlower"),
+                        Conversion::AllUpper => Some("allupper"),
+                        Conversion::StartUpper => Some("startupper"),
+                    };
+
+                    // only single digits can be referenced in text
+                    if conversion.is_none() && m.regex_replacer.is_none() && m.id.0 < 10 {
+                        return Ok(Node::Text(format!("\\{}", m.id.0)));
+                    }
+
+                    let mut element = Element::new("match")
+                        .attr("no", m.id.0.to_string())
+                        .opt_attr("case_conversion", conversion);
+                    if let Some((regex, replacement)) = &m.regex_replacer {
+                        element = element
+                            .attr("regexp_match", regex.as_str())
+                            .attr("regexp_replace", replacement.as_str());
+                    }
+
+                    Ok(Node::Element(element))
+                }
+            })
+            .collect()
+    }
+
+    /// Gets the `<message>` and the `<suggestion>`s outside of the message.
+    /// The compiler puts the parts of suggestions in the message into the message as well, so
+    /// suggestions found in order in the message are written inside of it.
+    fn message(
+        &self,
+        message: &Synthesizer,
+        suggesters: &[Synthesizer],
+    ) -> Result<(Element, Vec<Element>), String> {
+        let message_nodes = self.synthesizer_nodes(&message.parts)?;
+        let message_keys: Vec<_> = message_nodes.iter().map(node_key).collect();
+
+        let mut element = Element::new("message");
+        let mut suggestions = Vec::new();
+        let mut cursor = 0;
+
+        for suggester in suggesters {
+            let nodes = self.synthesizer_nodes(&suggester.parts)?;
+            let keys: Vec<_> = nodes.iter().map(node_key).collect();
+
+            let position = if suggestions.is_empty() && !keys.is_empty() {
+                message_keys[cursor..]
+                    .windows(keys.len())
+                    .position(|x| x == keys.as_slice())
+            } else {
+                None
+            };
+
+            let mut suggestion = Element::new("suggestion");
+            for node in nodes {
+                suggestion.push(node);
+            }
+
+            match position {
+                Some(offset) => {
+                    for node in &message_nodes[cursor..cursor + offset] {
+                        element.push(node.clone());
+                    }
+                    element.push(suggestion);
+                    cursor += offset + keys.len();
+                }
+                None => suggestions.push(suggestion),
+            }
+        }
+
+        for node in &message_nodes[cursor..] {
+            element.push(node.clone());
+        }
+
+        Ok((element, suggestions))
+    }
+
+    fn rule(&mut self, rule: &Rule) -> Result<Element, String> {
+        let details = rule.details.get();
+        let mut element = Element::new("rule").attr("name", details.name.as_str());
+        if !rule.enabled {
+            element = element.attr("default", "off");
+        }
+
+        match &rule.engine {
+            Engine::Token(engine) => {
+                let features = match &rule.unification {
+                    Some(unification) => self.unification(unification, &engine.composition)?,
+                    None => Vec::new(),
+                };
+                let marker = Exporter::marker(&engine.composition, rule.start, rule.end)?;
+
+                for antipattern in &engine.antipatterns {
+                    element.push(
+                        self.pattern("antipattern", antipattern, None, &[], Vec::new())?
+                            .0,
+                    );
+                }
+                element.push(
+                    self.pattern("pattern", &engine.composition, marker, &[], features)?
+                        .0,
+                );
+            }
+            Engine::Text(regex, _) => {
+                if rule.unification.is_some() || rule.start != rule.end {
+                    return Err("regex rule with unification or marker range".into());
+                }
+
+                let mut regexp = Element::new("regexp")
+                    .attr("case_sensitive", "yes")
+                    .text(regex.as_str());
+                if rule.start.0 != 0 {
+                    regexp = regexp.attr("mark", rule.start.0.to_string());
+                }
+                element.push(regexp);
+            }
+        }
+
+        let (message, suggestions) = self.message(&details.message, &details.suggesters)?;
+        element.push(message);
+        for suggestion in suggestions {
+            element.push(suggestion);
+        }
+
+        if let Some(url) = &details.url {
+            element.push(Element::new("url").text(url.as_str()));
+        }
+        if let Some(short) = &details.short {
+            element.push(Element::new("short").text(short.as_str()));
+        }
+
+        for example in &details.examples {
+            element.push(example_element(example)?);
+        }
+
+        Ok(element)
+    }
+
+    fn reading_filter(
+        &self,
+        data_or_filter: &either::Either<owned::WordData, POSFilter>,
+    ) -> Result<Element, String> {
+        Ok(match data_or_filter {
+            either::Left(data) => word_data_element(data),
+            either::Right(filter) => Element::new("match")
+                .attr("no", "0")
+                .attr("postag", self.pos_regex(&filter.matcher.mask)?)
+                .attr("postag_regexp", "yes"),
+        })
+    }
+
+    fn disambiguation_rule(&mut self, rule: &DisambiguationRule) -> Result<Element, String> {
+        let engine = match &rule.engine {
+            Engine::Token(engine) => engine,
+            Engine::Text(_, _) => return Err("disambiguation rule with regex".into()),
+        };
+        let composition = &engine.composition;
+        let marker = Exporter::marker(composition, rule.start, rule.end)?;
+
+        // `filterall` and `unify` derive their filters from the marked tokens
+        let mut disambig = Element::new("disambig");
+        let mut expected_filters: Option<Vec<Option<&PosMatcher>>> = None;
+
+        match &rule.disambiguations {
+            Disambiguation::Remove(data) => {
+                disambig = disambig.attr("action", "remove");
+                for data_or_filter in data {
+                    disambig.push(self.reading_filter(data_or_filter)?);
+                }
+            }
+            Disambiguation::Add(data) => {
+                disambig = disambig.attr("action", "add");
+                for data in data {
+                    disambig.push(word_data_element(data));
+                }
+            }
+            Disambiguation::Replace(data) => {
+                disambig = disambig.attr("action", "replace");
+                for data in data {
+                    disambig.push(word_data_element(data));
+                }
+            }
+            Disambiguation::Filter(data) => {
+                if data.iter().all(Option::is_some) {
+                    disambig = disambig.attr("action", "filter");
+                    for data_or_filter in data.iter().flatten() {
+                        disambig.push(self.reading_filter(data_or_filter)?);
+                    }
+                } else {
+                    disambig = disambig.attr("action", "filterall");
+                    expected_filters = Some(
+                        data.iter()
+                            .map(|x| match x {
+                                Some(either::Right(filter)) => Ok(Some(&filter.matcher)),
+                                Some(either::Left(_)) => {
+                                    Err("filter with readings and empty filters".to_string())
+                                }
+                                None => Ok(None),
+                            })
+                            .collect::<Result<_, _>>()?,
+                    );
+                }
+            }
+            Disambiguation::Unify(filters, marker_filters, mask) => {
+                disambig = disambig.attr("action", "unify");
+
+                let unification_filters = rule
+                    .unification
+                    .as_ref()
+                    .map_or(&[][..], |x| x.filters.as_slice());
+                if !same_filters(filters, unification_filters) {
+                    return Err("unify action with filters which differ from the pattern".into());
+                }
+
+                let unify_mask: Vec<_> = composition
+                    .parts
+                    .iter()
+                    .filter(|x| x.visible)
+                    .skip(rule.start.0 - 1)
+                    .take(rule.end.0 + 1 - rule.start.0)
+                    .map(|x| x.unify.is_some())
+                    .collect();
+                if &unify_mask != mask {
+                    return Err("unify action with mask which differs from the pattern".into());
+                }
+
+                expected_filters = Some(
+                    marker_filters
+                        .iter()
+                        .map(|x| x.as_ref().map(|x| &x.matcher))
+                        .collect(),
+                );
+            }
+            Disambiguation::Nop => {
+                disambig = disambig.attr("action", "immunize");
+            }
+        }
+
+        let mut filters = Vec::new();
+        if let Some(expected) = &expected_filters {
+            if expected.len() != rule.end.0 + 1 - rule.start.0 {
+                return Err(
+                    "disambiguation with a different number of filters than marked tokens".into(),
+                );
+            }
+
+            filters.resize(rule.start.0 - 1, None);
+            filters.extend(expected.iter().copied());
+        }
+
+        let features = match &rule.unification {
+            Some(unification) => self.unification(unification, composition)?,
+            None => Vec::new(),
+        };
+
+        let mut element = Element::new("rule");
+        for antipattern in &engine.antipatterns {
+            element.push(
+                self.pattern("antipattern", antipattern, None, &[], Vec::new())?
+                    .0,
+            );
+        }
+
+        let (pattern, derived_filters) =
+            self.pattern("pattern", composition, marker, &filters, features)?;
+        if let Some(expected) = expected_filters {
+            let derived = &derived_filters[rule.start.0 - 1..rule.end.0];
+            let matches = expected
+                .iter()
+                .zip(derived)
+                .all(|(expected, derived)| expected.map(|x| &x.mask) == derived.as_ref());
+
+            if !matches {
+                return Err(
+                    "disambiguation with part-of-speech filters which differ from the tokens"
+                        .into(),
+                );
+            }
+        }
+        element.push(pattern);
+        element.push(disambig);
+
+        if let Some(filter) = &rule.filter {
+            element.push(filter_element(filter)?);
+        }
+
+        for example in &rule.examples {
+            element.push(disambiguation_example_element(example)?);
+        }
+
+        Ok(element)
+    }
+}
+
+fn node_key(node: &Node) -> String {
+    match node {
+        Node::Element(element) => element.to_xml(),
+        Node::Text(text) => escape(text),
+    }
+}
+
+fn same_filters(a: &[Vec<POSFilter>], b: &[Vec<POSFilter>]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a.matcher.mask == b.matcher.mask)
+        })
+}
+
+fn word_data_element(data: &owned::WordData) -> Element {
+    let lemma = data.lemma.as_ref();
+    let pos = data.pos.as_ref();
+
+    Element::new("wd")
+        .opt_attr("lemma", Some(lemma).filter(|x| !x.is_empty()))
+        .opt_attr("pos", Some(pos).filter(|x| !x.is_empty()))
+}
+
+fn filter_element(filter: &Filter) -> Result<Element, String> {
+    match filter {
+        Filter::NoDisambiguationEnglishPartialPosTagFilter(filter) => {
+            if filter.negate_postag {
+                return Err("filter with negate_postag".into());
+            }
+
+            let args = format!(
+                "no:{} regexp:{} postag_regexp:{}",
+                filter.id.0,
+                full_match_pattern(&filter.regexp)?,
+                full_match_pattern(&filter.postag_regexp)?
+            );
+            // arguments are separated by spaces
+            if args.split(' ').count() != 3 {
+                return Err("filter argument with a space".into());
+            }
+
+            Ok(Element::new("filter")
+                .attr(
+                    "class",
+                    "org.languagetool.rules.en.NoDisambiguationEnglishPartialPosTagFilter",
+                )
+                .attr("args", args))
+        }
+    }
+}
+
+/// Splits a text into the parts before, in and after a char span.
+fn split_chars(
+    text: &str,
+    (start, end): (usize, usize),
+) -> Result<(String, String, String), String> {
+    if start >= end || end > text.chars().count() {
+        return Err(format!(
+            "example with invalid marker from {} to {}",
+            start, end
+        ));
+    }
+
+    let before = text.chars().take(start).collect();
+    let marked = text.chars().skip(start).take(end - start).collect();
+    let after = text.chars().skip(end).collect();
+
+    Ok((before, marked, after))
+}
+
+fn marked_text(mut element: Element, text: &str, span: (usize, usize)) -> Result<Element, String> {
+    let (before, marked, after) = split_chars(text, span)?;
+
+    if !before.is_empty() {
+        element = element.text(before);
+    }
+    element = element.child(Element::new("marker").text(marked));
+    if !after.is_empty() {
+        element = element.text(after);
+    }
+
+    Ok(element)
+}
+
+fn example_element(example: &Example) -> Result<Element, String> {
+    let element = Element::new("example");
+
+    match example.suggestion() {
+        Some(suggestion) => {
+            if suggestion.replacements.iter().any(|x| x.contains('|')) {
+                return Err("example correction containing `|`".into());
+            }
+
+            marked_text(
+                element.attr("correction", suggestion.replacements.join("|")),
+                example.text(),
+                (suggestion.start, suggestion.end),
+            )
+        }
+        None => Ok(element.text(example.text())),
+    }
+}
+
+/// Gets the form `text[lemma/POS,lemma/POS]` of a word used in disambiguation examples.
+fn tag_form(word: &owned::Word) -> Result<String, String> {
+    let text = word.text.as_ref();
+    if text.is_empty() || text.contains('[') {
+        return Err(format!("example with word `{}`", text));
+    }
+
+    let mut tags = Vec::new();
+    for data in &word.tags {
+        let (lemma, pos) = (data.lemma.as_ref(), data.pos.as_ref());
+        if [lemma, pos]
+            .iter()
+            .any(|x| x.contains(&[',', '/', ']'][..]))
+        {
+            return Err(format!("example with reading `{}/{}`", lemma, pos));
+        }
+
+        tags.push(format!("{}/{}", lemma, pos));
+    }
+
+    // a special symbol without a reading, the only way to write a word without any tags
+    if tags.is_empty() {
+        tags.push("</S>".into());
+    }
+
+    Ok(format!("{}[{}]", text, tags.join(",")))
+}
+
+fn disambiguation_example_element(example: &DisambiguationExample) -> Result<Element, String> {
+    match example {
+        DisambiguationExample::Unchanged(text) => Ok(Element::new("example")
+            .attr("type", "untouched")
+            .text(text.as_str())),
+        DisambiguationExample::Changed(change) => marked_text(
+            Element::new("example")
+                .attr("type", "ambiguous")
+                .attr("inputform", tag_form(&change.before)?)
+                .attr("outputform", tag_form(&change.after)?),
+            &change.text,
+            change.char_span,
+        ),
+    }
+}
@@ -13,6 +13,8 @@ pub enum Filter {
 #[enum_dispatch(Filter)]
 pub trait Filterable {
     fn keep(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> bool;
+
+    fn regexes(&self) -> Vec<&Regex>;
 }
 
 #[derive(Serialize, Deserialize)]
@@ -20,7 +22,6 @@ pub struct NoDisambiguationEnglishPartialPosTagFilter {
     pub(crate) id: GraphId,
     pub(crate) regexp: Regex,
     pub(crate) postag_regexp: Regex,
-    #[allow(dead_code)]
     pub(crate) negate_postag: bool,
 }
 
@@ -39,4 +40,8 @@ impl Filterable for NoDisambiguationEnglishPartialPosTagFilter {
             }
         })
     }
+
+    fn regexes(&self) -> Vec<&Regex> {
+        vec![&self.regexp, &self.postag_regexp]
+    }
 }
@@ -0,0 +1,764 @@
+//! Human-readable dumps of tokenizers and rule sets.
+//!
+//! The compiled structures refer to words and part-of-speech tags by their numerical IDs in the tagger.
+//! The dumps resolve these IDs to strings so they can be read without the original XML and diffed between versions.
+//! All dumps implement [Serialize] and are meant to be written as JSON, e. g. with the `inspect` binary.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{inspect, Rules, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+//! let rules = Rules::new("path/to/en_rules.bin")?;
+//!
+//! let dump = inspect::dump_rules(&rules, &tokenizer)?;
+//! println!("{} rules", dump.rules.len());
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{
+    binary::Metadata,
+    filter::Filter,
+    rule::{
+        disambiguation::{Disambiguation, DisambiguationExample, POSFilter},
+        engine::{
+            composition::{Atom, Composition, Matcher, PosMatcher, TextMatcher},
+            Engine,
+        },
+        grammar::{Conversion, Synthesizer, SynthesizerPart},
+        DisambiguationRule, Rule, Unification,
+    },
+    tokenizer::tag::{Tagger, TaggerRepresentation},
+    types::*,
+    Error, Rules, Tokenizer,
+};
+use serde::Serialize;
+
+/// A dump of a [Tokenizer].
+#[derive(Debug, Serialize)]
+pub struct TokenizerDump {
+    /// Information about the sources of the binary the tokenizer was loaded from, if any.
+    pub metadata: Option<Metadata>,
+    /// Statistics about the tagger dictionary.
+    pub tagger: TaggerDump,
+    /// Whether the tokenizer has a chunker.
+    pub has_chunker: bool,
+    /// The number of phrases known to the multiword tagger.
+    pub n_multiwords: usize,
+    /// The disambiguation rules in the order they are applied.
+    pub rules: Vec<DisambiguationRuleDump>,
+}
+
+/// Statistics about the dictionary of a [Tagger].
+#[derive(Debug, Serialize)]
+pub struct TaggerDump {
+    /// How the dictionary is represented in memory.
+    pub representation: TaggerRepresentation,
+    /// The fingerprint of the dictionary, see [Tagger::fingerprint].
+    pub fingerprint: Option<u64>,
+    /// The number of known words.
+    pub n_words: usize,
+    /// The number of `(word, lemma, part-of-speech)` entries.
+    pub n_entries: usize,
+    /// All part-of-speech tags, ordered by their ID.
+    pub pos_tags: Vec<String>,
+}
+
+/// A dump of a [Rules] set.
+#[derive(Debug, Serialize)]
+pub struct RulesDump {
+    /// Information about the sources of the binary the rules were loaded from, if any.
+    pub metadata: Option<Metadata>,
+    /// The rules in the order they are applied.
+    pub rules: Vec<RuleDump>,
+}
+
+/// A dump of a grammatical error correction [Rule].
+#[derive(Debug, Serialize)]
+pub struct RuleDump {
+    /// The ID of the rule.
+    pub id: String,
+    /// The human-readable name of the rule.
+    pub name: String,
+    /// The name of the category of the rule.
+    pub category_name: String,
+    /// The type of the category of the rule.
+    pub category_type: Option<String>,
+    /// Whether the rule is enabled.
+    pub enabled: bool,
+    /// The pattern the rule matches.
+    pub engine: EngineDump,
+    /// The ID of the first group covered by a suggestion.
+    pub start: usize,
+    /// The ID of the last group covered by a suggestion.
+    pub end: usize,
+    /// The unification constraint of the rule, if any.
+    pub unification: Option<UnificationDump>,
+    /// The message template.
+    pub message: SynthesizerDump,
+    /// The templates of the suggested replacements.
+    pub suggesters: Vec<SynthesizerDump>,
+    /// A short message.
+    pub short: Option<String>,
+    /// A link to further information.
+    pub url: Option<String>,
+    /// The examples of the rule.
+    pub examples: Vec<ExampleDump>,
+}
+
+/// A dump of a [DisambiguationRule].
+#[derive(Debug, Serialize)]
+pub struct DisambiguationRuleDump {
+    /// The ID of the rule.
+    pub id: String,
+    /// The pattern the rule matches.
+    pub engine: EngineDump,
+    /// The ID of the first group which is changed.
+    pub start: usize,
+    /// The ID of the last group which is changed.
+    pub end: usize,
+    /// The unification constraint of the rule, if any.
+    pub unification: Option<UnificationDump>,
+    /// An additional filter on the matches, if any.
+    pub filter: Option<FilterDump>,
+    /// The change applied to the matched tokens.
+    pub disambiguation: DisambiguationDump,
+    /// The examples of the rule.
+    pub examples: Vec<DisambiguationExampleDump>,
+}
+
+/// A dump of the matching engine of a rule.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineDump {
+    /// Matches a sequence of tokens.
+    Token {
+        /// The parts of the pattern.
+        pattern: Vec<PartDump>,
+        /// Patterns which prevent a match if they match an overlapping sequence of tokens.
+        antipatterns: Vec<Vec<PartDump>>,
+    },
+    /// Matches a regular expression on the text of a sentence.
+    Text {
+        /// The regular expression.
+        regex: String,
+    },
+}
+
+/// A dump of one part of a token pattern.
+#[derive(Debug, Serialize)]
+pub struct PartDump {
+    /// The condition a token has to fulfill.
+    pub atom: AtomDump,
+    /// The minimum number of tokens matched by this part.
+    pub min: usize,
+    /// The maximum number of tokens matched by this part. `None` if unbounded.
+    pub max: Option<usize>,
+    /// Whether this part matches as many tokens as possible.
+    pub greedy: bool,
+    /// Whether the tokens matched by this part are part of the match.
+    pub visible: bool,
+    /// Whether the tokens matched by this part are subject to unification.
+    pub unify: Option<bool>,
+}
+
+/// A dump of a condition on a token.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AtomDump {
+    /// Matches the text of the token.
+    Text {
+        /// The matcher for the text.
+        matcher: TextMatcherDump,
+    },
+    /// Matches the chunks of the token.
+    Chunk {
+        /// The matcher for the chunks.
+        matcher: MatcherDump,
+    },
+    /// Matches whether the token is preceded by whitespace.
+    SpaceBefore {
+        /// The required value.
+        value: bool,
+    },
+    /// Matches if one reading of the token fulfills both conditions.
+    WordData {
+        /// The allowed part-of-speech tags. `None` if any tag is allowed.
+        pos: Option<Vec<String>>,
+        /// The matcher for the lemma. `None` if any lemma is allowed.
+        lemma: Option<TextMatcherDump>,
+        /// Whether the lemma is matched case-sensitively.
+        case_sensitive: bool,
+    },
+    /// Always matches.
+    True,
+    /// Never matches.
+    False,
+    /// Matches if all atoms match.
+    And {
+        /// The atoms.
+        atoms: Vec<AtomDump>,
+    },
+    /// Matches if any atom matches.
+    Or {
+        /// The atoms.
+        atoms: Vec<AtomDump>,
+    },
+    /// Matches if the atom does not match.
+    Not {
+        /// The negated atom.
+        atom: Box<AtomDump>,
+    },
+    /// Matches if the atom matches the token at an offset.
+    Offset {
+        /// The atom.
+        atom: Box<AtomDump>,
+        /// The offset relative to the current token.
+        offset: isize,
+    },
+}
+
+/// A dump of a matcher for strings.
+#[derive(Debug, Serialize)]
+pub struct MatcherDump {
+    /// What the string is compared to.
+    pub pattern: PatternDump,
+    /// Whether the result is negated.
+    pub negate: bool,
+    /// Whether the comparison is case-sensitive.
+    pub case_sensitive: bool,
+    /// Whether empty strings never match.
+    pub empty_always_false: bool,
+}
+
+/// What a string is compared to.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternDump {
+    /// Equality with a string.
+    Text(String),
+    /// Equality with the text of a previously matched group.
+    Reference(usize),
+    /// A regular expression.
+    Regex(String),
+}
+
+/// A dump of a matcher for words.
+#[derive(Debug, Serialize)]
+pub struct TextMatcherDump {
+    /// The matcher.
+    #[serde(flatten)]
+    pub matcher: MatcherDump,
+    /// The known words the matcher was precomputed to match, if any.
+    pub words: Option<Vec<String>>,
+}
+
+/// A dump of a unification constraint.
+#[derive(Debug, Serialize)]
+pub struct UnificationDump {
+    /// For each part of the pattern whether it is unified and if so, whether the unification is negated.
+    pub mask: Vec<Option<bool>>,
+    /// The part-of-speech tags allowed by each filter of each feature.
+    pub filters: Vec<Vec<Vec<String>>>,
+}
+
+/// A dump of a template for a message or suggestion.
+#[derive(Debug, Serialize)]
+pub struct SynthesizerDump {
+    /// Whether the result is made title case if the replaced text is title case.
+    pub use_titlecase_adjust: bool,
+    /// The parts which are concatenated.
+    pub parts: Vec<SynthesizerPartDump>,
+}
+
+/// A dump of one part of a template.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SynthesizerPartDump {
+    /// A fixed string.
+    Text {
+        /// The string.
+        text: String,
+    },
+    /// The text of a matched group.
+    Match {
+        /// The ID of the group.
+        id: usize,
+        /// The case conversion applied to the text.
+        conversion: String,
+        /// The part-of-speech tags of the inflection the text is replaced with, if any.
+        pos_replacer: Option<Vec<String>>,
+        /// A regular expression and replacement applied to the text, if any.
+        regex_replacer: Option<(String, String)>,
+    },
+}
+
+/// A dump of an example of a grammatical error correction rule.
+#[derive(Debug, Serialize)]
+pub struct ExampleDump {
+    /// The text.
+    pub text: String,
+    /// The expected suggestion. `None` if the rule should not match.
+    pub suggestion: Option<Suggestion>,
+}
+
+/// A dump of a filter on the matches of a rule.
+#[derive(Debug, Serialize)]
+pub struct FilterDump {
+    /// The name of the filter.
+    pub name: String,
+    /// The ID of the group the filter is applied to.
+    pub id: usize,
+    /// The arguments of the filter.
+    pub args: Vec<(String, String)>,
+}
+
+/// A dump of the change made by a disambiguation rule.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DisambiguationDump {
+    /// Removes the readings matching the filter for each group.
+    Remove {
+        /// The readings or filters for each group.
+        data: Vec<ReadingFilterDump>,
+    },
+    /// Adds a reading for each group.
+    Add {
+        /// The readings.
+        data: Vec<ReadingDump>,
+    },
+    /// Replaces all readings with one reading for each group.
+    Replace {
+        /// The readings.
+        data: Vec<ReadingDump>,
+    },
+    /// Keeps only the readings matching the filter for each group.
+    Filter {
+        /// The readings or filters for each group. `None` if the group is not changed.
+        data: Vec<Option<ReadingFilterDump>>,
+    },
+    /// Keeps only the readings which are consistent across groups.
+    Unify {
+        /// The part-of-speech tags allowed by each filter of each feature.
+        filters: Vec<Vec<Vec<String>>>,
+        /// The part-of-speech tags of an additional filter for each group, if any.
+        disambig: Vec<Option<Vec<String>>>,
+        /// For each group whether it is unified.
+        mask: Vec<bool>,
+    },
+    /// Does not change anything.
+    Nop,
+}
+
+/// A reading or a set of part-of-speech tags selecting readings.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingFilterDump {
+    /// Selects readings equal to this one. An empty lemma matches any lemma.
+    Reading(ReadingDump),
+    /// Selects readings with one of these part-of-speech tags.
+    Pos(Vec<String>),
+}
+
+/// A dump of a reading of a word.
+#[derive(Debug, Serialize)]
+pub struct ReadingDump {
+    /// The lemma.
+    pub lemma: String,
+    /// The part-of-speech tag.
+    pub pos: String,
+}
+
+/// A dump of a word with its readings.
+#[derive(Debug, Serialize)]
+pub struct WordDump {
+    /// The text of the word.
+    pub text: String,
+    /// The readings of the word.
+    pub tags: Vec<ReadingDump>,
+}
+
+/// A dump of an example of a disambiguation rule.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DisambiguationExampleDump {
+    /// The rule should not change this text.
+    Unchanged {
+        /// The text.
+        text: String,
+    },
+    /// The rule should change a word in this text.
+    Changed {
+        /// The text.
+        text: String,
+        /// The char span of the changed word.
+        char_span: (usize, usize),
+        /// The word before the change.
+        before: WordDump,
+        /// The word after the change.
+        after: WordDump,
+    },
+}
+
+/// Dumps a tokenizer.
+pub fn dump_tokenizer(tokenizer: &Tokenizer) -> TokenizerDump {
+    let tagger = tokenizer.tagger();
+
+    TokenizerDump {
+        metadata: tokenizer.header().map(|header| header.metadata.clone()),
+        tagger: dump_tagger(tagger),
+        has_chunker: tokenizer.chunker().is_some(),
+        n_multiwords: tokenizer
+            .multiword_tagger
+            .as_ref()
+            .map_or(0, |x| x.multiwords.len()),
+        rules: tokenizer
+            .rules()
+            .iter()
+            .map(|rule| dump_disambiguation_rule(rule, tagger))
+            .collect(),
+    }
+}
+
+/// Dumps the statistics of a tagger.
+pub fn dump_tagger(tagger: &Tagger) -> TaggerDump {
+    let mut pos_tags: Vec<_> = tagger.tag_store().iter().collect();
+    pos_tags.sort_by_key(|(_, id)| **id);
+
+    TaggerDump {
+        representation: tagger.representation(),
+        fingerprint: tagger.fingerprint(),
+        n_words: tagger.n_words(),
+        n_entries: tagger.n_entries(),
+        pos_tags: pos_tags.into_iter().map(|(tag, _)| tag.clone()).collect(),
+    }
+}
+
+/// Dumps a rule set. The tokenizer is needed to resolve word and part-of-speech tag IDs.
+///
+/// # Errors
+/// If the rule set is not compatible with the tokenizer, see [Rules::check_compatible].
+pub fn dump_rules(rules: &Rules, tokenizer: &Tokenizer) -> Result<RulesDump, Error> {
+    rules.check_compatible(tokenizer)?;
+
+    Ok(RulesDump {
+        metadata: rules.header().map(|header| header.metadata.clone()),
+        rules: rules
+            .rules()
+            .iter()
+            .map(|rule| dump_rule(rule, tokenizer.tagger()))
+            .collect(),
+    })
+}
+
+/// Dumps a single grammatical error correction rule.
+/// The tagger must be the one of the tokenizer the rule was compiled for.
+pub fn dump_rule(rule: &Rule, tagger: &Tagger) -> RuleDump {
+    let details = rule.details.get();
+
+    RuleDump {
+        id: rule.id().to_string(),
+        name: details.name.clone(),
+        category_name: details.category_name.clone(),
+        category_type: details.category_type.clone(),
+        enabled: rule.enabled(),
+        engine: dump_engine(&rule.engine, tagger),
+        start: rule.start.0,
+        end: rule.end.0,
+        unification: rule
+            .unification
+            .as_ref()
+            .map(|x| dump_unification(x, tagger)),
+        message: dump_synthesizer(&details.message, tagger),
+        suggesters: details
+            .suggesters
+            .iter()
+            .map(|x| dump_synthesizer(x, tagger))
+            .collect(),
+        short: details.short.clone(),
+        url: details.url.clone(),
+        examples: details
+            .examples
+            .iter()
+            .map(|example| ExampleDump {
+                text: example.text().to_owned(),
+                suggestion: example.suggestion().cloned(),
+            })
+            .collect(),
+    }
+}
+
+/// Dumps a single disambiguation rule.
+/// The tagger must be the one of the tokenizer the rule belongs to.
+pub fn dump_disambiguation_rule(
+    rule: &DisambiguationRule,
+    tagger: &Tagger,
+) -> DisambiguationRuleDump {
+    DisambiguationRuleDump {
+        id: rule.id().to_string(),
+        engine: dump_engine(&rule.engine, tagger),
+        start: rule.start.0,
+        end: rule.end.0,
+        unification: rule
+            .unification
+            .as_ref()
+            .map(|x| dump_unification(x, tagger)),
+        filter: rule.filter.as_ref().map(dump_filter),
+        disambiguation: dump_disambiguation(&rule.disambiguations, tagger),
+        examples: rule
+            .examples
+            .iter()
+            .map(|example| match example {
+                DisambiguationExample::Unchanged(text) => {
+                    DisambiguationExampleDump::Unchanged { text: text.clone() }
+                }
+                DisambiguationExample::Changed(change) => DisambiguationExampleDump::Changed {
+                    text: change.text.clone(),
+                    char_span: change.char_span,
+                    before: dump_word(&change.before),
+                    after: dump_word(&change.after),
+                },
+            })
+            .collect(),
+    }
+}
+
+fn dump_engine(engine: &Engine, tagger: &Tagger) -> EngineDump {
+    match engine {
+        Engine::Token(engine) => EngineDump::Token {
+            pattern: dump_composition(&engine.composition, tagger),
+            antipatterns: engine
+                .antipatterns
+                .iter()
+                .map(|x| dump_composition(x, tagger))
+                .collect(),
+        },
+        Engine::Text(regex, _) => EngineDump::Text {
+            regex: regex.as_str().to_owned(),
+        },
+    }
+}
+
+fn dump_composition(composition: &Composition, tagger: &Tagger) -> Vec<PartDump> {
+    composition
+        .parts
+        .iter()
+        .map(|part| PartDump {
+            atom: dump_atom(&part.atom, tagger),
+            min: part.quantifier.min,
+            max: if part.quantifier.max == usize::MAX {
+                None
+            } else {
+                Some(part.quantifier.max)
+            },
+            greedy: part.greedy,
+            visible: part.visible,
+            unify: part.unify,
+        })
+        .collect()
+}
+
+fn dump_atom(atom: &Atom, tagger: &Tagger) -> AtomDump {
+    let dump_all =
+        |atoms: &[Atom]| -> Vec<AtomDump> { atoms.iter().map(|x| dump_atom(x, tagger)).collect() };
+
+    match atom {
+        Atom::TextAtom(atom) => AtomDump::Text {
+            matcher: dump_text_matcher(&atom.matcher, tagger),
+        },
+        Atom::ChunkAtom(atom) => AtomDump::Chunk {
+            matcher: dump_matcher(&atom.matcher),
+        },
+        Atom::SpaceBeforeAtom(atom) => AtomDump::SpaceBefore { value: atom.value },
+        Atom::WordDataAtom(atom) => AtomDump::WordData {
+            pos: atom
+                .matcher
+                .pos_matcher
+                .as_ref()
+                .map(|x| dump_pos_matcher(x, tagger)),
+            lemma: atom
+                .matcher
+                .inflect_matcher
+                .as_ref()
+                .map(|x| dump_text_matcher(x, tagger)),
+            case_sensitive: atom.case_sensitive,
+        },
+        Atom::TrueAtom(_) => AtomDump::True,
+        Atom::FalseAtom(_) => AtomDump::False,
+        Atom::AndAtom(x) => AtomDump::And {
+            atoms: dump_all(&x.atoms),
+        },
+        Atom::OrAtom(x) => AtomDump::Or {
+            atoms: dump_all(&x.atoms),
+        },
+        Atom::NotAtom(x) => AtomDump::Not {
+            atom: Box::new(dump_atom(&x.atom, tagger)),
+        },
+        Atom::OffsetAtom(x) => AtomDump::Offset {
+            atom: Box::new(dump_atom(&x.atom, tagger)),
+            offset: x.offset,
+        },
+    }
+}
+
+fn dump_matcher(matcher: &Matcher) -> MatcherDump {
+    let pattern = match &matcher.matcher {
+        either::Left(either::Left(string)) => PatternDump::Text(string.clone()),
+        either::Left(either::Right(id)) => PatternDump::Reference(id.0),
+        either::Right(regex) => PatternDump::Regex(regex.as_str().to_owned()),
+    };
+
+    MatcherDump {
+        pattern,
+        negate: matcher.negate,
+        case_sensitive: matcher.case_sensitive,
+        empty_always_false: matcher.empty_always_false,
+    }
+}
+
+fn dump_text_matcher(matcher: &TextMatcher, tagger: &Tagger) -> TextMatcherDump {
+    TextMatcherDump {
+        matcher: dump_matcher(&matcher.matcher),
+        words: matcher.set.as_ref().map(|set| {
+            let mut words: Vec<_> = set
+                .iter()
+                .map(|id| tagger.str_for_word_id(id).to_owned())
+                .collect();
+            words.sort();
+            words
+        }),
+    }
+}
+
+fn dump_pos_matcher(matcher: &PosMatcher, tagger: &Tagger) -> Vec<String> {
+    matcher
+        .mask
+        .iter()
+        .enumerate()
+        .filter(|(_, is_match)| **is_match)
+        .map(|(i, _)| tagger.str_for_pos_id(&PosIdInt(i as u16)).to_owned())
+        .collect()
+}
+
+fn dump_pos_filter(filter: &POSFilter, tagger: &Tagger) -> Vec<String> {
+    dump_pos_matcher(&filter.matcher, tagger)
+}
+
+fn dump_unification(unification: &Unification, tagger: &Tagger) -> UnificationDump {
+    UnificationDump {
+        mask: unification.mask.clone(),
+        filters: unification
+            .filters
+            .iter()
+            .map(|filters| filters.iter().map(|x| dump_pos_filter(x, tagger)).collect())
+            .collect(),
+    }
+}
+
+fn dump_synthesizer(synthesizer: &Synthesizer, tagger: &Tagger) -> SynthesizerDump {
+    SynthesizerDump {
+        use_titlecase_adjust: synthesizer.use_titlecase_adjust,
+        parts: synthesizer
+            .parts
+            .iter()
+            .map(|part| match part {
+                SynthesizerPart::Text(text) => SynthesizerPartDump::Text { text: text.clone() },
+                SynthesizerPart::Match(m) => SynthesizerPartDump::Match {
+                    id: m.id.0,
+                    conversion: match m.conversion {
+                        Conversion::Nop => "nop",
+                        Conversion::AllLower => "all_lower",
+                        Conversion::StartLower => "start_lower",
+                        Conversion::AllUpper => "all_upper",
+                        Conversion::StartUpper => "start_upper",
+                    }
+                    .to_owned(),
+                    pos_replacer: m
+                        .pos_replacer
+                        .as_ref()
+                        .map(|x| dump_pos_matcher(&x.matcher, tagger)),
+                    regex_replacer: m.regex_replacer.as_ref().map(|(regex, replacement)| {
+                        (regex.as_str().to_owned(), replacement.clone())
+                    }),
+                },
+            })
+            .collect(),
+    }
+}
+
+fn dump_filter(filter: &Filter) -> FilterDump {
+    match filter {
+        Filter::NoDisambiguationEnglishPartialPosTagFilter(filter) => FilterDump {
+            name: "NoDisambiguationEnglishPartialPosTagFilter".into(),
+            id: filter.id.0,
+            args: vec![
+                ("regexp".into(), filter.regexp.as_str().to_owned()),
+                (
+                    "postag_regexp".into(),
+                    filter.postag_regexp.as_str().to_owned(),
+                ),
+                ("negate_postag".into(), filter.negate_postag.to_string()),
+            ],
+        },
+    }
+}
+
+fn dump_reading(data: &owned::WordData) -> ReadingDump {
+    ReadingDump {
+        lemma: data.lemma.as_ref().to_owned(),
+        pos: data.pos.as_ref().to_owned(),
+    }
+}
+
+fn dump_word(word: &owned::Word) -> WordDump {
+    WordDump {
+        text: word.text.as_ref().to_owned(),
+        tags: word.tags.iter().map(dump_reading).collect(),
+    }
+}
+
+fn dump_reading_filter(
+    data_or_filter: &either::Either<owned::WordData, POSFilter>,
+    tagger: &Tagger,
+) -> ReadingFilterDump {
+    match data_or_filter {
+        either::Left(data) => ReadingFilterDump::Reading(dump_reading(data)),
+        either::Right(filter) => ReadingFilterDump::Pos(dump_pos_filter(filter, tagger)),
+    }
+}
+
+fn dump_disambiguation(disambiguation: &Disambiguation, tagger: &Tagger) -> DisambiguationDump {
+    match disambiguation {
+        Disambiguation::Remove(data) => DisambiguationDump::Remove {
+            data: data
+                .iter()
+                .map(|x| dump_reading_filter(x, tagger))
+                .collect(),
+        },
+        Disambiguation::Add(data) => DisambiguationDump::Add {
+            data: data.iter().map(dump_reading).collect(),
+        },
+        Disambiguation::Replace(data) => DisambiguationDump::Replace {
+            data: data.iter().map(dump_reading).collect(),
+        },
+        Disambiguation::Filter(data) => DisambiguationDump::Filter {
+            data: data
+                .iter()
+                .map(|x| x.as_ref().map(|x| dump_reading_filter(x, tagger)))
+                .collect(),
+        },
+        Disambiguation::Unify(filters, disambig, mask) => DisambiguationDump::Unify {
+            filters: filters
+                .iter()
+                .map(|filters| filters.iter().map(|x| dump_pos_filter(x, tagger)).collect())
+                .collect(),
+            disambig: disambig
+                .iter()
+                .map(|x| x.as_ref().map(|x| dump_pos_filter(x, tagger)))
+                .collect(),
+            mask: mask.clone(),
+        },
+        Disambiguation::Nop => DisambiguationDump::Nop,
+    }
+}
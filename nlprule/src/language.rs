@@ -0,0 +1,188 @@
+//! Bundles of a tokenizer and a rule set for one language in a single binary.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::Language;
+//!
+//! let language = Language::load("path/to/en_bundle.bin")?;
+//! assert_eq!(language.metadata().lang_code, "en");
+//!
+//! let suggestions = language.suggest("She was not been here since Monday.");
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{
+    binary::{self, BinaryKind, Header, Metadata},
+    rules::{RulesFields, RulesOptions},
+    tokenizer::TokenizerOptions,
+    types::Suggestion,
+    Error, Rules, Tokenizer,
+};
+use fs_err::File;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Read, Write},
+    path::Path,
+};
+
+#[derive(Deserialize)]
+struct BundleFields {
+    tokenizer: Tokenizer,
+    rules: RulesFields,
+    extra: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct BundleFieldsRef<'a> {
+    tokenizer: &'a Tokenizer,
+    rules: &'a Rules,
+    extra: &'a BTreeMap<String, String>,
+}
+
+/// A tokenizer and a compatible rule set for one language.
+/// Can be stored in and loaded from a single binary together with metadata.
+pub struct Language {
+    tokenizer: Tokenizer,
+    rules: Rules,
+    metadata: Metadata,
+    extra: BTreeMap<String, String>,
+    header: Option<Header>,
+}
+
+impl Language {
+    /// Creates a new language from a tokenizer and a rule set.
+    ///
+    /// # Errors
+    /// If the rule set is not compatible with the tokenizer, see [Rules::check_compatible].
+    pub fn new(tokenizer: Tokenizer, rules: Rules, metadata: Metadata) -> Result<Self, Error> {
+        rules.check_compatible(&tokenizer)?;
+
+        Ok(Language {
+            tokenizer,
+            rules,
+            metadata,
+            extra: BTreeMap::new(),
+            header: None,
+        })
+    }
+
+    /// Loads a language from a path to a bundle binary.
+    ///
+    /// # Errors
+    /// - If the file can not be opened.
+    /// - If the file is not a bundle binary compatible with this version of nlprule or is corrupted.
+    /// - If the file content can not be deserialized to a language.
+    pub fn load<P: AsRef<Path>>(p: P) -> Result<Self, Error> {
+        Language::load_with_options(p, TokenizerOptions::default(), RulesOptions::default())
+    }
+
+    /// Loads a language with options for the tokenizer and the rule set. See [load][Language::load].
+    pub fn load_with_options<P: AsRef<Path>>(
+        p: P,
+        tokenizer_options: TokenizerOptions,
+        rules_options: RulesOptions,
+    ) -> Result<Self, Error> {
+        let reader = BufReader::new(File::open(p.as_ref())?);
+        Language::from_reader_with_options(reader, tokenizer_options, rules_options)
+    }
+
+    /// Loads a language from a reader.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        Language::from_reader_with_options(
+            reader,
+            TokenizerOptions::default(),
+            RulesOptions::default(),
+        )
+    }
+
+    /// Loads a language from a reader with options for the tokenizer and the rule set.
+    pub fn from_reader_with_options<R: Read>(
+        reader: R,
+        tokenizer_options: TokenizerOptions,
+        rules_options: RulesOptions,
+    ) -> Result<Self, Error> {
+        let (header, payload) = binary::read(reader, BinaryKind::Bundle)?;
+        let fields: BundleFields = bincode::deserialize(&payload)?;
+
+        let mut tokenizer = fields.tokenizer;
+        tokenizer.set_options(tokenizer_options);
+        tokenizer.header = Some(header.clone());
+
+        let mut rules = Rules::from_fields(fields.rules, rules_options)?;
+        rules.header = Some(header.clone());
+
+        let mut language = Language::new(tokenizer, rules, header.metadata.clone())?;
+        language.extra = fields.extra;
+        language.header = Some(header);
+        Ok(language)
+    }
+
+    /// Writes this language as a bundle binary.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Error> {
+        binary::write(
+            writer,
+            BinaryKind::Bundle,
+            &self.metadata,
+            &BundleFieldsRef {
+                tokenizer: &self.tokenizer,
+                rules: &self.rules,
+                extra: &self.extra,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the tokenizer.
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    /// Gets the rule set.
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Gets the rule set (mutable).
+    pub fn rules_mut(&mut self) -> &mut Rules {
+        &mut self.rules
+    }
+
+    /// Gets information about the sources of this language, e. g. the language code.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Gets additional free-form metadata stored with this language.
+    pub fn extra(&self) -> &BTreeMap<String, String> {
+        &self.extra
+    }
+
+    /// Gets additional free-form metadata stored with this language (mutable).
+    pub fn extra_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.extra
+    }
+
+    /// Gets the header of the binary this language was loaded from.
+    /// `None` if the language was not loaded from a binary.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Splits this language into the tokenizer and the rule set.
+    pub fn into_parts(self) -> (Tokenizer, Rules) {
+        (self.tokenizer, self.rules)
+    }
+
+    /// Compute the suggestions for a text by checking all rules. See [Rules::suggest].
+    pub fn suggest(&self, text: &str) -> Vec<Suggestion> {
+        self.rules.suggest(text, &self.tokenizer)
+    }
+
+    /// Corrects the text by applying the first suggestion of each match. See [Rules::correct].
+    pub fn correct(&self, text: &str) -> String {
+        self.rules.correct(text, &self.tokenizer)
+    }
+}
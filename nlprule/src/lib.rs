@@ -58,17 +58,27 @@ use std::io;
 
 use thiserror::Error;
 
+pub mod binary;
 #[cfg(feature = "compile")]
 pub mod compile;
+pub mod diff;
+pub mod dsl;
+pub mod evaluate;
+pub mod export;
 mod filter;
+pub mod inspect;
+pub mod language;
+pub mod pattern;
 pub mod rule;
 pub mod rules;
 pub mod tokenizer;
 pub mod types;
 pub(crate) mod utils;
 
+pub use language::Language;
 pub use rules::Rules;
 pub use tokenizer::Tokenizer;
+pub use utils::parallelism::{with_parallelism, Parallelism};
 
 #[derive(Error, Debug)]
 #[allow(missing_docs)]
@@ -77,6 +87,45 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error("deserialization error: {0}")]
     Deserialization(#[from] bincode::Error),
+    #[error("not an nlprule binary")]
+    NotABinary,
+    #[error("binary has format version {found} (created by nlprule {crate_version}), but this version of nlprule requires format version {expected}")]
+    IncompatibleFormat {
+        found: u32,
+        expected: u32,
+        crate_version: String,
+    },
+    #[error("expected a {expected} binary, found a {found} binary")]
+    WrongBinaryKind {
+        expected: binary::BinaryKind,
+        found: binary::BinaryKind,
+    },
+    #[error(
+        "checksum mismatch (expected {expected:#x}, found {found:#x}): the binary is corrupted"
+    )]
+    ChecksumMismatch { expected: u64, found: u64 },
+    #[error("rules were compiled for a tagger with fingerprint {expected:#x}, but the tokenizer has fingerprint {found:#x}")]
+    IncompatibleTokenizer { expected: u64, found: u64 },
+    #[error("rule {id} can not be exported to XML: {reason}")]
+    Export { id: String, reason: String },
+    #[error(transparent)]
+    Regex(#[from] RegexError),
+    #[error("invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("invalid rule {id}: {reason}")]
+    InvalidRule { id: String, reason: String },
+    #[error("invalid M2 annotation in line {line}: {reason}")]
+    InvalidM2 { line: usize, reason: String },
+}
+
+/// An error encountered while compiling a regular expression.
+#[derive(Error, Debug, Clone)]
+#[error("invalid regex `{regex}`: {message}")]
+pub struct RegexError {
+    /// The pattern of the regex which failed to compile.
+    pub regex: String,
+    /// The error message of the regex backend.
+    pub message: String,
 }
 
 /// Gets the canonical filename for the tokenizer binary for a language code in ISO 639-1 (two-letter) format.
@@ -89,6 +138,11 @@ pub fn rules_filename(lang_code: &str) -> String {
     format!("{}_rules.bin", lang_code)
 }
 
+/// Gets the canonical filename for the bundle of a tokenizer and rules for a language code in ISO 639-1 (two-letter) format.
+pub fn bundle_filename(lang_code: &str) -> String {
+    format!("{}_bundle.bin", lang_code)
+}
+
 /// Gets the canonical filename for the tokenizer binary for a language code in ISO 639-1 (two-letter) format.
 #[macro_export]
 macro_rules! tokenizer_filename {
@@ -104,3 +158,11 @@ macro_rules! rules_filename {
         concat!($lang_code, "_rules.bin")
     };
 }
+
+/// Gets the canonical filename for the bundle of a tokenizer and rules for a language code in ISO 639-1 (two-letter) format.
+#[macro_export]
+macro_rules! bundle_filename {
+    ($lang_code:literal) => {
+        concat!($lang_code, "_bundle.bin")
+    };
+}
@@ -0,0 +1,619 @@
+//! Token patterns to find matches in the output of a [Tokenizer] without writing grammar rules.
+//!
+//! A [Pattern] is a sequence of parts, each matching one token (or a range of tokens if quantified) which
+//! fulfills the conditions of a [TokenSpec] on the text, lemmas, part-of-speech tags and chunks of the token.
+//! Parts can be named to get the span they matched as a *capture*. Patterns use the same engine as the grammar rules.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{pattern::{Pattern, TokenSpec}, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+//!
+//! let pattern = Pattern::builder()
+//!     .token(TokenSpec::new().lemma("be"))
+//!     .optional(TokenSpec::new().pos_regex("RB.*"))
+//!     .token(TokenSpec::new().pos_regex("JJ.*"))
+//!     .named("adjective")
+//!     .build(&tokenizer)?;
+//!
+//! let text = "The cat is very happy.";
+//! let matches = pattern.find(text, &tokenizer);
+//!
+//! assert_eq!(matches[0].span().text(text), "is very happy");
+//! assert_eq!(matches[0].capture("adjective").unwrap().text(text), "happy");
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use crate::{
+    rule::engine::composition::{
+        concrete, AndAtom, Atom, Composition, GraphId, Matcher, NotAtom, OrAtom, Part, PosMatcher,
+        Quantifier, TextMatcher, TrueAtom, WordDataMatcher,
+    },
+    tokenizer::tag::Tagger,
+    types::*,
+    utils::{budget::Budget, regex::Regex},
+    Error, Tokenizer,
+};
+
+/// A condition on a string.
+#[derive(Debug, Clone, PartialEq)]
+enum StrMatch {
+    Exact(String),
+    Regex(String),
+}
+
+impl StrMatch {
+    /// Creates a regex which has to match the full input.
+    fn full_match_regex(regex: &str, case_sensitive: bool) -> Result<Regex, Error> {
+        let flags = if case_sensitive { "" } else { "(?i)" };
+        let regex = Regex::new(format!("{}^(?:{})$", flags, regex));
+        regex.compile()?;
+
+        Ok(regex)
+    }
+
+    fn to_matcher(&self, case_sensitive: bool, empty_always_false: bool) -> Result<Matcher, Error> {
+        Ok(Matcher {
+            matcher: match self {
+                StrMatch::Exact(string) => either::Left(either::Left(string.clone())),
+                StrMatch::Regex(regex) => {
+                    either::Right(StrMatch::full_match_regex(regex, case_sensitive)?)
+                }
+            },
+            negate: false,
+            case_sensitive,
+            empty_always_false,
+        })
+    }
+}
+
+/// The conditions a token has to fulfill to match one part of a [Pattern].
+///
+/// A token matches if it fulfills all conditions, matches one of the alternatives (if there are any)
+/// and matches none of the exceptions. If the specification is [negated][TokenSpec::negate], the result is inverted.
+/// The default specification matches any token.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenSpec {
+    text: Option<StrMatch>,
+    lemma: Option<StrMatch>,
+    pos: Option<StrMatch>,
+    chunk: Option<StrMatch>,
+    space_before: Option<bool>,
+    case_sensitive: bool,
+    negate: bool,
+    alternatives: Vec<TokenSpec>,
+    exceptions: Vec<TokenSpec>,
+}
+
+impl TokenSpec {
+    /// Creates a new specification matching any token.
+    pub fn new() -> Self {
+        TokenSpec::default()
+    }
+
+    /// Creates a specification matching tokens which match any of the given specifications.
+    pub fn any_of<I: IntoIterator<Item = TokenSpec>>(alternatives: I) -> Self {
+        TokenSpec {
+            alternatives: alternatives.into_iter().collect(),
+            ..TokenSpec::default()
+        }
+    }
+
+    /// The text of the token must be equal to `text`.
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = Some(StrMatch::Exact(text.into()));
+        self
+    }
+
+    /// The text of the token must fully match the regular expression.
+    pub fn text_regex<S: Into<String>>(mut self, regex: S) -> Self {
+        self.text = Some(StrMatch::Regex(regex.into()));
+        self
+    }
+
+    /// Some reading of the token must have this lemma. If a part-of-speech tag is set too, both have to match in the same reading.
+    pub fn lemma<S: Into<String>>(mut self, lemma: S) -> Self {
+        self.lemma = Some(StrMatch::Exact(lemma.into()));
+        self
+    }
+
+    /// Some reading of the token must have a lemma which fully matches the regular expression.
+    pub fn lemma_regex<S: Into<String>>(mut self, regex: S) -> Self {
+        self.lemma = Some(StrMatch::Regex(regex.into()));
+        self
+    }
+
+    /// Some reading of the token must have this part-of-speech tag. The tag must be known to the tagger.
+    pub fn pos<S: Into<String>>(mut self, pos: S) -> Self {
+        self.pos = Some(StrMatch::Exact(pos.into()));
+        self
+    }
+
+    /// Some reading of the token must have a part-of-speech tag which fully matches the regular expression.
+    /// Tags are always matched case-sensitively.
+    pub fn pos_regex<S: Into<String>>(mut self, regex: S) -> Self {
+        self.pos = Some(StrMatch::Regex(regex.into()));
+        self
+    }
+
+    /// One of the chunks of the token must be equal to `chunk`.
+    pub fn chunk<S: Into<String>>(mut self, chunk: S) -> Self {
+        self.chunk = Some(StrMatch::Exact(chunk.into()));
+        self
+    }
+
+    /// One of the chunks of the token must fully match the regular expression.
+    pub fn chunk_regex<S: Into<String>>(mut self, regex: S) -> Self {
+        self.chunk = Some(StrMatch::Regex(regex.into()));
+        self
+    }
+
+    /// Whether the token must (`true`) or must not (`false`) be preceded by whitespace.
+    pub fn space_before(mut self, space_before: bool) -> Self {
+        self.space_before = Some(space_before);
+        self
+    }
+
+    /// Whether text and lemmas are compared case-sensitively. Defaults to `false`.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// The token must not match `exception`.
+    pub fn except(mut self, exception: TokenSpec) -> Self {
+        self.exceptions.push(exception);
+        self
+    }
+
+    /// Inverts the specification: tokens match if they would not match otherwise.
+    pub fn negate(mut self) -> Self {
+        self.negate = !self.negate;
+        self
+    }
+
+    fn pos_matcher(pos: &StrMatch, tagger: &Tagger) -> Result<PosMatcher, Error> {
+        let mut mask = vec![false; tagger.tag_store().len()];
+
+        match pos {
+            StrMatch::Exact(tag) => {
+                let id = tagger.tag_store().get_by_left(tag).ok_or_else(|| {
+                    Error::InvalidPattern(format!("unknown part-of-speech tag `{}`", tag))
+                })?;
+                mask[id.0 as usize] = true;
+            }
+            StrMatch::Regex(regex) => {
+                let regex = StrMatch::full_match_regex(regex, true)?;
+
+                for (tag, id) in tagger.tag_store().iter() {
+                    // the empty tag never matches, like in the grammar rules
+                    mask[id.0 as usize] = !tag.is_empty() && regex.is_match(tag);
+                }
+            }
+        }
+
+        Ok(PosMatcher { mask })
+    }
+
+    pub(crate) fn to_atom(&self, tagger: &Tagger) -> Result<Atom, Error> {
+        let mut atoms = Vec::new();
+
+        if let Some(text) = &self.text {
+            atoms.push(Atom::TextAtom(concrete::TextAtom {
+                matcher: TextMatcher {
+                    matcher: text.to_matcher(self.case_sensitive, false)?,
+                    set: None,
+                },
+            }));
+        }
+
+        if self.lemma.is_some() || self.pos.is_some() {
+            let inflect_matcher = match &self.lemma {
+                Some(lemma) => Some(TextMatcher {
+                    matcher: lemma.to_matcher(self.case_sensitive, true)?,
+                    set: None,
+                }),
+                None => None,
+            };
+            let pos_matcher = match &self.pos {
+                Some(pos) => Some(TokenSpec::pos_matcher(pos, tagger)?),
+                None => None,
+            };
+
+            atoms.push(Atom::WordDataAtom(concrete::WordDataAtom {
+                matcher: WordDataMatcher {
+                    pos_matcher,
+                    inflect_matcher,
+                },
+                case_sensitive: self.case_sensitive,
+            }));
+        }
+
+        if let Some(chunk) = &self.chunk {
+            atoms.push(Atom::ChunkAtom(concrete::ChunkAtom {
+                matcher: chunk.to_matcher(true, true)?,
+            }));
+        }
+
+        if let Some(value) = self.space_before {
+            atoms.push(Atom::SpaceBeforeAtom(concrete::SpaceBeforeAtom { value }));
+        }
+
+        if !self.alternatives.is_empty() {
+            atoms.push(Atom::OrAtom(OrAtom {
+                atoms: self
+                    .alternatives
+                    .iter()
+                    .map(|x| x.to_atom(tagger))
+                    .collect::<Result<_, _>>()?,
+            }));
+        }
+
+        if !self.exceptions.is_empty() {
+            atoms.push(Atom::NotAtom(NotAtom {
+                atom: Box::new(Atom::OrAtom(OrAtom {
+                    atoms: self
+                        .exceptions
+                        .iter()
+                        .map(|x| x.to_atom(tagger))
+                        .collect::<Result<_, _>>()?,
+                })),
+            }));
+        }
+
+        let atom = match atoms.len() {
+            0 => Atom::TrueAtom(TrueAtom {}),
+            1 => atoms.remove(0),
+            _ => Atom::AndAtom(AndAtom { atoms }),
+        };
+
+        Ok(if self.negate {
+            Atom::NotAtom(NotAtom {
+                atom: Box::new(atom),
+            })
+        } else {
+            atom
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PartSpec {
+    token: TokenSpec,
+    min: usize,
+    max: usize,
+    name: Option<String>,
+}
+
+/// Builds a [Pattern] part by part. Created with [Pattern::builder].
+#[derive(Debug, Clone, Default)]
+pub struct PatternBuilder {
+    parts: Vec<PartSpec>,
+}
+
+impl PatternBuilder {
+    /// Adds a part matching exactly one token.
+    pub fn token(self, token: TokenSpec) -> Self {
+        self.repeat(token, 1, 1)
+    }
+
+    /// Adds a part matching zero or one tokens.
+    pub fn optional(self, token: TokenSpec) -> Self {
+        self.repeat(token, 0, 1)
+    }
+
+    /// Adds a part matching at least `min` and at most `max` consecutive tokens.
+    /// As many tokens as possible are matched.
+    pub fn repeat(mut self, token: TokenSpec, min: usize, max: usize) -> Self {
+        self.parts.push(PartSpec {
+            token,
+            min,
+            max,
+            name: None,
+        });
+        self
+    }
+
+    /// Names the last added part. The span it matched can then be retrieved with [PatternMatch::capture].
+    ///
+    /// # Panics
+    /// If no part has been added yet.
+    pub fn named<S: Into<String>>(mut self, name: S) -> Self {
+        self.parts
+            .last_mut()
+            .expect("a part must be added before it can be named")
+            .name = Some(name.into());
+        self
+    }
+
+    pub(crate) fn composition(&self, tagger: &Tagger) -> Result<Composition, Error> {
+        if self.parts.iter().all(|x| x.min == 0) {
+            return Err(Error::InvalidPattern(
+                "pattern must contain a part which matches at least one token".into(),
+            ));
+        }
+
+        let mut parts = Vec::new();
+        for (i, part) in self.parts.iter().enumerate() {
+            if part.max == 0 || part.min > part.max {
+                return Err(Error::InvalidPattern(format!(
+                    "part {} has invalid quantifier {{{}, {}}}",
+                    i, part.min, part.max
+                )));
+            }
+
+            parts.push(Part {
+                atom: part.token.to_atom(tagger)?,
+                quantifier: Quantifier {
+                    min: part.min,
+                    max: part.max,
+                },
+                greedy: true,
+                visible: true,
+                unify: None,
+            });
+        }
+
+        let can_stop_mask = (0..parts.len())
+            .map(|i| parts[i..].iter().all(|x| x.quantifier.min == 0))
+            .collect();
+        let id_to_idx = (0..=parts.len()).map(|i| (GraphId(i), i)).collect();
+        let tag_windows = parts.iter().map(|x| x.atom.tag_window()).collect();
+
+        Ok(Composition {
+            parts,
+            id_to_idx,
+            can_stop_mask,
+            uses_graph: false,
+            tag_windows,
+        })
+    }
+
+    /// Builds the pattern. The pattern can only be used on tokens of this tokenizer.
+    ///
+    /// # Errors
+    /// - If a regular expression is invalid.
+    /// - If a part-of-speech tag is not known to the tagger of the tokenizer.
+    /// - If the pattern could match zero tokens, a quantifier is invalid or a name is used twice.
+    pub fn build(self, tokenizer: &Tokenizer) -> Result<Pattern, Error> {
+        let composition = self.composition(tokenizer.tagger())?;
+        let names: Vec<_> = self.parts.into_iter().map(|x| x.name).collect();
+
+        for (i, name) in names.iter().enumerate() {
+            if let Some(name) = name {
+                if names[..i].contains(&Some(name.clone())) {
+                    return Err(Error::InvalidPattern(format!(
+                        "name `{}` is used more than once",
+                        name
+                    )));
+                }
+            }
+        }
+
+        Ok(Pattern { composition, names })
+    }
+}
+
+/// A span of text, e. g. of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The start (inclusive) and end (exclusive) character index.
+    pub char_span: (usize, usize),
+    /// The start (inclusive) and end (exclusive) byte index.
+    pub byte_span: (usize, usize),
+}
+
+impl Span {
+    /// Gets the text of the span. `text` must be the text the span refers to.
+    pub fn text<'t>(&self, text: &'t str) -> &'t str {
+        &text[self.byte_span.0..self.byte_span.1]
+    }
+
+    /// Shift the span to the right by the specified amount of characters and bytes.
+    pub fn rshift(&mut self, chars: usize, bytes: usize) {
+        self.char_span = (self.char_span.0 + chars, self.char_span.1 + chars);
+        self.byte_span = (self.byte_span.0 + bytes, self.byte_span.1 + bytes);
+    }
+
+    fn from_tokens(tokens: &[&Token]) -> Option<Self> {
+        let first = tokens.first()?;
+        let last = tokens.last()?;
+
+        Some(Span {
+            char_span: (first.char_span.0, last.char_span.1),
+            byte_span: (first.byte_span.0, last.byte_span.1),
+        })
+    }
+}
+
+/// A match of a [Pattern].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternMatch<'p> {
+    span: Span,
+    parts: Vec<Option<Span>>,
+    names: &'p [Option<String>],
+}
+
+impl<'p> PatternMatch<'p> {
+    /// Gets the span of the entire match.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Gets the span of the tokens matched by the part at `index`. `None` if the part did not match any tokens.
+    pub fn part(&self, index: usize) -> Option<Span> {
+        self.parts.get(index).copied().flatten()
+    }
+
+    /// Gets the span of the tokens matched by the part named `name`.
+    /// `None` if there is no such part or it did not match any tokens.
+    pub fn capture(&self, name: &str) -> Option<Span> {
+        self.names
+            .iter()
+            .position(|x| x.as_deref() == Some(name))
+            .and_then(|index| self.part(index))
+    }
+
+    /// Gets the names and spans of all named parts which matched at least one token.
+    pub fn captures(&self) -> impl Iterator<Item = (&'p str, Span)> + '_ {
+        self.names
+            .iter()
+            .zip(&self.parts)
+            .filter_map(|(name, span)| Some((name.as_deref()?, (*span)?)))
+    }
+
+    fn rshift(&mut self, chars: usize, bytes: usize) {
+        self.span.rshift(chars, bytes);
+        for span in self.parts.iter_mut().flatten() {
+            span.rshift(chars, bytes);
+        }
+    }
+}
+
+/// A pattern of tokens. Built with a [PatternBuilder].
+#[derive(Debug)]
+pub struct Pattern {
+    composition: Composition,
+    names: Vec<Option<String>>,
+}
+
+impl Pattern {
+    /// Creates a builder for a new pattern.
+    pub fn builder() -> PatternBuilder {
+        PatternBuilder::default()
+    }
+
+    /// Finds the matches in a sentence, e. g. one of the sentences returned by [Tokenizer::pipe].
+    /// The spans are relative to the sentence. Matches do not overlap: like with regular expressions,
+    /// searching continues after the end of a match. The tokens must be created by the tokenizer the pattern was built with.
+    pub fn apply<'p, 't>(&'p self, tokens: &'t [Token<'t>]) -> Matches<'p, 't> {
+        Matches {
+            pattern: self,
+            tokens,
+            position: 0,
+            budget: Budget::default(),
+        }
+    }
+
+    /// Finds the matches in a text. The spans are relative to the text.
+    pub fn find(&self, text: &str, tokenizer: &Tokenizer) -> Vec<PatternMatch<'_>> {
+        let mut matches = Vec::new();
+        let mut char_offset = 0;
+        let mut byte_offset = 0;
+
+        for tokens in tokenizer.pipe(text) {
+            if tokens.is_empty() {
+                continue;
+            }
+
+            matches.extend(self.apply(&tokens).map(|mut x| {
+                x.rshift(char_offset, byte_offset);
+                x
+            }));
+
+            let sentence = tokens[0].sentence;
+            char_offset += sentence.chars().count();
+            byte_offset += sentence.len();
+        }
+
+        matches
+    }
+}
+
+/// An iterator over the matches of a [Pattern] in a sentence. Created with [Pattern::apply].
+pub struct Matches<'p, 't> {
+    pattern: &'p Pattern,
+    tokens: &'t [Token<'t>],
+    position: usize,
+    budget: Budget,
+}
+
+impl<'p, 't> Iterator for Matches<'p, 't> {
+    type Item = PatternMatch<'p>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tokens = self.tokens;
+
+        while self.position < tokens.len() {
+            let start = self.position;
+            self.position += 1;
+
+            // special tokens with zero range (e. g. SENT_START) can not be part of matches
+            if tokens[start].char_span.0 == tokens[start].char_span.1 {
+                continue;
+            }
+
+            let graph = match self
+                .pattern
+                .composition
+                .apply(tokens, start, &self.budget, None)
+            {
+                Some(graph) => graph,
+                None => continue,
+            };
+
+            let groups: Vec<Vec<_>> = graph.groups()[1..]
+                .iter()
+                .map(|group| group.tokens(tokens).collect())
+                .collect();
+            let parts: Vec<_> = groups.iter().map(|x| Span::from_tokens(x)).collect();
+            let span =
+                Span::from_tokens(&groups.concat()).expect("pattern matches at least one token");
+
+            while self.position < tokens.len()
+                && tokens[self.position].char_span.0 < span.char_span.1
+            {
+                self.position += 1;
+            }
+
+            return Some(PatternMatch {
+                span,
+                parts,
+                names: &self.pattern.names,
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(builder: PatternBuilder) -> Result<Pattern, Error> {
+        builder.build(&Tokenizer::default())
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let is_invalid =
+            |result: Result<Pattern, Error>| matches!(result, Err(Error::InvalidPattern(_)));
+
+        assert!(is_invalid(build(Pattern::builder())));
+        assert!(is_invalid(build(
+            Pattern::builder().optional(TokenSpec::new().text("a"))
+        )));
+        assert!(is_invalid(build(Pattern::builder().repeat(
+            TokenSpec::new(),
+            2,
+            1
+        ))));
+        assert!(is_invalid(build(
+            Pattern::builder()
+                .token(TokenSpec::new())
+                .named("a")
+                .token(TokenSpec::new())
+                .named("a")
+        )));
+        assert!(is_invalid(build(
+            Pattern::builder().token(TokenSpec::new().pos("NOT_A_TAG"))
+        )));
+        assert!(matches!(
+            build(Pattern::builder().token(TokenSpec::new().text_regex("("))),
+            Err(Error::Regex(_))
+        ));
+    }
+}
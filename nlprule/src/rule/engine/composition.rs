@@ -1,10 +1,14 @@
-use crate::{types::*, utils::regex::Regex};
+use crate::{
+    types::*,
+    utils::{budget::Budget, ordered, regex::Regex},
+};
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use unicase::UniCase;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Matcher {
     pub matcher: either::Either<either::Either<String, GraphId>, Regex>,
     pub negate: bool,
@@ -13,6 +17,19 @@ pub struct Matcher {
 }
 
 impl Matcher {
+    /// Whether this matcher compares against the text of a previously matched group.
+    pub(crate) fn uses_graph(&self) -> bool {
+        matches!(self.matcher, either::Left(either::Right(_)))
+    }
+
+    pub(crate) fn regex(&self) -> Option<&Regex> {
+        if let either::Right(regex) = &self.matcher {
+            Some(regex)
+        } else {
+            None
+        }
+    }
+
     pub fn is_slice_match<S: AsRef<str>>(
         &self,
         input: &[S],
@@ -71,6 +88,7 @@ impl Matcher {
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct TextMatcher {
     pub(crate) matcher: Matcher,
+    #[serde(serialize_with = "ordered::option_set")]
     pub(crate) set: Option<DefaultHashSet<WordIdInt>>,
 }
 
@@ -94,6 +112,27 @@ impl TextMatcher {
                 .is_match(word_id.as_ref(), graph, case_sensitive)
         }
     }
+
+    fn requirement(&self) -> Option<Requirement<'_>> {
+        match (&self.set, &self.matcher.matcher) {
+            (Some(set), _) => Some(Requirement::Words(set, &self.matcher)),
+            (None, either::Left(either::Left(string)))
+                if !self.matcher.negate && !string.is_empty() =>
+            {
+                Some(Requirement::Text(string))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A condition a token has to fulfill for some atom to match it.
+pub(crate) enum Requirement<'a> {
+    /// The text of the token has to be equal to this string, ignoring case.
+    Text(&'a str),
+    /// The token has to be one of these known words.
+    /// Tokens which are not in the word store have to be checked with the matcher.
+    Words(&'a DefaultHashSet<WordIdInt>, &'a Matcher),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -167,6 +206,99 @@ pub enum Atom {
     OffsetAtom,
 }
 
+impl Atom {
+    /// Gets requirements of which at least one has to be fulfilled by some token in the sentence for this atom to match.
+    /// Returns `None` if there are no such requirements.
+    pub(crate) fn requirements(&self) -> Option<Vec<Requirement<'_>>> {
+        match self {
+            Atom::TextAtom(atom) => atom.matcher.requirement().map(|x| vec![x]),
+            Atom::AndAtom(x) => x
+                .atoms
+                .iter()
+                .filter_map(Atom::requirements)
+                .min_by_key(|x| x.len()),
+            Atom::OrAtom(x) => x
+                .atoms
+                .iter()
+                .map(Atom::requirements)
+                .collect::<Option<Vec<_>>>()
+                .map(|x| x.into_iter().flatten().collect()),
+            // the inner atom has to match a token in the sentence too, just at a different position
+            Atom::OffsetAtom(x) => x.atom.requirements(),
+            _ => None,
+        }
+    }
+
+    /// Whether this atom or one of its children depends on previously matched groups.
+    pub(crate) fn uses_graph(&self) -> bool {
+        match self {
+            Atom::ChunkAtom(atom) => atom.matcher.uses_graph(),
+            Atom::TextAtom(atom) => atom.matcher.matcher.uses_graph(),
+            Atom::WordDataAtom(atom) => atom
+                .matcher
+                .inflect_matcher
+                .as_ref()
+                .map_or(false, |x| x.matcher.uses_graph()),
+            Atom::SpaceBeforeAtom(_) | Atom::TrueAtom(_) | Atom::FalseAtom(_) => false,
+            Atom::AndAtom(x) => x.atoms.iter().any(Atom::uses_graph),
+            Atom::OrAtom(x) => x.atoms.iter().any(Atom::uses_graph),
+            Atom::NotAtom(x) => x.atom.uses_graph(),
+            Atom::OffsetAtom(x) => x.atom.uses_graph(),
+        }
+    }
+
+    /// Gets the range of offsets relative to the matched position of the tokens whose tags this atom reads.
+    /// Returns `None` if the atom does not depend on tags at all. Tags are the only part of a token changed by disambiguation.
+    pub(crate) fn tag_window(&self) -> Option<(isize, isize)> {
+        let union = |atoms: &[Atom]| {
+            atoms.iter().filter_map(Atom::tag_window).fold(
+                None,
+                |acc: Option<(isize, isize)>, (lo, hi)| {
+                    Some(acc.map_or((lo, hi), |(acc_lo, acc_hi)| {
+                        (acc_lo.min(lo), acc_hi.max(hi))
+                    }))
+                },
+            )
+        };
+
+        match self {
+            Atom::WordDataAtom(_) => Some((0, 0)),
+            Atom::ChunkAtom(_)
+            | Atom::TextAtom(_)
+            | Atom::SpaceBeforeAtom(_)
+            | Atom::TrueAtom(_)
+            | Atom::FalseAtom(_) => None,
+            Atom::AndAtom(x) => union(&x.atoms),
+            Atom::OrAtom(x) => union(&x.atoms),
+            Atom::NotAtom(x) => x.atom.tag_window(),
+            Atom::OffsetAtom(x) => x
+                .atom
+                .tag_window()
+                .map(|(lo, hi)| (lo + x.offset, hi + x.offset)),
+        }
+    }
+
+    /// Gets all regexes used by this atom and its children.
+    pub(crate) fn regexes(&self) -> Vec<&Regex> {
+        match self {
+            Atom::ChunkAtom(atom) => atom.matcher.regex().into_iter().collect(),
+            Atom::TextAtom(atom) => atom.matcher.matcher.regex().into_iter().collect(),
+            Atom::WordDataAtom(atom) => atom
+                .matcher
+                .inflect_matcher
+                .as_ref()
+                .and_then(|x| x.matcher.regex())
+                .into_iter()
+                .collect(),
+            Atom::SpaceBeforeAtom(_) | Atom::TrueAtom(_) | Atom::FalseAtom(_) => Vec::new(),
+            Atom::AndAtom(x) => x.atoms.iter().flat_map(Atom::regexes).collect(),
+            Atom::OrAtom(x) => x.atoms.iter().flat_map(Atom::regexes).collect(),
+            Atom::NotAtom(x) => x.atom.regexes(),
+            Atom::OffsetAtom(x) => x.atom.regexes(),
+        }
+    }
+}
+
 pub mod concrete {
     use super::{Atomable, MatchGraph, Matcher, TextMatcher, Token, WordDataMatcher};
     use serde::{Deserialize, Serialize};
@@ -461,17 +593,97 @@ pub struct Part {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Composition {
     pub(crate) parts: Vec<Part>,
+    #[serde(serialize_with = "ordered::map")]
     pub(crate) id_to_idx: DefaultHashMap<GraphId, usize>,
     pub(crate) can_stop_mask: Vec<bool>,
+    pub(crate) uses_graph: bool,
+    /// The [tag window][Atom::tag_window] of the atom of each part.
+    pub(crate) tag_windows: Vec<Option<(isize, isize)>>,
+}
+
+/// Records the tokens whose tags were read while matching.
+/// Used to find out whether the result of matching can change if the tags of some tokens change.
+pub(crate) struct TagReads {
+    mask: Vec<Cell<bool>>,
+}
+
+impl TagReads {
+    pub fn new(n_tokens: usize) -> Self {
+        TagReads {
+            mask: vec![Cell::new(false); n_tokens],
+        }
+    }
+
+    fn record(&self, position: usize, window: (isize, isize)) {
+        let start = (position as isize + window.0).max(0) as usize;
+        let end = (position as isize + window.1 + 1).min(self.mask.len() as isize);
+
+        for i in start..end.max(0) as usize {
+            self.mask[i].set(true);
+        }
+    }
+
+    /// Records that the tags of all tokens within the char span were read.
+    pub fn record_span(&self, tokens: &[Token], char_span: (usize, usize)) {
+        for (read, token) in self.mask.iter().zip(tokens) {
+            if token.char_span.0 >= char_span.0 && token.char_span.1 <= char_span.1 {
+                read.set(true);
+            }
+        }
+    }
+
+    /// Gets a mask of the tokens whose tags were read.
+    pub fn into_mask(self) -> Vec<bool> {
+        self.mask.into_iter().map(Cell::into_inner).collect()
+    }
+}
+
+/// A thread in the automaton simulated by [Composition::apply].
+/// The state of the automaton is the index of the current part and how often it has matched so far.
+struct Thread<'t> {
+    atom_idx: usize,
+    count: usize,
+    graph: MatchGraph<'t>,
 }
 
 impl Composition {
+    pub(crate) fn regexes(&self) -> Vec<&Regex> {
+        self.parts.iter().flat_map(|x| x.atom.regexes()).collect()
+    }
+
+    /// Gets requirements of which at least one has to be fulfilled by some token in the sentence for this composition to match.
+    /// Every part which has to match at least once imposes requirements, the most specific ones are returned.
+    pub(crate) fn requirements(&self) -> Option<Vec<Requirement<'_>>> {
+        self.parts
+            .iter()
+            .filter(|x| x.quantifier.min > 0)
+            .filter_map(|x| x.atom.requirements())
+            .min_by_key(|x| x.len())
+    }
+
+    /// Checks whether the atom of the part at `index` matches the token at `position`, recording the reads.
+    fn part_matches(
+        &self,
+        index: usize,
+        tokens: &[Token],
+        graph: &MatchGraph,
+        position: usize,
+        reads: Option<&TagReads>,
+    ) -> bool {
+        if let (Some(reads), Some(window)) = (reads, self.tag_windows[index]) {
+            reads.record(position, window);
+        }
+
+        self.parts[index].atom.is_match(tokens, graph, position)
+    }
+
     fn next_can_match(
         &self,
         tokens: &[Token],
         graph: &MatchGraph,
         position: usize,
         index: usize,
+        reads: Option<&TagReads>,
     ) -> bool {
         let next_required_pos = match self.parts[index + 1..]
             .iter()
@@ -481,56 +693,96 @@ impl Composition {
             None => self.parts.len(),
         };
 
-        self.parts[index + 1..next_required_pos]
-            .iter()
-            .any(|x| x.atom.is_match(tokens, graph, position))
+        (index + 1..next_required_pos).any(|i| self.part_matches(i, tokens, graph, position, reads))
+    }
+
+    /// Checks whether the composition accepts if no further tokens are consumed by the thread.
+    fn accept<'t>(
+        &self,
+        mut atom_idx: usize,
+        count: usize,
+        graph: MatchGraph<'t>,
+    ) -> Option<MatchGraph<'t>> {
+        // the current part is done if its minimum has been reached
+        if atom_idx < self.parts.len() && count >= self.parts[atom_idx].quantifier.min {
+            atom_idx += 1;
+        }
+
+        if atom_idx == self.parts.len() || self.can_stop_mask[atom_idx] {
+            Some(graph)
+        } else {
+            None
+        }
     }
 
-    fn apply_recursive<'t>(
+    /// Follows all transitions of the thread at `position` which do not consume a token in order of priority.
+    /// Threads consuming the token at `position` are pushed to `next`, ordered by priority.
+    ///
+    /// Returns the graph if the thread reaches an accepting state before consuming another token.
+    fn step<'t>(
         &'t self,
         tokens: &'t [Token<'t>],
-        mut position: usize,
-        mut cur_atom_idx: usize,
-        mut graph: MatchGraph<'t>,
+        position: usize,
+        thread: Thread<'t>,
+        next: &mut Vec<Thread<'t>>,
+        visited: &mut Option<Vec<(usize, usize)>>,
+        reads: Option<&TagReads>,
     ) -> Option<MatchGraph<'t>> {
-        let mut cur_count = 0;
-        let is_match = loop {
-            if cur_atom_idx >= self.parts.len() {
-                break true;
+        let Thread {
+            mut atom_idx,
+            mut count,
+            mut graph,
+        } = thread;
+
+        loop {
+            if atom_idx >= self.parts.len() {
+                return Some(graph);
             }
 
-            let part = &self.parts[cur_atom_idx];
-
-            if cur_count >= part.quantifier.max {
-                cur_atom_idx += 1;
-                cur_count = 0;
-                if cur_atom_idx >= self.parts.len() {
-                    break false;
+            // a thread with higher priority has already been in this state at this position.
+            // since the state alone determines what happens from here, this thread can not lead to a new match
+            if let Some(visited) = visited.as_mut() {
+                if visited.contains(&(atom_idx, count)) {
+                    return None;
                 }
+                visited.push((atom_idx, count));
+            }
+
+            let part = &self.parts[atom_idx];
+
+            if count >= part.quantifier.max {
+                atom_idx += 1;
+                count = 0;
                 continue;
             }
 
             if position >= tokens.len() {
-                break false;
+                return self.accept(atom_idx, count, graph);
             }
 
-            if cur_count >= part.quantifier.min && cur_atom_idx + 1 < self.parts.len() {
-                if !part.greedy && self.next_can_match(tokens, &graph, position, cur_atom_idx) {
-                    cur_atom_idx += 1;
-                    cur_count = 0;
+            if count >= part.quantifier.min && atom_idx + 1 < self.parts.len() {
+                if !part.greedy && self.next_can_match(tokens, &graph, position, atom_idx, reads) {
+                    atom_idx += 1;
+                    count = 0;
                     continue;
                 }
+
+                // moving on to the next part takes priority over consuming another token with the current one
                 if part.greedy {
-                    if let Some(graph) =
-                        self.apply_recursive(tokens, position, cur_atom_idx + 1, graph.clone())
-                    {
+                    let thread = Thread {
+                        atom_idx: atom_idx + 1,
+                        count: 0,
+                        graph: graph.clone(),
+                    };
+
+                    if let Some(graph) = self.step(tokens, position, thread, next, visited, reads) {
                         return Some(graph);
                     }
                 }
             }
 
-            if part.atom.is_match(tokens, &graph, position) {
-                let mut group = &mut graph.groups[cur_atom_idx + 1];
+            return if self.part_matches(atom_idx, tokens, &graph, position, reads) {
+                let group = &mut graph.groups[atom_idx + 1];
 
                 // set the group beginning if the char end was zero (i. e. the group was empty)
                 if group.char_span.1 == 0 {
@@ -538,28 +790,28 @@ impl Composition {
                 }
                 group.char_span.1 = tokens[position].char_span.1;
 
-                position += 1;
-                cur_count += 1;
+                next.push(Thread {
+                    atom_idx,
+                    count: count + 1,
+                    graph,
+                });
+                None
             } else {
-                break false;
-            }
-        };
-
-        // edge case if the last atom is quantified and the minimum has been exceeded
-        // TODO to avoid further such undetected edge cases: revisit the entire composition logic
-        if cur_atom_idx < self.parts.len() && cur_count >= self.parts[cur_atom_idx].quantifier.min {
-            cur_atom_idx += 1;
-        }
-
-        if is_match || cur_atom_idx == self.parts.len() || self.can_stop_mask[cur_atom_idx] {
-            graph.fill_empty();
-            Some(graph)
-        } else {
-            None
+                self.accept(atom_idx, count, graph)
+            };
         }
     }
 
-    pub fn apply<'t>(&'t self, tokens: &'t [Token<'t>], start: usize) -> Option<MatchGraph<'t>> {
+    /// Matches the composition starting at `start`. Returns `None` if the composition does not match
+    /// or the budget is exhausted before it is decided whether it matches.
+    /// If `reads` is set, the tokens whose tags were read are recorded in it.
+    pub fn apply<'t>(
+        &'t self,
+        tokens: &'t [Token<'t>],
+        start: usize,
+        budget: &Budget,
+        reads: Option<&TagReads>,
+    ) -> Option<MatchGraph<'t>> {
         // this path is extremely hot so more optimizations are done
 
         // the first matcher can never rely on the match graph, so we use an empty default graph for the first match
@@ -569,19 +821,297 @@ impl Composition {
         };
 
         if self.parts[0].quantifier.min > 0
-            && !self.parts[0].atom.is_match(tokens, &DEFAULT_GRAPH, start)
+            && !self.part_matches(0, tokens, &DEFAULT_GRAPH, start, reads)
         {
             return None;
         }
 
-        let position = start;
-
         let graph = MatchGraph::new(
             vec![Group::default(); self.parts.len() + 1],
             &self.id_to_idx,
             tokens,
         );
 
-        self.apply_recursive(tokens, position, 0, graph)
+        // simulate the automaton in one pass over the tokens, keeping all threads ordered by priority.
+        // this is equivalent to backtracking in order of priority but the work is bounded by the number of states.
+        let mut threads = vec![Thread {
+            atom_idx: 0,
+            count: 0,
+            graph,
+        }];
+        let mut result = None;
+        let mut position = start;
+
+        while !threads.is_empty() {
+            if !budget.step(threads.len()) {
+                return None;
+            }
+
+            let mut next = Vec::new();
+            // states can only be deduplicated if the atoms do not depend on the captured groups
+            let mut visited = if self.uses_graph {
+                None
+            } else {
+                Some(Vec::new())
+            };
+
+            for thread in threads {
+                if let Some(graph) =
+                    self.step(tokens, position, thread, &mut next, &mut visited, reads)
+                {
+                    // threads with lower priority can not change the result anymore, threads with
+                    // higher priority in `next` still can if they reach an accepting state later
+                    result = Some(graph);
+                    break;
+                }
+            }
+
+            threads = next;
+            position += 1;
+        }
+
+        result.map(|mut graph| {
+            graph.fill_empty();
+            graph
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tag::Tagger;
+    use quickcheck_macros::quickcheck;
+
+    impl Composition {
+        /// The backtracking implementation the automaton in [Composition::apply] replaced. Used as reference.
+        fn apply_backtracking<'t>(
+            &'t self,
+            tokens: &'t [Token<'t>],
+            mut position: usize,
+            mut cur_atom_idx: usize,
+            mut graph: MatchGraph<'t>,
+        ) -> Option<MatchGraph<'t>> {
+            let mut cur_count = 0;
+            let is_match = loop {
+                if cur_atom_idx >= self.parts.len() {
+                    break true;
+                }
+
+                let part = &self.parts[cur_atom_idx];
+
+                if cur_count >= part.quantifier.max {
+                    cur_atom_idx += 1;
+                    cur_count = 0;
+                    if cur_atom_idx >= self.parts.len() {
+                        break false;
+                    }
+                    continue;
+                }
+
+                if position >= tokens.len() {
+                    break false;
+                }
+
+                if cur_count >= part.quantifier.min && cur_atom_idx + 1 < self.parts.len() {
+                    if !part.greedy
+                        && self.next_can_match(tokens, &graph, position, cur_atom_idx, None)
+                    {
+                        cur_atom_idx += 1;
+                        cur_count = 0;
+                        continue;
+                    }
+                    if part.greedy {
+                        if let Some(graph) = self.apply_backtracking(
+                            tokens,
+                            position,
+                            cur_atom_idx + 1,
+                            graph.clone(),
+                        ) {
+                            return Some(graph);
+                        }
+                    }
+                }
+
+                if part.atom.is_match(tokens, &graph, position) {
+                    let group = &mut graph.groups[cur_atom_idx + 1];
+
+                    if group.char_span.1 == 0 {
+                        group.char_span.0 = tokens[position].char_span.0;
+                    }
+                    group.char_span.1 = tokens[position].char_span.1;
+
+                    position += 1;
+                    cur_count += 1;
+                } else {
+                    break false;
+                }
+            };
+
+            if cur_atom_idx < self.parts.len()
+                && cur_count >= self.parts[cur_atom_idx].quantifier.min
+            {
+                cur_atom_idx += 1;
+            }
+
+            if is_match || cur_atom_idx == self.parts.len() || self.can_stop_mask[cur_atom_idx] {
+                graph.fill_empty();
+                Some(graph)
+            } else {
+                None
+            }
+        }
+    }
+
+    fn text_atom(text: &str) -> Atom {
+        Atom::TextAtom(concrete::TextAtom {
+            matcher: TextMatcher {
+                matcher: Matcher {
+                    matcher: either::Left(either::Left(text.to_string())),
+                    negate: false,
+                    case_sensitive: true,
+                    empty_always_false: false,
+                },
+                set: None,
+            },
+        })
+    }
+
+    fn composition(pattern: &[(u8, u8, u8, bool)], uses_graph: bool) -> Composition {
+        let parts: Vec<_> = pattern
+            .iter()
+            .enumerate()
+            .map(|(i, (kind, min, extra, greedy))| {
+                let atom = match kind % 4 {
+                    0 => text_atom("a"),
+                    1 => text_atom("b"),
+                    2 => Atom::TrueAtom(TrueAtom {}),
+                    _ => Atom::OrAtom(OrAtom {
+                        atoms: vec![text_atom("a"), text_atom("c")],
+                    }),
+                };
+                // the first part has to match at least once, otherwise the match might not contain any tokens
+                let min = if i == 0 { 1 } else { (*min % 3) as usize };
+
+                Part {
+                    atom,
+                    quantifier: Quantifier {
+                        min,
+                        max: min + (*extra % 3) as usize,
+                    },
+                    greedy: *greedy,
+                    visible: true,
+                    unify: None,
+                }
+            })
+            .collect();
+
+        let can_stop_mask = (0..parts.len())
+            .map(|i| parts[i..].iter().all(|x| x.quantifier.min == 0))
+            .collect();
+        let id_to_idx = (0..=parts.len()).map(|i| (GraphId(i), i)).collect();
+        let tag_windows = parts.iter().map(|x| x.atom.tag_window()).collect();
+
+        Composition {
+            parts,
+            id_to_idx,
+            can_stop_mask,
+            uses_graph,
+            tag_windows,
+        }
+    }
+
+    fn tokens<'t>(text: &'t str, tagger: &'t Tagger) -> Vec<Token<'t>> {
+        let token = |text: &'t str, char_span| Token {
+            word: Word::new_with_tags(WordId(text.into(), None), Vec::new()),
+            char_span,
+            byte_span: char_span,
+            has_space_before: false,
+            chunks: Vec::new(),
+            sentence: text,
+            tagger,
+        };
+
+        std::iter::once(token("", (0, 0)))
+            .chain((0..text.len()).map(|i| token(&text[i..i + 1], (i, i + 1))))
+            .collect()
+    }
+
+    fn spans(graph: Option<MatchGraph>) -> Option<Vec<(usize, usize)>> {
+        graph.map(|x| x.groups().iter().map(|x| x.char_span).collect())
+    }
+
+    #[test]
+    fn tag_window_covers_offsets() {
+        let word_data = || {
+            Atom::WordDataAtom(concrete::WordDataAtom {
+                matcher: WordDataMatcher {
+                    pos_matcher: None,
+                    inflect_matcher: None,
+                },
+                case_sensitive: false,
+            })
+        };
+        let offset = |atom, offset| {
+            Atom::OffsetAtom(OffsetAtom {
+                atom: Box::new(atom),
+                offset,
+            })
+        };
+
+        assert_eq!(text_atom("a").tag_window(), None);
+        assert_eq!(word_data().tag_window(), Some((0, 0)));
+        assert_eq!(
+            Atom::OrAtom(OrAtom {
+                atoms: vec![
+                    text_atom("a"),
+                    offset(word_data(), -2),
+                    offset(offset(word_data(), 1), 2)
+                ]
+            })
+            .tag_window(),
+            Some((-2, 3))
+        );
+    }
+
+    #[quickcheck]
+    fn automaton_matches_like_backtracking(
+        pattern: Vec<(u8, u8, u8, bool)>,
+        text: Vec<u8>,
+    ) -> bool {
+        if pattern.is_empty() {
+            return true;
+        }
+
+        let text: String = text
+            .iter()
+            .map(|x| ['a', 'b', 'c'][*x as usize % 3])
+            .collect();
+        let tagger = Tagger::default();
+        let tokens = tokens(&text, &tagger);
+
+        [false, true].iter().all(|uses_graph| {
+            let composition = composition(&pattern, *uses_graph);
+
+            (1..tokens.len()).all(|start| {
+                let expected =
+                    if composition.parts[0]
+                        .atom
+                        .is_match(&tokens, &MatchGraph::default(), start)
+                    {
+                        let graph = MatchGraph::new(
+                            vec![Group::default(); composition.parts.len() + 1],
+                            &composition.id_to_idx,
+                            &tokens,
+                        );
+                        composition.apply_backtracking(&tokens, start, 0, graph)
+                    } else {
+                        None
+                    };
+
+                spans(composition.apply(&tokens, start, &Budget::default(), None))
+                    == spans(expected)
+            })
+        })
     }
 }
@@ -1,11 +1,15 @@
 use crate::{
     types::*,
-    utils::regex::{CaptureMatches, Regex},
+    utils::{
+        budget::Budget,
+        ordered,
+        regex::{CaptureMatches, Regex},
+    },
 };
 use serde::{Deserialize, Serialize};
 pub mod composition;
 
-use composition::{Composition, Group, MatchGraph};
+use composition::{Composition, Group, MatchGraph, TagReads};
 
 use self::composition::GraphId;
 
@@ -16,54 +20,106 @@ pub struct TokenEngine {
 }
 
 impl TokenEngine {
-    fn get_match<'t>(&'t self, tokens: &'t [Token], i: usize) -> Option<MatchGraph<'t>> {
-        if let Some(graph) = self.composition.apply(tokens, i) {
-            let mut blocked = false;
-
-            // TODO: cache / move to outer loop
-            for i in 0..tokens.len() {
-                for antipattern in &self.antipatterns {
-                    if let Some(anti_graph) = antipattern.apply(tokens, i) {
-                        let anti_start = anti_graph.by_index(0).char_span.0;
-                        let anti_end = anti_graph
-                            .by_index(anti_graph.groups().len() - 1)
-                            .char_span
-                            .1;
+    /// Computes the index of the antipattern and the char span of all antipattern matches in the sentence.
+    fn antipattern_matches(
+        &self,
+        tokens: &[Token],
+        budget: &Budget,
+        reads: Option<&TagReads>,
+    ) -> Vec<(usize, (usize, usize))> {
+        let mut matches = Vec::new();
 
-                        let rule_start = graph.by_index(0).char_span.0;
-                        let rule_end = graph.by_index(graph.groups().len() - 1).char_span.1;
+        for i in 0..tokens.len() {
+            for (index, antipattern) in self.antipatterns.iter().enumerate() {
+                if let Some(anti_graph) = antipattern.apply(tokens, i, budget, reads) {
+                    let anti_start = anti_graph.by_index(0).char_span.0;
+                    let anti_end = anti_graph
+                        .by_index(anti_graph.groups().len() - 1)
+                        .char_span
+                        .1;
 
-                        if anti_start <= rule_end && rule_start <= anti_end {
-                            blocked = true;
-                            break;
-                        }
-                    }
-                }
-                if blocked {
-                    break;
+                    matches.push((index, (anti_start, anti_end)));
                 }
             }
+        }
+
+        matches
+    }
 
-            if !blocked {
-                return Some(graph);
+    fn get_match<'t>(
+        &'t self,
+        tokens: &'t [Token],
+        i: usize,
+        blocked: &mut Option<Vec<(usize, (usize, usize))>>,
+        budget: &Budget,
+        reads: Option<&TagReads>,
+    ) -> Option<MatchGraph<'t>> {
+        let graph = self.composition.apply(tokens, i, budget, reads)?;
+
+        if self.antipatterns.is_empty() {
+            return Some(graph);
+        }
+
+        // antipatterns are only evaluated once the composition matches for the first time,
+        // most rules never match so computing them upfront would be wasted work
+        if blocked.is_none() {
+            let matches = self.antipattern_matches(tokens, budget, reads);
+
+            // a partial list could let matches through which an antipattern blocks, so it is not used or cached
+            if budget.exhausted() {
+                return None;
             }
+
+            *blocked = Some(matches);
         }
+        let blocked = blocked.as_ref().expect("antipattern matches are computed");
+
+        let rule_start = graph.by_index(0).char_span.0;
+        let rule_end = graph.by_index(graph.groups().len() - 1).char_span.1;
 
-        None
+        if blocked
+            .iter()
+            .any(|(_, (anti_start, anti_end))| *anti_start <= rule_end && rule_start <= *anti_end)
+        {
+            None
+        } else {
+            Some(graph)
+        }
     }
 }
 
+/// Why a match of an [Engine] is not returned by [get_matches][Engine::get_matches].
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Rejection {
+    /// The match overlaps the antipattern match with this index in [Candidates::antipatterns].
+    Antipattern(usize),
+    /// The marked part of the match overlaps the marked part of a previous match.
+    Overlap,
+}
+
+/// All matches of an [Engine] in a sentence, including the rejected ones.
+pub(crate) struct Candidates<'t> {
+    /// The index of the antipattern and the char span of all antipattern matches.
+    pub antipatterns: Vec<(usize, (usize, usize))>,
+    pub matches: Vec<(MatchGraph<'t>, Option<Rejection>)>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Engine {
     Token(TokenEngine),
     // regex with the `fancy_regex` backend is large on the stack
-    Text(Box<Regex>, DefaultHashMap<GraphId, usize>),
+    Text(
+        Box<Regex>,
+        #[serde(serialize_with = "ordered::map")] DefaultHashMap<GraphId, usize>,
+    ),
 }
 
 struct TokenMatches<'a> {
     engine: &'a TokenEngine,
     index: usize,
     mask: Vec<bool>,
+    // antipattern matches, computed once per sentence on first use
+    blocked: Option<Vec<(usize, (usize, usize))>>,
 }
 
 struct TextMatches<'a, 't> {
@@ -81,6 +137,8 @@ pub struct EngineMatches<'a, 't> {
     tokens: &'t [Token<'t>],
     start: GraphId,
     end: GraphId,
+    budget: &'a Budget,
+    reads: Option<&'a TagReads>,
     inner: InnerMatches<'a, 't>,
 }
 
@@ -91,57 +149,146 @@ impl<'a, 't> Iterator for EngineMatches<'a, 't> {
         let tokens = self.tokens;
         let start_id = self.start;
         let end_id = self.end;
+        let budget = self.budget;
+        let reads = self.reads;
+
+        if budget.exhausted() {
+            return None;
+        }
 
         match &mut self.inner {
             InnerMatches::Token(inner) => (inner.index..tokens.len()).find_map(|i| {
-                inner.engine.get_match(tokens, i).and_then(|graph| {
-                    let start_group = graph.by_id(start_id);
-                    let end_group = graph.by_id(end_id);
-
-                    let start = start_group.char_span.0;
-                    let end = end_group.char_span.1;
-
-                    if inner.mask[start..end].iter().all(|x| !x) {
-                        inner.mask[start..end].iter_mut().for_each(|x| *x = true);
-
-                        inner.index += 1;
-                        Some(graph)
-                    } else {
-                        None
-                    }
-                })
-            }),
-            InnerMatches::Text(inner) => inner.captures.next().map(|captures| {
-                let bi_to_ci = &inner.byte_idx_to_char_idx;
-                let mut groups = Vec::new();
-
-                for group in captures.iter() {
-                    if let Some(group) = group {
-                        let start = *bi_to_ci
-                            .get(&group.start())
-                            .expect("byte index is at char boundary");
-                        let end = *bi_to_ci
-                            .get(&group.end())
-                            .expect("byte index is at char boundary");
-
-                        groups.push(Group::new((start, end)));
-                    } else {
-                        groups.push(Group::new((0, 0)));
-                    }
-                }
+                inner
+                    .engine
+                    .get_match(tokens, i, &mut inner.blocked, budget, reads)
+                    .and_then(|graph| {
+                        let start_group = graph.by_id(start_id);
+                        let end_group = graph.by_id(end_id);
+
+                        let start = start_group.char_span.0;
+                        let end = end_group.char_span.1;
 
-                MatchGraph::new(groups, inner.id_to_idx, tokens)
+                        if inner.mask[start..end].iter().all(|x| !x) {
+                            inner.mask[start..end].iter_mut().for_each(|x| *x = true);
+
+                            inner.index += 1;
+                            Some(graph)
+                        } else {
+                            None
+                        }
+                    })
             }),
+            // a single regex search can not be interrupted so only the number of matches is limited
+            InnerMatches::Text(inner) => {
+                inner
+                    .captures
+                    .next()
+                    .filter(|_| budget.step(1))
+                    .map(|captures| {
+                        let bi_to_ci = &inner.byte_idx_to_char_idx;
+                        let mut groups = Vec::new();
+
+                        for group in captures.iter() {
+                            if let Some(group) = group {
+                                let start = *bi_to_ci
+                                    .get(&group.start())
+                                    .expect("byte index is at char boundary");
+                                let end = *bi_to_ci
+                                    .get(&group.end())
+                                    .expect("byte index is at char boundary");
+
+                                groups.push(Group::new((start, end)));
+                            } else {
+                                groups.push(Group::new((0, 0)));
+                            }
+                        }
+
+                        MatchGraph::new(groups, inner.id_to_idx, tokens)
+                    })
+            }
         }
     }
 }
 
 impl Engine {
+    pub(crate) fn regexes(&self) -> Vec<&Regex> {
+        match &self {
+            Engine::Token(engine) => engine
+                .composition
+                .regexes()
+                .into_iter()
+                .chain(engine.antipatterns.iter().flat_map(Composition::regexes))
+                .collect(),
+            Engine::Text(regex, _) => vec![regex.as_ref()],
+        }
+    }
+
+    /// Gets all matches in the sentence like [get_matches][Engine::get_matches], but keeps the matches
+    /// which are rejected together with the reason. Only meant for debugging.
+    pub(crate) fn candidates<'t>(
+        &'t self,
+        tokens: &'t [Token<'t>],
+        start: GraphId,
+        end: GraphId,
+        budget: &'t Budget,
+    ) -> Candidates<'t> {
+        assert!(!tokens.is_empty());
+
+        match &self {
+            Engine::Token(engine) => {
+                let antipatterns = engine.antipattern_matches(tokens, budget, None);
+                let mut mask = vec![false; tokens[0].sentence.chars().count()];
+
+                let matches = (0..tokens.len())
+                    .filter_map(|i| {
+                        let graph = engine.composition.apply(tokens, i, budget, None)?;
+
+                        let rule_start = graph.by_index(0).char_span.0;
+                        let rule_end = graph.by_index(graph.groups().len() - 1).char_span.1;
+
+                        let rejection = if let Some(index) =
+                            antipatterns.iter().position(|(_, (anti_start, anti_end))| {
+                                *anti_start <= rule_end && rule_start <= *anti_end
+                            }) {
+                            Some(Rejection::Antipattern(index))
+                        } else {
+                            let start = graph.by_id(start).char_span.0;
+                            let end = graph.by_id(end).char_span.1;
+
+                            if mask[start..end].iter().all(|x| !x) {
+                                mask[start..end].iter_mut().for_each(|x| *x = true);
+                                None
+                            } else {
+                                Some(Rejection::Overlap)
+                            }
+                        };
+
+                        Some((graph, rejection))
+                    })
+                    .collect();
+
+                Candidates {
+                    antipatterns,
+                    matches,
+                }
+            }
+            Engine::Text(_, _) => Candidates {
+                antipatterns: Vec::new(),
+                matches: self
+                    .get_matches(tokens, start, end, budget, None)
+                    .map(|graph| (graph, None))
+                    .collect(),
+            },
+        }
+    }
+
     pub fn get_matches<'a, 't>(
         &'a self,
         tokens: &'t [Token],
         start: GraphId,
         end: GraphId,
+        budget: &'a Budget,
+        reads: Option<&'a TagReads>,
     ) -> EngineMatches<'a, 't> {
         assert!(!tokens.is_empty()); // this has to be checked before calling `get_matches`
 
@@ -149,11 +296,14 @@ impl Engine {
             tokens,
             start,
             end,
+            budget,
+            reads,
             inner: match &self {
                 Engine::Token(engine) => InnerMatches::Token(TokenMatches {
                     engine,
                     index: 0,
                     mask: vec![false; tokens[0].sentence.chars().count()],
+                    blocked: None,
                 }),
                 Engine::Text(regex, id_to_idx) => {
                     let sentence = tokens[0].sentence;
@@ -0,0 +1,483 @@
+//! Explanations of how a rule is applied to a sentence.
+//!
+//! An [Explanation] shows which parts of the pattern of a rule match each token, which matches were
+//! found, which of them were blocked by antipatterns, overlaps or unification and how the message and the
+//! suggestions were synthesized. It is meant to find out why a rule produces a false positive (or why it does not match).
+//! Computing an explanation is much slower than applying the rule.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use nlprule::{Rules, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+//! let rules = Rules::new("path/to/en_rules.bin")?;
+//!
+//! for explanation in rules.explain("She was not been here since Monday.", &tokenizer) {
+//!     println!("{}: {:#?}", explanation.rule, explanation.candidates);
+//! }
+//! # Ok::<(), nlprule::Error>(())
+//! ```
+
+use super::{
+    engine::{
+        composition::{Atom, Atomable, GraphId, MatchGraph, Matcher, Part, PosMatcher},
+        Engine, Rejection,
+    },
+    grammar::{Synthesizer, SynthesizerPart},
+    Rule,
+};
+use crate::{
+    tokenizer::{tag::Tagger, Tokenizer},
+    types::*,
+    utils::budget::Budget,
+};
+use serde::{Deserialize, Serialize};
+
+/// Part-of-speech matchers matching more tags than this are described by the number of tags.
+const MAX_DESCRIBED_TAGS: usize = 8;
+
+/// How a rule was applied to one sentence. All char spans are relative to the text the explanation was computed for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Explanation {
+    /// The ID of the rule.
+    pub rule: String,
+    /// The text of the sentence.
+    pub sentence: String,
+    /// The char span of the sentence.
+    pub char_span: (usize, usize),
+    /// A description of each part of the pattern. Rules matching a regular expression on the text have one part.
+    pub pattern: Vec<String>,
+    /// The tokens of the sentence.
+    pub tokens: Vec<TokenExplanation>,
+    /// The matches of the antipatterns of the rule.
+    pub antipatterns: Vec<AntipatternMatch>,
+    /// The matches of the pattern, in the order they were found.
+    pub candidates: Vec<Candidate>,
+}
+
+/// A token of the sentence and the parts of the pattern matching it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenExplanation {
+    /// The text of the token.
+    pub text: String,
+    /// The char span of the token.
+    pub char_span: (usize, usize),
+    /// For each part of the pattern, whether it matches this token on its own.
+    /// `None` if the part refers to other groups of the match and can only be evaluated within a match.
+    pub parts: Vec<Option<bool>>,
+}
+
+/// A match of an antipattern. Matches of the pattern overlapping it are blocked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AntipatternMatch {
+    /// The index of the antipattern in the rule.
+    pub index: usize,
+    /// The char span of the match.
+    pub char_span: (usize, usize),
+    /// The matched text.
+    pub text: String,
+}
+
+/// A group of a match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupExplanation {
+    /// The ID messages and suggestions use to refer to this group (e. g. `\1`), if there is one.
+    pub id: Option<usize>,
+    /// The index of the part of the pattern which matched this group, see [Explanation::pattern].
+    /// `None` for the groups of regular expressions.
+    pub part: Option<usize>,
+    /// The char span of the group.
+    pub char_span: (usize, usize),
+    /// The texts of the tokens in the group.
+    pub tokens: Vec<String>,
+}
+
+/// What happened to a match of the pattern.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The match resulted in a suggestion.
+    Suggested,
+    /// The match overlaps the antipattern match with this index in [Explanation::antipatterns].
+    Antipattern(usize),
+    /// The marked part of the match overlaps the marked part of a previous match.
+    Overlap,
+    /// The tokens marked for unification do not agree.
+    Unification,
+    /// No suggestion could be synthesized or all suggestions are equal to the matched text.
+    Unchanged,
+    /// The suggestion overlaps the suggestion of the rule with this ID, which takes precedence.
+    /// Only set by [Rules::explain][crate::rules::Rules::explain].
+    Superseded(String),
+}
+
+/// A match of the pattern of a rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    /// The char span of the match.
+    pub char_span: (usize, usize),
+    /// The groups of the match.
+    pub groups: Vec<GroupExplanation>,
+    /// Whether the unification accepted the match. `None` if the rule has no unification or the match was
+    /// rejected before it was evaluated.
+    pub unification: Option<bool>,
+    /// How the message was synthesized. `None` if the match was rejected before synthesis.
+    pub message: Option<Synthesis>,
+    /// How each suggestion was synthesized. Empty if the match was rejected before synthesis.
+    pub suggesters: Vec<Synthesis>,
+    /// The resulting suggestion, if there is one.
+    pub suggestion: Option<Suggestion>,
+    /// What happened to the match.
+    pub outcome: Outcome,
+}
+
+/// How a message or suggestion was synthesized from a match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Synthesis {
+    /// The parts which are joined to form the output.
+    pub parts: Vec<SynthesisPart>,
+    /// The output after whitespace normalization and case adjustment. `None` if some part could not be synthesized.
+    pub output: Option<String>,
+}
+
+/// A part of a [Synthesis].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SynthesisPart {
+    /// Literal text.
+    Text(String),
+    /// Text derived from a group of the match.
+    Match {
+        /// The ID of the group.
+        id: usize,
+        /// The text of the group.
+        text: String,
+        /// The text after part-of-speech replacement, regex replacement and case conversion.
+        /// `None` if no word with the requested part-of-speech tag exists.
+        output: Option<String>,
+    },
+}
+
+impl Explanation {
+    /// Shift all char spans to the right by the specified amount.
+    pub fn rshift(&mut self, offset: usize) {
+        self.char_span.0 += offset;
+        self.char_span.1 += offset;
+
+        for token in self.tokens.iter_mut() {
+            token.char_span.0 += offset;
+            token.char_span.1 += offset;
+        }
+
+        for antipattern in self.antipatterns.iter_mut() {
+            antipattern.char_span.0 += offset;
+            antipattern.char_span.1 += offset;
+        }
+
+        for candidate in self.candidates.iter_mut() {
+            candidate.char_span.0 += offset;
+            candidate.char_span.1 += offset;
+
+            for group in candidate.groups.iter_mut() {
+                group.char_span.0 += offset;
+                group.char_span.1 += offset;
+            }
+
+            if let Some(suggestion) = candidate.suggestion.as_mut() {
+                suggestion.rshift(offset);
+            }
+        }
+    }
+}
+
+fn text_of(sentence: &str, char_span: (usize, usize)) -> String {
+    sentence
+        .chars()
+        .skip(char_span.0)
+        .take(char_span.1.saturating_sub(char_span.0))
+        .collect()
+}
+
+fn describe_matcher(key: &str, matcher: &Matcher) -> String {
+    let value = match &matcher.matcher {
+        either::Left(either::Left(string)) => format!("{:?}", string),
+        either::Left(either::Right(id)) => format!("\\{}", id.0),
+        either::Right(regex) => format!("/{}/", regex.as_str()),
+    };
+
+    format!(
+        "{}{}{}",
+        key,
+        if matcher.negate { "!=" } else { "=" },
+        value
+    )
+}
+
+fn describe_pos(matcher: &PosMatcher, tagger: &Tagger) -> String {
+    let tags = |value: bool| {
+        matcher
+            .mask
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| **x == value)
+            .map(|(i, _)| tagger.str_for_pos_id(&PosIdInt(i as u16)))
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+    };
+
+    let (matched, unmatched) = (tags(true), tags(false));
+    // negated matchers are stored as the set of matched tags, the complement is usually shorter
+    let (operator, tags) = if unmatched.len() < matched.len() {
+        ("!=", unmatched)
+    } else {
+        ("=", matched)
+    };
+
+    if tags.len() > MAX_DESCRIBED_TAGS {
+        format!("pos{}<{} tags>", operator, tags.len())
+    } else {
+        format!("pos{}{}", operator, tags.join("|"))
+    }
+}
+
+fn join(atoms: &[Atom], separator: &str, tagger: &Tagger) -> String {
+    match atoms {
+        [atom] => describe_atom(atom, tagger),
+        atoms => format!(
+            "({})",
+            atoms
+                .iter()
+                .map(|x| describe_atom(x, tagger))
+                .collect::<Vec<_>>()
+                .join(separator)
+        ),
+    }
+}
+
+/// Gets a short human-readable description of an atom e. g. `text="have" & pos=VB|VBP`.
+fn describe_atom(atom: &Atom, tagger: &Tagger) -> String {
+    match atom {
+        Atom::TextAtom(atom) => describe_matcher("text", &atom.matcher.matcher),
+        Atom::ChunkAtom(atom) => describe_matcher("chunk", &atom.matcher),
+        Atom::SpaceBeforeAtom(atom) => format!("space_before={}", atom.value),
+        Atom::WordDataAtom(atom) => {
+            let mut parts = Vec::new();
+            if let Some(matcher) = &atom.matcher.inflect_matcher {
+                parts.push(describe_matcher("lemma", &matcher.matcher));
+            }
+            if let Some(matcher) = &atom.matcher.pos_matcher {
+                parts.push(describe_pos(matcher, tagger));
+            }
+
+            if parts.is_empty() {
+                "*".into()
+            } else {
+                parts.join(" & ")
+            }
+        }
+        Atom::TrueAtom(_) => "*".into(),
+        Atom::FalseAtom(_) => "!*".into(),
+        Atom::AndAtom(x) => join(&x.atoms, " & ", tagger),
+        Atom::OrAtom(x) => join(&x.atoms, " | ", tagger),
+        Atom::NotAtom(x) => format!("!({})", describe_atom(&x.atom, tagger)),
+        Atom::OffsetAtom(x) => format!("({})@{:+}", describe_atom(&x.atom, tagger), x.offset),
+    }
+}
+
+fn describe_part(part: &Part, tagger: &Tagger) -> String {
+    let quantifier = match (part.quantifier.min, part.quantifier.max) {
+        (1, 1) => "".into(),
+        (0, 1) => "?".into(),
+        (0, usize::MAX) => "*".into(),
+        (1, usize::MAX) => "+".into(),
+        (min, usize::MAX) => format!("{{{},}}", min),
+        (min, max) => format!("{{{},{}}}", min, max),
+    };
+
+    format!("[{}]{}", describe_atom(&part.atom, tagger), quantifier)
+}
+
+fn synthesis(
+    synthesizer: &Synthesizer,
+    graph: &MatchGraph,
+    tokenizer: &Tokenizer,
+    start: GraphId,
+    end: GraphId,
+) -> Synthesis {
+    let sentence = graph.tokens()[0].sentence;
+
+    Synthesis {
+        parts: synthesizer
+            .parts
+            .iter()
+            .map(|part| match part {
+                SynthesizerPart::Text(text) => SynthesisPart::Text(text.clone()),
+                SynthesizerPart::Match(m) => SynthesisPart::Match {
+                    id: m.id.0,
+                    text: graph.by_id(m.id).text(sentence).to_owned(),
+                    output: m.apply(graph, tokenizer),
+                },
+            })
+            .collect(),
+        output: synthesizer.apply(graph, tokenizer, start, end),
+    }
+}
+
+/// Explains how the rule is applied to the tokens of one sentence. Char spans are relative to the sentence.
+pub(crate) fn explain_sentence(
+    rule: &Rule,
+    tokens: &[Token],
+    tokenizer: &Tokenizer,
+) -> Explanation {
+    assert!(!tokens.is_empty());
+
+    let tagger = tokenizer.tagger();
+    let sentence = tokens[0].sentence;
+    let empty_graph = MatchGraph::default();
+
+    let (pattern, parts, id_to_idx): (Vec<_>, &[Part], _) = match &rule.engine {
+        Engine::Token(engine) => (
+            engine
+                .composition
+                .parts
+                .iter()
+                .map(|part| describe_part(part, tagger))
+                .collect(),
+            &engine.composition.parts[..],
+            &engine.composition.id_to_idx,
+        ),
+        Engine::Text(regex, id_to_idx) => {
+            (vec![format!("/{}/", regex.as_str())], &[][..], id_to_idx)
+        }
+    };
+
+    let token_explanations = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| TokenExplanation {
+            text: token.word.text.as_ref().to_owned(),
+            char_span: token.char_span,
+            parts: parts
+                .iter()
+                .map(|part| {
+                    if part.atom.uses_graph() {
+                        None
+                    } else {
+                        Some(part.atom.is_match(tokens, &empty_graph, i))
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    let budget = Budget::default();
+    let candidates = rule
+        .engine
+        .candidates(tokens, rule.start, rule.end, &budget);
+
+    let antipatterns = candidates
+        .antipatterns
+        .iter()
+        .map(|(index, char_span)| AntipatternMatch {
+            index: *index,
+            char_span: *char_span,
+            text: text_of(sentence, *char_span),
+        })
+        .collect();
+
+    let candidates = candidates
+        .matches
+        .iter()
+        .map(|(graph, rejection)| {
+            let groups = graph
+                .groups()
+                .iter()
+                .enumerate()
+                .map(|(idx, group)| GroupExplanation {
+                    id: id_to_idx
+                        .iter()
+                        .filter(|(_, x)| **x == idx)
+                        .map(|(id, _)| id.0)
+                        .min(),
+                    part: if parts.is_empty() || idx == 0 {
+                        None
+                    } else {
+                        Some(idx - 1)
+                    },
+                    char_span: group.char_span,
+                    tokens: group
+                        .tokens(graph.tokens())
+                        .map(|x| x.word.text.as_ref().to_owned())
+                        .collect(),
+                })
+                .collect();
+
+            let char_span = (
+                graph.by_index(0).char_span.0,
+                graph.by_index(graph.groups().len() - 1).char_span.1,
+            );
+
+            let mut candidate = Candidate {
+                char_span,
+                groups,
+                unification: None,
+                message: None,
+                suggesters: Vec::new(),
+                suggestion: None,
+                outcome: Outcome::Unchanged,
+            };
+
+            match rejection {
+                Some(Rejection::Antipattern(index)) => {
+                    candidate.outcome = Outcome::Antipattern(*index);
+                    return candidate;
+                }
+                Some(Rejection::Overlap) => {
+                    candidate.outcome = Outcome::Overlap;
+                    return candidate;
+                }
+                None => {}
+            }
+
+            if let Some(unification) = &rule.unification {
+                let keep = unification.keep(graph, tokens);
+                candidate.unification = Some(keep);
+
+                if !keep {
+                    candidate.outcome = Outcome::Unification;
+                    return candidate;
+                }
+            }
+
+            let details = rule.details.get();
+            candidate.message = Some(synthesis(
+                &details.message,
+                graph,
+                tokenizer,
+                rule.start,
+                rule.end,
+            ));
+            candidate.suggesters = details
+                .suggesters
+                .iter()
+                .map(|x| synthesis(x, graph, tokenizer, rule.start, rule.end))
+                .collect();
+            candidate.suggestion = rule.suggestion(graph, tokenizer, tokens);
+
+            if candidate.suggestion.is_some() {
+                candidate.outcome = Outcome::Suggested;
+            }
+
+            candidate
+        })
+        .collect();
+
+    Explanation {
+        rule: rule.id.to_string(),
+        sentence: sentence.to_owned(),
+        char_span: (0, sentence.chars().count()),
+        pattern,
+        tokens: token_explanations,
+        antipatterns,
+        candidates,
+    }
+}
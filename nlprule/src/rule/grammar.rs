@@ -107,7 +107,7 @@ pub struct Match {
 }
 
 impl Match {
-    fn apply(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> Option<String> {
+    pub(crate) fn apply(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> Option<String> {
         let text = graph.by_id(self.id).text(graph.tokens()[0].sentence);
 
         let mut text = if let Some(replacer) = &self.pos_replacer {
@@ -145,6 +145,16 @@ pub struct Synthesizer {
 }
 
 impl Synthesizer {
+    pub(crate) fn regexes(&self) -> Vec<&Regex> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                SynthesizerPart::Match(m) => m.regex_replacer.as_ref().map(|(regex, _)| regex),
+                SynthesizerPart::Text(_) => None,
+            })
+            .collect()
+    }
+
     pub fn apply(
         &self,
         graph: &MatchGraph,
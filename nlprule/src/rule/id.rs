@@ -122,6 +122,11 @@ impl Group {
     pub fn parent(&self) -> &Category {
         &self.parent
     }
+
+    /// Gets the identifier of this group within its category.
+    pub fn inner(&self) -> &str {
+        &self.inner
+    }
 }
 
 impl Index {
@@ -129,6 +134,11 @@ impl Index {
     pub fn parent(&self) -> &Group {
         &self.parent
     }
+
+    /// Gets the position of this index within its group.
+    pub fn inner(&self) -> usize {
+        self.inner
+    }
 }
 
 /// A *selector* to filter rules by checking if an [Index] matches the selector.
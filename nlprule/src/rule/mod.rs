@@ -4,7 +4,7 @@ use crate::types::*;
 use crate::{
     filter::{Filter, Filterable},
     tokenizer::{finalize, Tokenizer},
-    utils,
+    utils::{self, budget::Budget, lazy::Lazy, regex::Regex},
 };
 use itertools::Itertools;
 use log::{error, info, warn};
@@ -14,18 +14,25 @@ use std::fmt;
 
 pub(crate) mod disambiguation;
 pub(crate) mod engine;
+pub mod explain;
 pub(crate) mod grammar;
 pub mod id;
+pub mod report;
 
 use engine::Engine;
 
-pub(crate) use engine::composition::MatchGraph;
+pub(crate) use engine::composition::{MatchGraph, TagReads};
 pub use grammar::Example;
 
 use self::{
     disambiguation::POSFilter,
-    engine::{composition::GraphId, EngineMatches},
+    engine::{
+        composition::{GraphId, Requirement},
+        EngineMatches,
+    },
+    explain::Explanation,
     id::Index,
+    report::{ExampleOutput, ExampleReport, TokenReadings},
 };
 
 /// A *Unification* makes an otherwise matching pattern invalid if no combination of its filters
@@ -100,6 +107,16 @@ impl Changes {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Gets a mask of the tokens which are affected by these changes.
+    pub fn token_mask(&self, tokens: &[Token]) -> Vec<bool> {
+        let byte_spans: HashSet<_> = self.0.iter().flatten().flatten().collect();
+
+        tokens
+            .iter()
+            .map(|x| byte_spans.contains(&x.byte_span))
+            .collect()
+    }
 }
 
 impl DisambiguationRule {
@@ -108,14 +125,38 @@ impl DisambiguationRule {
         &self.id
     }
 
-    pub(crate) fn apply<'t>(&'t self, tokens: &[Token<'t>], tokenizer: &Tokenizer) -> Changes {
+    pub(crate) fn regexes(&self) -> Vec<&Regex> {
+        let mut regexes = self.engine.regexes();
+        if let Some(filter) = &self.filter {
+            regexes.extend(filter.regexes());
+        }
+        regexes
+    }
+
+    pub(crate) fn apply<'t>(
+        &'t self,
+        tokens: &[Token<'t>],
+        tokenizer: &Tokenizer,
+        budget: &Budget,
+        reads: Option<&TagReads>,
+    ) -> Changes {
         if matches!(self.disambiguations, disambiguation::Disambiguation::Nop) {
             return Changes::default();
         }
 
         let mut all_byte_spans = Vec::new();
 
-        for graph in self.engine.get_matches(tokens, self.start, self.end) {
+        for graph in self
+            .engine
+            .get_matches(tokens, self.start, self.end, budget, reads)
+        {
+            // unification reads the tags of the matched tokens
+            if let Some(reads) = reads {
+                let start = graph.by_index(0).char_span.0;
+                let end = graph.by_index(graph.groups().len() - 1).char_span.1;
+                reads.record_span(tokens, (start, end));
+            }
+
             if let Some(unification) = &self.unification {
                 if !unification.keep(&graph, tokens) {
                     continue;
@@ -178,7 +219,12 @@ impl DisambiguationRule {
     /// Often there are examples associated with a rule.
     /// This method checks whether the correct action is taken in the examples.
     pub fn test(&self, tokenizer: &Tokenizer) -> bool {
-        let mut passes = Vec::new();
+        self.test_with_report(tokenizer).iter().all(|x| x.passed)
+    }
+
+    /// Checks the examples like [test][DisambiguationRule::test], but returns the result of each example.
+    pub fn test_with_report(&self, tokenizer: &Tokenizer) -> Vec<ExampleReport> {
+        let mut reports = Vec::new();
 
         for (i, test) in self.examples.iter().enumerate() {
             let text = match test {
@@ -190,7 +236,7 @@ impl DisambiguationRule {
             let tokens_before =
                 tokenizer.disambiguate_up_to_id(tokenizer.tokenize(text), Some(&self.id));
             let finalized = finalize(tokens_before.clone());
-            let changes = self.apply(&finalized, tokenizer);
+            let changes = self.apply(&finalized, tokenizer, &Budget::default(), None);
 
             let tokens_before: Vec<_> = tokens_before.into_iter().map(|x| x.0).collect();
             let mut tokens_after: Vec<_> = tokens_before.clone();
@@ -201,9 +247,22 @@ impl DisambiguationRule {
 
             info!("Tokens: {:#?}", tokens_before);
 
-            let pass = match test {
+            let readings = |token: &IncompleteToken| TokenReadings {
+                char_span: token.char_span,
+                text: token.word.text.as_ref().to_owned(),
+                readings: token.word.tags.iter().map(Reading::from).collect(),
+            };
+
+            let (pass, expected, actual) = match test {
                 disambiguation::DisambiguationExample::Unchanged(_) => {
-                    tokens_before == tokens_after
+                    let changed = tokens_before
+                        .iter()
+                        .zip(tokens_after.iter())
+                        .filter(|(before, after)| before != after)
+                        .map(|(_, after)| readings(after))
+                        .collect();
+
+                    (tokens_before == tokens_after, Vec::new(), changed)
                 }
                 disambiguation::DisambiguationExample::Changed(change) => {
                     let _before = tokens_before
@@ -230,11 +289,26 @@ impl DisambiguationRule {
                         .iter()
                         .collect::<HashSet<&owned::WordData>>();
 
-                    after.word.text == change.after.text.as_ref_id()
-                        && unordered_tags == unordered_tags_change
+                    let expected = TokenReadings {
+                        char_span: change.char_span,
+                        text: change.after.text.as_ref().to_owned(),
+                        readings: change.after.tags.iter().map(Reading::from).collect(),
+                    };
+
+                    (
+                        after.word.text == change.after.text.as_ref_id()
+                            && unordered_tags == unordered_tags_change,
+                        vec![expected],
+                        vec![readings(after)],
+                    )
                 }
             };
 
+            let known_failure = tokenizer
+                .lang_options()
+                .known_failures
+                .contains(&format!("{}:{}", self.id, i));
+
             if !pass {
                 let error_str = format!(
                     "Rule {}: Test \"{:#?}\" failed. Before: {:#?}. After: {:#?}.",
@@ -244,21 +318,25 @@ impl DisambiguationRule {
                     tokens_after.into_iter().collect::<Vec<_>>(),
                 );
 
-                if tokenizer
-                    .lang_options()
-                    .known_failures
-                    .contains(&format!("{}:{}", self.id, i))
-                {
+                if known_failure {
                     warn!("{}", error_str)
                 } else {
                     error!("{}", error_str)
                 }
             }
 
-            passes.push(pass);
+            reports.push(ExampleReport {
+                rule: self.id.to_string(),
+                index: i,
+                text: text.to_owned(),
+                passed: pass,
+                known_failure: Some(known_failure),
+                expected: ExampleOutput::Readings(expected),
+                actual: ExampleOutput::Readings(actual),
+            });
         }
 
-        passes.iter().all(|x| *x)
+        reports
     }
 }
 
@@ -268,6 +346,14 @@ pub struct Suggestions<'a, 't> {
     tokenizer: &'a Tokenizer,
     matches: EngineMatches<'a, 't>,
     tokens: &'t [Token<'t>],
+    n_matches: usize,
+}
+
+impl<'a, 't> Suggestions<'a, 't> {
+    /// The number of matches consumed so far, including those which did not produce a suggestion.
+    pub(crate) fn n_matches(&self) -> usize {
+        self.n_matches
+    }
 }
 
 impl<'a, 't> Iterator for Suggestions<'a, 't> {
@@ -281,81 +367,18 @@ impl<'a, 't> Iterator for Suggestions<'a, 't> {
         let rule = self.rule;
         let tokenizer = self.tokenizer;
         let tokens = self.tokens;
-        let (start, end) = (self.rule.start, self.rule.end);
+        let n_matches = &mut self.n_matches;
 
         self.matches.find_map(|graph| {
+            *n_matches += 1;
+
             if let Some(unification) = &rule.unification {
                 if !unification.keep(&graph, tokens) {
                     return None;
                 }
             }
 
-            let start_group = graph.by_id(start);
-            let end_group = graph.by_id(end);
-
-            let replacements: Vec<String> = rule
-                .suggesters
-                .iter()
-                .filter_map(|x| x.apply(&graph, tokenizer, start, end))
-                .collect();
-
-            let start = if replacements
-                .iter()
-                .all(|x| utils::no_space_chars().chars().any(|c| x.starts_with(c)))
-            {
-                let first_token = graph.groups()[graph.get_index(start)..]
-                    .iter()
-                    .find_map(|x| x.tokens(graph.tokens()).next())
-                    .unwrap();
-
-                let idx = tokens
-                    .iter()
-                    .position(|x| std::ptr::eq(x, first_token))
-                    .unwrap_or(0);
-
-                if idx > 0 {
-                    tokens[idx - 1].char_span.1
-                } else {
-                    start_group.char_span.0
-                }
-            } else {
-                start_group.char_span.0
-            };
-            let end = end_group.char_span.1;
-
-            // this should never happen, but just return None instead of raising an Error
-            // `end` COULD be equal to `start` if the suggestion is to insert text at this position
-            if end < start {
-                return None;
-            }
-            let text_before: String = tokens[0]
-                .sentence
-                .chars()
-                .skip(start)
-                .take(end - start)
-                .collect();
-
-            // fix e. g. "Super , dass"
-            let replacements: Vec<String> = replacements
-                .into_iter()
-                .filter(|suggestion| *suggestion != text_before)
-                .map(|x| utils::fix_nospace_chars(&x))
-                .collect();
-
-            if !replacements.is_empty() {
-                Some(Suggestion {
-                    message: rule
-                        .message
-                        .apply(&graph, tokenizer, rule.start, rule.end)
-                        .expect("Rules must have a message."),
-                    source: rule.id.to_string(),
-                    start,
-                    end,
-                    replacements,
-                })
-            } else {
-                None
-            }
+            rule.suggestion(&graph, tokenizer, tokens)
         })
     }
 }
@@ -379,18 +402,25 @@ impl<'a, 't> Iterator for Suggestions<'a, 't> {
 pub struct Rule {
     pub(crate) id: Index,
     pub(crate) engine: Engine,
+    pub(crate) start: GraphId,
+    pub(crate) end: GraphId,
+    pub(crate) unification: Option<Unification>,
+    pub(crate) enabled: bool,
+    pub(crate) details: Lazy<RuleDetails>,
+}
+
+/// The parts of a [Rule] which are not needed to find matches.
+/// Deserialized on first use if the rule set is loaded lazily, see [RulesOptions::lazy][crate::rules::RulesOptions::lazy].
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct RuleDetails {
     pub(crate) examples: Vec<Example>,
     pub(crate) suggesters: Vec<grammar::Synthesizer>,
     pub(crate) message: grammar::Synthesizer,
-    pub(crate) start: GraphId,
-    pub(crate) end: GraphId,
     pub(crate) url: Option<String>,
     pub(crate) short: Option<String>,
     pub(crate) name: String,
     pub(crate) category_name: String,
     pub(crate) category_type: Option<String>,
-    pub(crate) unification: Option<Unification>,
-    pub(crate) enabled: bool,
 }
 
 impl fmt::Display for Rule {
@@ -422,57 +452,184 @@ impl Rule {
 
     /// Gets a short text describing this rule e.g. "Possible typo" if there is one.
     pub fn short(&self) -> Option<&str> {
-        self.short.as_deref()
+        self.details.get().short.as_deref()
     }
 
     /// Gets an url with more information about this rule if there is one.
     pub fn url(&self) -> Option<&str> {
-        self.url.as_deref()
+        self.details.get().url.as_deref()
     }
 
     /// Gets the examples associated with this rule.
     pub fn examples(&self) -> &[Example] {
-        &self.examples
+        &self.details.get().examples
     }
 
     /// Gets a human-readable name of this rule.
     pub fn name(&self) -> &str {
-        &self.name
+        &self.details.get().name
     }
 
     /// Gets a human-readable name of the category this rule is in.
     pub fn category_name(&self) -> &str {
-        &self.category_name
+        &self.details.get().category_name
     }
 
     /// Gets the type of the category this rule is in e. g. "style" or "grammar".
     pub fn category_type(&self) -> Option<&str> {
-        self.category_type.as_deref()
+        self.details.get().category_type.as_deref()
+    }
+
+    /// Gets requirements of which at least one has to be fulfilled by some token in the sentence for this rule to match.
+    /// Returns `None` if the rule could match any sentence.
+    pub(crate) fn requirements(&self) -> Option<Vec<Requirement<'_>>> {
+        match &self.engine {
+            Engine::Token(engine) => engine.composition.requirements(),
+            Engine::Text(_, _) => None,
+        }
+    }
+
+    pub(crate) fn regexes(&self) -> Vec<&Regex> {
+        let mut regexes = self.engine.regexes();
+        let details = self.details.get();
+        regexes.extend(details.message.regexes());
+        regexes.extend(details.suggesters.iter().flat_map(|x| x.regexes()));
+        regexes
+    }
+
+    /// Computes the suggestion for a match of this rule. Returns `None` if there is no replacement
+    /// which differs from the matched text.
+    fn suggestion<'t>(
+        &self,
+        graph: &MatchGraph<'t>,
+        tokenizer: &Tokenizer,
+        tokens: &[Token<'t>],
+    ) -> Option<Suggestion> {
+        let start_group = graph.by_id(self.start);
+        let end_group = graph.by_id(self.end);
+
+        let replacements: Vec<String> = self
+            .details
+            .get()
+            .suggesters
+            .iter()
+            .filter_map(|x| x.apply(graph, tokenizer, self.start, self.end))
+            .collect();
+
+        let start = if replacements
+            .iter()
+            .all(|x| utils::no_space_chars().chars().any(|c| x.starts_with(c)))
+        {
+            let first_token = graph.groups()[graph.get_index(self.start)..]
+                .iter()
+                .find_map(|x| x.tokens(graph.tokens()).next())
+                .unwrap();
+
+            let idx = tokens
+                .iter()
+                .position(|x| std::ptr::eq(x, first_token))
+                .unwrap_or(0);
+
+            if idx > 0 {
+                tokens[idx - 1].char_span.1
+            } else {
+                start_group.char_span.0
+            }
+        } else {
+            start_group.char_span.0
+        };
+        let end = end_group.char_span.1;
+
+        // this should never happen, but just return None instead of raising an Error
+        // `end` COULD be equal to `start` if the suggestion is to insert text at this position
+        if end < start {
+            return None;
+        }
+        let text_before: String = tokens[0]
+            .sentence
+            .chars()
+            .skip(start)
+            .take(end - start)
+            .collect();
+
+        // fix e. g. "Super , dass"
+        let replacements: Vec<String> = replacements
+            .into_iter()
+            .filter(|suggestion| *suggestion != text_before)
+            .map(|x| utils::fix_nospace_chars(&x))
+            .collect();
+
+        if !replacements.is_empty() {
+            Some(Suggestion {
+                message: self
+                    .details
+                    .get()
+                    .message
+                    .apply(graph, tokenizer, self.start, self.end)
+                    .expect("Rules must have a message."),
+                source: self.id.to_string(),
+                start,
+                end,
+                replacements,
+            })
+        } else {
+            None
+        }
     }
 
     pub(crate) fn apply<'a, 't>(
         &'a self,
         tokens: &'t [Token<'t>],
         tokenizer: &'a Tokenizer,
+        budget: &'a Budget,
     ) -> Suggestions<'a, 't> {
         Suggestions {
-            matches: self.engine.get_matches(tokens, self.start, self.end),
+            matches: self
+                .engine
+                .get_matches(tokens, self.start, self.end, budget, None),
             rule: &self,
             tokenizer,
             tokens,
+            n_matches: 0,
         }
     }
 
+    /// Explains how this rule is applied to each sentence of the text, see [Explanation].
+    /// Unlike [Rules::explain][crate::rules::Rules::explain], this also returns explanations for sentences without any match.
+    pub fn explain(&self, text: &str, tokenizer: &Tokenizer) -> Vec<Explanation> {
+        let mut explanations = Vec::new();
+        let mut char_offset = 0;
+
+        for tokens in tokenizer.pipe(text) {
+            if tokens.is_empty() {
+                continue;
+            }
+
+            let mut explanation = explain::explain_sentence(self, &tokens, tokenizer);
+            explanation.rshift(char_offset);
+            explanations.push(explanation);
+
+            char_offset += tokens[0].sentence.chars().count();
+        }
+
+        explanations
+    }
+
     /// Grammar rules always have at least one example associated with them.
     /// This method checks whether the correct action is taken in the examples.
     pub fn test(&self, tokenizer: &Tokenizer) -> bool {
-        let mut passes = Vec::new();
+        self.test_with_report(tokenizer).iter().all(|x| x.passed)
+    }
+
+    /// Checks the examples like [test][Rule::test], but returns the result of each example.
+    pub fn test_with_report(&self, tokenizer: &Tokenizer) -> Vec<ExampleReport> {
+        let mut reports = Vec::new();
 
-        for test in self.examples.iter() {
+        for (i, test) in self.examples().iter().enumerate() {
             // by convention examples are always considered as one sentence even if the sentencizer would split
             let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(&test.text())));
             info!("Tokens: {:#?}", tokens);
-            let suggestions: Vec<_> = self.apply(&tokens, tokenizer).collect();
+            let suggestions: Vec<_> = self.apply(&tokens, tokenizer, &Budget::default()).collect();
 
             let pass = if suggestions.len() > 1 {
                 false
@@ -495,9 +652,19 @@ impl Rule {
                 );
             }
 
-            passes.push(pass);
+            reports.push(ExampleReport {
+                rule: self.id.to_string(),
+                index: i,
+                text: test.text().to_owned(),
+                passed: pass,
+                known_failure: None,
+                expected: ExampleOutput::Suggestions(
+                    test.suggestion().cloned().into_iter().collect(),
+                ),
+                actual: ExampleOutput::Suggestions(suggestions),
+            });
         }
 
-        passes.iter().all(|x| *x)
+        reports
     }
 }
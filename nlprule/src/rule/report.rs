@@ -0,0 +1,148 @@
+//! Structured results of checking the examples of rules, see [Rule::test_with_report][crate::rule::Rule::test_with_report]
+//! and [DisambiguationRule::test_with_report][crate::rule::DisambiguationRule::test_with_report].
+
+use crate::{export::escape, types::*};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// The readings of a token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenReadings {
+    /// The char span of the token in the example text.
+    pub char_span: (usize, usize),
+    /// The text of the token.
+    pub text: String,
+    /// The readings of the token.
+    pub readings: Vec<Reading>,
+}
+
+/// What a rule does (or should do) to the text of an example.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleOutput {
+    /// The suggestions of a grammar rule.
+    Suggestions(Vec<Suggestion>),
+    /// The readings of the tokens changed by a disambiguation rule.
+    Readings(Vec<TokenReadings>),
+}
+
+/// The result of checking one example of a rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExampleReport {
+    /// The ID of the rule.
+    pub rule: String,
+    /// The index of the example in the examples of the rule.
+    pub index: usize,
+    /// The text of the example.
+    pub text: String,
+    /// Whether the rule took the correct action.
+    pub passed: bool,
+    /// Whether the example is listed as a known failure in the language options of the tokenizer.
+    /// Only disambiguation rules have known failures, `None` for grammar rules.
+    pub known_failure: Option<bool>,
+    /// What the rule should do.
+    pub expected: ExampleOutput,
+    /// What the rule did.
+    pub actual: ExampleOutput,
+}
+
+/// Writes the reports as a JUnit XML document with one test suite called `name`.
+/// Each example is a test case named after the rule and the index of the example.
+/// Known failures which fail are reported as skipped.
+pub fn to_junit(name: &str, reports: &[ExampleReport]) -> String {
+    let failures = reports
+        .iter()
+        .filter(|x| !x.passed && x.known_failure != Some(true))
+        .count();
+    let skipped = reports
+        .iter()
+        .filter(|x| !x.passed && x.known_failure == Some(true))
+        .count();
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        out,
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">",
+        escape(name),
+        reports.len(),
+        failures,
+        skipped
+    )
+    .expect("writing to string can not fail");
+
+    for report in reports {
+        write!(
+            out,
+            "  <testcase classname=\"{}\" name=\"{}:{}\">",
+            escape(&report.rule),
+            escape(&report.rule),
+            report.index
+        )
+        .expect("writing to string can not fail");
+
+        if !report.passed {
+            let details = format!(
+                "Text: {}\nExpected: {:#?}\nActual: {:#?}",
+                report.text, report.expected, report.actual
+            );
+
+            if report.known_failure == Some(true) {
+                write!(
+                    out,
+                    "<skipped message=\"known failure\">{}</skipped>",
+                    escape(&details)
+                )
+            } else {
+                write!(
+                    out,
+                    "<failure message=\"{}\">{}</failure>",
+                    escape(&format!("example {} failed", report.index)),
+                    escape(&details)
+                )
+            }
+            .expect("writing to string can not fail");
+        }
+
+        out.push_str("</testcase>\n");
+    }
+
+    out.push_str("</testsuite>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(index: usize, passed: bool, known_failure: Option<bool>) -> ExampleReport {
+        ExampleReport {
+            rule: "GRAMMAR/A&B/0".into(),
+            index,
+            text: "<text>".into(),
+            passed,
+            known_failure,
+            expected: ExampleOutput::Suggestions(Vec::new()),
+            actual: ExampleOutput::Readings(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn junit_counts_and_escapes() {
+        let xml = to_junit(
+            "rules",
+            &[
+                report(0, true, None),
+                report(1, false, Some(false)),
+                report(2, false, Some(true)),
+                report(3, false, None),
+            ],
+        );
+
+        assert!(xml.contains(r#"tests="4" failures="2" skipped="1""#));
+        assert!(xml.contains(r#"name="GRAMMAR/A&amp;B/0:1"><failure"#));
+        assert!(xml.contains(r#"name="GRAMMAR/A&amp;B/0:2"><skipped"#));
+        assert!(xml.contains("&lt;text&gt;"));
+        assert!(!xml.contains("<text>"));
+    }
+}
@@ -1,19 +1,66 @@
 //! Sets of grammatical error correction rules.
 
+use crate::binary::{self, BinaryKind, Header};
 use crate::types::*;
-use crate::utils::parallelism::MaybeParallelRefIterator;
-use crate::{rule::id::Selector, tokenizer::Tokenizer};
-use crate::{rule::Rule, Error};
+use crate::utils::{
+    budget::Budget,
+    lazy::{Lazy, LazyRef},
+    mapped,
+    parallelism::{
+        prefer_outer_parallelism, MaybeParallelIterator, MaybeParallelRefIterator, Parallelism,
+    },
+    regex,
+};
+use crate::{rule::Rule, Error, RegexError};
+use crate::{
+    rule::{
+        engine::composition::{Matcher, Requirement},
+        explain::{self, Explanation, Outcome},
+        id::{Index, Selector},
+        MatchGraph,
+    },
+    tokenizer::Tokenizer,
+};
 use fs_err::File;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::{
-    io::{BufReader, Read},
+    convert::TryFrom,
+    io::{BufReader, Read, Write},
     path::Path,
+    time::Instant,
 };
+use unicase::UniCase;
 
 /// Options for a rule set.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct RulesOptions {}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulesOptions {
+    /// Whether to skip rules which can not match a sentence because it does not contain any of the words
+    /// the rule requires. This does not change the suggestions and should only be turned off for debugging.
+    pub prefilter: bool,
+    /// Limits on the work done per sentence.
+    pub limits: Limits,
+    /// How to parallelize checking the rules. Not serialized.
+    #[serde(skip)]
+    pub parallelism: Parallelism,
+    /// Whether to deserialize the messages, suggestions and examples of a rule when they are first used
+    /// instead of when loading the rule set. Only has an effect when loading a rule set.
+    pub lazy: bool,
+    /// If set, only rules matching one of these selectors are loaded. The other rules are not deserialized at all.
+    /// Only has an effect when loading a rule set.
+    pub selectors: Option<Vec<Selector>>,
+}
+
+impl Default for RulesOptions {
+    fn default() -> Self {
+        RulesOptions {
+            prefilter: true,
+            limits: Limits::default(),
+            parallelism: Parallelism::default(),
+            lazy: false,
+            selectors: None,
+        }
+    }
+}
 
 /// Language-dependent options for a rule set.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,11 +85,141 @@ impl Default for RulesLangOptions {
     }
 }
 
+/// Maps words to the rules which can only match a sentence containing one of these words.
+/// Used to skip rules which can not match a sentence without running the matching engine.
+#[derive(Default)]
+struct RulesIndex {
+    /// Rules which do not have any requirements.
+    unconditional: Vec<usize>,
+    by_text: DefaultHashMap<UniCase<String>, Vec<usize>>,
+    by_id: DefaultHashMap<WordIdInt, Vec<usize>>,
+    /// Matchers which have to be checked for words which are not in the word store.
+    unknown: Vec<(Matcher, usize)>,
+}
+
+impl RulesIndex {
+    fn new(rules: &[Rule]) -> Self {
+        let mut index = RulesIndex::default();
+
+        for (i, rule) in rules.iter().enumerate() {
+            let requirements = match rule.requirements() {
+                Some(requirements) => requirements,
+                None => {
+                    index.unconditional.push(i);
+                    continue;
+                }
+            };
+
+            for requirement in requirements {
+                match requirement {
+                    Requirement::Text(text) => {
+                        index
+                            .by_text
+                            .entry(UniCase::new(text.to_owned()))
+                            .or_insert_with(Vec::new)
+                            .push(i);
+                    }
+                    Requirement::Words(ids, matcher) => {
+                        for id in ids {
+                            index.by_id.entry(*id).or_insert_with(Vec::new).push(i);
+                        }
+                        index.unknown.push((matcher.clone(), i));
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Computes a mask of the rules which could match the given tokens.
+    fn candidates(&self, tokens: &[Token], n_rules: usize) -> Vec<bool> {
+        let mut mask = vec![false; n_rules];
+        let graph = MatchGraph::default();
+
+        for i in &self.unconditional {
+            mask[*i] = true;
+        }
+
+        for token in tokens {
+            let text = token.word.text.as_ref();
+
+            if let Some(rules) = self.by_text.get(&UniCase::new(text.to_owned())) {
+                for i in rules {
+                    mask[*i] = true;
+                }
+            }
+
+            if let Some(id) = token.word.text.id() {
+                if let Some(rules) = self.by_id.get(id) {
+                    for i in rules {
+                        mask[*i] = true;
+                    }
+                }
+            } else {
+                for (matcher, i) in &self.unknown {
+                    if !mask[*i] && matcher.is_match(text, &graph, None) {
+                        mask[*i] = true;
+                    }
+                }
+            }
+        }
+
+        mask
+    }
+}
+
+/// Each rule is stored as a separate blob next to its id so rules can be selected without deserializing them.
+#[derive(Deserialize)]
+pub(crate) struct RulesFields {
+    pub(crate) rules: Vec<(Index, Lazy<Rule>)>,
+    pub(crate) options: RulesOptions,
+    pub(crate) tagger_fingerprint: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct RulesFieldsRef<'a> {
+    rules: Vec<(&'a Index, LazyRef<'a, Rule>)>,
+    options: &'a RulesOptions,
+    tagger_fingerprint: Option<u64>,
+}
+
+impl TryFrom<RulesFields> for Rules {
+    type Error = Error;
+
+    fn try_from(data: RulesFields) -> Result<Self, Self::Error> {
+        let options = data.options.clone();
+        Rules::from_fields(data, options)
+    }
+}
+
 /// A set of grammatical error correction rules.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Deserialize, Default)]
+#[serde(try_from = "RulesFields")]
 pub struct Rules {
     pub(crate) rules: Vec<Rule>,
     pub(crate) options: RulesOptions,
+    #[serde(skip)]
+    index: RulesIndex,
+    #[serde(skip)]
+    pub(crate) header: Option<Header>,
+    #[serde(skip)]
+    tagger_fingerprint: Option<u64>,
+}
+
+impl Serialize for Rules {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RulesFieldsRef {
+            rules: self
+                .rules
+                .iter()
+                .map(|rule| (rule.id(), LazyRef(rule)))
+                .collect(),
+            options: &self.options,
+            tagger_fingerprint: self.tagger_fingerprint,
+        }
+        .serialize(serializer)
+    }
 }
 
 impl Rules {
@@ -50,6 +227,7 @@ impl Rules {
     ///
     /// # Errors
     /// - If the file can not be opened.
+    /// - If the file is not a rules binary compatible with this version of nlprule or is corrupted.
     /// - If the file content can not be deserialized to a rules set.
     pub fn new<P: AsRef<Path>>(p: P) -> Result<Self, Error> {
         Rules::new_with_options(p, RulesOptions::default())
@@ -58,9 +236,95 @@ impl Rules {
     /// Creates a new rule set with options. See [new][Rules::new].
     pub fn new_with_options<P: AsRef<Path>>(p: P, options: RulesOptions) -> Result<Self, Error> {
         let reader = BufReader::new(File::open(p.as_ref())?);
-        let mut rules: Rules = bincode::deserialize_from(reader)?;
+        let (header, payload) = binary::read(reader, BinaryKind::Rules)?;
+        let fields: RulesFields = bincode::deserialize(&payload)?;
 
-        rules.options = options;
+        let mut rules = Rules::from_fields(fields, options)?;
+        rules.header = Some(header);
+        Ok(rules)
+    }
+
+    /// Creates a rule set from deserialized fields. Only deserializes the rules selected by the options
+    /// and, unless the options request lazy loading, their details.
+    ///
+    /// # Errors
+    /// If a rule or its details can not be deserialized.
+    pub(crate) fn from_fields(fields: RulesFields, options: RulesOptions) -> Result<Self, Error> {
+        let rules: Vec<Rule> = fields
+            .rules
+            .into_iter()
+            .filter(|(id, _)| {
+                options
+                    .selectors
+                    .as_ref()
+                    .map_or(true, |selectors| selectors.iter().any(|x| x.is_match(id)))
+            })
+            .map(|(_, rule)| {
+                let mut rule = rule.into_inner()?;
+                if !options.lazy {
+                    rule.details.force()?;
+                }
+                Ok(rule)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Rules {
+            index: RulesIndex::new(&rules),
+            rules,
+            options,
+            header: None,
+            tagger_fingerprint: fields.tagger_fingerprint,
+        })
+    }
+
+    /// Creates a rule set from rules created at runtime, e. g. with [RuleSpec::compile][crate::dsl::RuleSpec::compile].
+    /// The rules must have been created for this tokenizer.
+    pub fn from_rules(rules: Vec<Rule>, tokenizer: &Tokenizer) -> Self {
+        Rules {
+            index: RulesIndex::new(&rules),
+            rules,
+            options: RulesOptions::default(),
+            header: None,
+            tagger_fingerprint: tokenizer.tagger().fingerprint(),
+        }
+    }
+
+    /// Creates a new rule set by memory-mapping the binary at the given path.
+    /// Deserializes directly from the mapping instead of reading the file through a buffer.
+    /// If the rule set is loaded lazily (see [RulesOptions::lazy]), the details of rules are read from the mapping on first use.
+    ///
+    /// # Safety
+    /// The file must not be modified while the rule set is alive.
+    ///
+    /// # Errors
+    /// - If the file can not be opened or mapped.
+    /// - If the file is not a rules binary compatible with this version of nlprule.
+    ///   Unlike [new][Rules::new], the content is only checked against the checksum if the rule set is loaded lazily,
+    ///   so that only the used pages of the file are read. Otherwise all details are deserialized right away
+    ///   and invalid content is reported as an error instead.
+    /// - If the file content can not be deserialized to a rules set.
+    pub unsafe fn new_mapped<P: AsRef<Path>>(p: P) -> Result<Self, Error> {
+        Rules::new_mapped_with_options(p, RulesOptions::default())
+    }
+
+    /// Creates a new memory-mapped rule set with options. See [new_mapped][Rules::new_mapped].
+    ///
+    /// # Safety
+    /// See [new_mapped][Rules::new_mapped].
+    pub unsafe fn new_mapped_with_options<P: AsRef<Path>>(
+        p: P,
+        options: RulesOptions,
+    ) -> Result<Self, Error> {
+        let map = mapped::map(p)?;
+        let (header, offset) = binary::split(&map, BinaryKind::Rules)?;
+        // lazily loaded details are deserialized on first use where errors can not be reported
+        if options.lazy {
+            binary::verify(&header, &map[offset..])?;
+        }
+        let fields: RulesFields = mapped::deserialize(&map, offset)?;
+
+        let mut rules = Rules::from_fields(fields, options)?;
+        rules.header = Some(header);
         Ok(rules)
     }
 
@@ -76,7 +340,29 @@ impl Rules {
 
     /// Creates a new rules set from a reader.
     pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
-        Ok(bincode::deserialize_from(reader)?)
+        let (header, payload) = binary::read(reader, BinaryKind::Rules)?;
+        let mut rules: Rules = bincode::deserialize(&payload)?;
+
+        rules.header = Some(header);
+        Ok(rules)
+    }
+
+    /// Gets the header of the binary this rule set was loaded from.
+    /// `None` if the rule set was not loaded from a binary.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Writes this rule set as a rules binary. Keeps the metadata of the binary it was loaded from, if any.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let metadata = self
+            .header
+            .as_ref()
+            .map(|header| header.metadata.clone())
+            .unwrap_or_default();
+        binary::write(writer, BinaryKind::Rules, &metadata, self)?;
+
+        Ok(())
     }
 
     /// All rules ordered by priority.
@@ -89,6 +375,17 @@ impl Rules {
         &mut self.rules
     }
 
+    /// Compiles all regular expressions used by the rules in this set.
+    ///
+    /// Regexes are otherwise compiled lazily when they are first needed, which makes the first
+    /// calls to [suggest][Rules::suggest] considerably slower than subsequent ones.
+    /// Equal regexes are shared between rules so each one is only compiled once.
+    ///
+    /// Returns an error for every regex which could not be compiled.
+    pub fn warm_up(&self) -> Vec<RegexError> {
+        regex::compile_all(self.rules.iter().flat_map(Rule::regexes).collect())
+    }
+
     /// Returns an iterator over all rules matching the selector.
     pub fn select<'a>(&'a self, selector: &'a Selector) -> RulesIter<'a> {
         RulesIter {
@@ -105,34 +402,156 @@ impl Rules {
         }
     }
 
+    /// Gets the fingerprint of the tagger this rule set was compiled with, see [Tagger::fingerprint][crate::tokenizer::tag::Tagger::fingerprint].
+    pub fn tagger_fingerprint(&self) -> Option<u64> {
+        self.tagger_fingerprint
+    }
+
+    /// Checks whether this rule set can be used with the tokenizer.
+    /// Rules refer to words by ids which are only valid for the tagger they were compiled with,
+    /// so a rule set can not be used with a tokenizer for a different language or from a different build.
+    ///
+    /// # Errors
+    /// If the fingerprints of the taggers are both known and differ.
+    pub fn check_compatible(&self, tokenizer: &Tokenizer) -> Result<(), Error> {
+        match (self.tagger_fingerprint, tokenizer.tagger().fingerprint()) {
+            (Some(expected), Some(found)) if expected != found => {
+                Err(Error::IncompatibleTokenizer { expected, found })
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Compute the suggestions for the given tokens by checking all rules.
+    ///
+    /// # Panics
+    /// If the rule set is not compatible with the tokenizer, see [check_compatible][Rules::check_compatible].
     pub fn apply(&self, tokens: &[Token], tokenizer: &Tokenizer) -> Vec<Suggestion> {
+        self.apply_with_report(tokens, tokenizer).0
+    }
+
+    /// Compute the suggestions for the given tokens like [apply][Rules::apply].
+    /// Additionally returns a [Truncation] relative to the sentence if some rules were cut short because of the [Limits] in the options.
+    pub fn apply_with_report(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+    ) -> (Vec<Suggestion>, Option<Truncation>) {
+        let parallelism = self.options.parallelism.resolve();
+
+        parallelism
+            .install(|| self.apply_sentence(tokens, tokenizer, parallelism.is_parallel(), None))
+    }
+
+    /// Compute the suggestions for the given tokens like [apply][Rules::apply].
+    /// Additionally adds the time spent in each rule and the number of matches and suggestions of each rule to the [Profile].
+    /// Profiling is opt-in: the other methods do not measure anything.
+    pub fn apply_with_profile(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+        profile: &mut Profile,
+    ) -> Vec<Suggestion> {
+        let parallelism = self.options.parallelism.resolve();
+
+        parallelism
+            .install(|| {
+                self.apply_sentence(tokens, tokenizer, parallelism.is_parallel(), Some(profile))
+            })
+            .0
+    }
+
+    /// Computes the suggestions for one sentence. Rules are applied in parallel if `parallel` is set and parallelism is enabled.
+    /// Statistics are only collected if a profile is passed.
+    fn apply_sentence(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+        parallel: bool,
+        mut profile: Option<&mut Profile>,
+    ) -> (Vec<Suggestion>, Option<Truncation>) {
         if tokens.is_empty() {
-            return Vec::new();
+            return (Vec::new(), None);
+        }
+
+        if let Err(error) = self.check_compatible(tokenizer) {
+            panic!("{}", error);
         }
 
-        let mut output: Vec<(usize, Suggestion)> = self
+        let limits = &self.options.limits;
+        let sentence_length = tokens[0].sentence.chars().count();
+        let mut truncation = Truncation {
+            start: 0,
+            end: sentence_length,
+            skipped: false,
+            rules: Vec::new(),
+        };
+
+        if sentence_length > limits.max_sentence_length.unwrap_or(usize::MAX) {
+            truncation.skipped = true;
+            return (Vec::new(), Some(truncation));
+        }
+
+        let deadline = limits.time_budget.map(|budget| Instant::now() + budget);
+        let candidates = if self.options.prefilter {
+            Some(self.index.candidates(tokens, self.rules.len()))
+        } else {
+            None
+        };
+
+        let profiling = profile.is_some();
+
+        let results: Vec<(usize, Vec<Suggestion>, bool, Option<RuleProfile>)> = self
             .rules
-            .maybe_par_iter()
+            .maybe_par_iter_cond(parallel)
             .enumerate()
-            .filter(|(_, rule)| rule.enabled())
+            .filter(|(i, rule)| rule.enabled() && candidates.as_ref().map_or(true, |mask| mask[*i]))
             .map(|(i, rule)| {
-                let mut output = Vec::new();
+                let budget = Budget::new(limits.max_steps, deadline);
+                let start = if profiling {
+                    Some(Instant::now())
+                } else {
+                    None
+                };
 
-                for suggestion in rule.apply(tokens, tokenizer) {
-                    output.push((i, suggestion));
-                }
+                let mut iter = rule.apply(tokens, tokenizer, &budget);
+                let suggestions: Vec<_> = (&mut iter).collect();
+
+                let stats = start.map(|start| RuleProfile {
+                    time: start.elapsed(),
+                    evaluations: 1,
+                    matches: iter.n_matches(),
+                    suggestions: suggestions.len(),
+                });
 
-                output
+                (i, suggestions, budget.exhausted(), stats)
             })
-            .flatten()
             .collect();
 
+        let mut output: Vec<(usize, Suggestion)> = Vec::new();
+
+        for (i, suggestions, exhausted, stats) in results {
+            // suggestions found before a rule ran out of budget are still valid
+            if exhausted {
+                truncation.rules.push(self.rules[i].id().to_string());
+            }
+
+            if let (Some(profile), Some(stats)) = (profile.as_deref_mut(), stats) {
+                profile
+                    .rules
+                    .entry(self.rules[i].id().to_string())
+                    .or_default()
+                    .merge(&stats);
+            }
+
+            output.extend(suggestions.into_iter().map(|suggestion| (i, suggestion)));
+        }
+
         output.sort_by(|(ia, a), (ib, b)| a.start.cmp(&b.start).then_with(|| ib.cmp(ia)));
 
-        let mut mask = vec![false; tokens[0].sentence.chars().count()];
+        let mut mask = vec![false; sentence_length];
 
-        output
+        let suggestions = output
             .into_iter()
             .filter_map(|(_, suggestion)| {
                 if mask[suggestion.start..suggestion.end].iter().all(|x| !x) {
@@ -144,26 +563,150 @@ impl Rules {
                     None
                 }
             })
-            .collect()
+            .collect();
+
+        if truncation.rules.is_empty() {
+            (suggestions, None)
+        } else {
+            (suggestions, Some(truncation))
+        }
+    }
+
+    /// Explains how the enabled rules are applied to each sentence of the text, see [Explanation].
+    /// Only rules with at least one match in a sentence are explained. Suggestions which are dropped because they
+    /// overlap the suggestion of another rule are marked as [superseded][Outcome::Superseded].
+    ///
+    /// # Panics
+    /// If the rule set is not compatible with the tokenizer, see [check_compatible][Rules::check_compatible].
+    pub fn explain(&self, text: &str, tokenizer: &Tokenizer) -> Vec<Explanation> {
+        if let Err(error) = self.check_compatible(tokenizer) {
+            panic!("{}", error);
+        }
+
+        let mut explanations = Vec::new();
+        let mut char_offset = 0;
+
+        for tokens in tokenizer.pipe(text) {
+            if tokens.is_empty() {
+                continue;
+            }
+
+            let suggestions = self.apply(&tokens, tokenizer);
+
+            for rule in self.rules.iter().filter(|rule| rule.enabled()) {
+                let mut explanation = explain::explain_sentence(rule, &tokens, tokenizer);
+
+                if explanation.candidates.is_empty() {
+                    continue;
+                }
+
+                let id = explanation.rule.clone();
+
+                for candidate in explanation.candidates.iter_mut() {
+                    let (start, end) = match (&candidate.outcome, &candidate.suggestion) {
+                        (Outcome::Suggested, Some(suggestion)) => {
+                            (suggestion.start, suggestion.end)
+                        }
+                        _ => continue,
+                    };
+
+                    if suggestions
+                        .iter()
+                        .any(|x| x.source == id && x.start == start && x.end == end)
+                    {
+                        continue;
+                    }
+
+                    if let Some(other) = suggestions.iter().find(|x| x.start < end && start < x.end)
+                    {
+                        candidate.outcome = Outcome::Superseded(other.source.clone());
+                    }
+                }
+
+                explanation.rshift(char_offset);
+                explanations.push(explanation);
+            }
+
+            char_offset += tokens[0].sentence.chars().count();
+        }
+
+        explanations
     }
 
     /// Compute the suggestions for a text by checking all rules.
+    ///
+    /// # Panics
+    /// If the rule set is not compatible with the tokenizer, see [check_compatible][Rules::check_compatible].
     pub fn suggest(&self, text: &str, tokenizer: &Tokenizer) -> Vec<Suggestion> {
+        self.suggest_with_report(text, tokenizer).0
+    }
+
+    /// Compute the suggestions for a text like [suggest][Rules::suggest].
+    /// Additionally reports the sentences on which tokenization or rule checking was cut short because
+    /// of the [Limits] in the options of the tokenizer or the rule set, at most one [Truncation] per sentence.
+    pub fn suggest_with_report(
+        &self,
+        text: &str,
+        tokenizer: &Tokenizer,
+    ) -> (Vec<Suggestion>, Vec<Truncation>) {
         if text.is_empty() {
-            return Vec::new();
+            return (Vec::new(), Vec::new());
         }
 
         let mut suggestions = Vec::new();
+        let (sentences, mut truncations) = tokenizer.pipe_with_report(text);
         let mut char_offset = 0;
 
         // get suggestions sentence by sentence
+        for tokens in sentences {
+            if tokens.is_empty() {
+                continue;
+            }
+
+            let (sentence_suggestions, truncation) = self.apply_with_report(&tokens, tokenizer);
+
+            suggestions.extend(sentence_suggestions.into_iter().map(|mut suggestion| {
+                suggestion.rshift(char_offset);
+                suggestion
+            }));
+
+            if let Some(mut truncation) = truncation {
+                truncation.rshift(char_offset);
+
+                match truncations.iter_mut().find(|x| x.start == truncation.start) {
+                    Some(existing) => {
+                        existing.skipped |= truncation.skipped;
+                        existing.rules.extend(truncation.rules);
+                    }
+                    None => truncations.push(truncation),
+                }
+            }
+
+            char_offset += tokens[0].sentence.chars().count();
+        }
+
+        truncations.sort_by_key(|x| x.start);
+        (suggestions, truncations)
+    }
+
+    /// Compute the suggestions for a text like [suggest][Rules::suggest] and collect statistics
+    /// of each rule in the [Profile], see [apply_with_profile][Rules::apply_with_profile].
+    pub fn suggest_with_profile(
+        &self,
+        text: &str,
+        tokenizer: &Tokenizer,
+        profile: &mut Profile,
+    ) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+        let mut char_offset = 0;
+
         for tokens in tokenizer.pipe(text) {
             if tokens.is_empty() {
                 continue;
             }
 
             suggestions.extend(
-                self.apply(&tokens, tokenizer)
+                self.apply_with_profile(&tokens, tokenizer, profile)
                     .into_iter()
                     .map(|mut suggestion| {
                         suggestion.rshift(char_offset);
@@ -177,6 +720,45 @@ impl Rules {
         suggestions
     }
 
+    /// Compute the suggestions for multiple texts. The result is the same as calling [suggest][Rules::suggest]
+    /// on each text, but the sentences of all texts are processed in parallel.
+    /// Tokenization is parallelized according to the options of this rule set, not those of the tokenizer.
+    pub fn suggest_batch<S: AsRef<str> + Sync>(
+        &self,
+        texts: &[S],
+        tokenizer: &Tokenizer,
+    ) -> Vec<Vec<Suggestion>> {
+        let sentences = tokenizer.batch_sentences(texts);
+        let parallelism = self.options.parallelism.resolve();
+
+        let mut output: Vec<_> = texts.iter().map(|_| Vec::new()).collect();
+        let suggestions: Vec<_> = parallelism.install(|| {
+            let parallel = parallelism.is_parallel();
+            let parallel_sentences = parallel && prefer_outer_parallelism(sentences.len());
+            let parallel_rules = parallel && !parallel_sentences;
+
+            sentences
+                .into_maybe_par_iter_cond(parallel_sentences)
+                .map(|(i, char_offset, sentence)| {
+                    let (tokens, _) = tokenizer.pipe_sentence(sentence, parallel_rules);
+                    let (suggestions, _) =
+                        self.apply_sentence(&tokens, tokenizer, parallel_rules, None);
+
+                    (i, char_offset, suggestions)
+                })
+                .collect()
+        });
+
+        for (i, char_offset, suggestions) in suggestions {
+            output[i].extend(suggestions.into_iter().map(|mut suggestion| {
+                suggestion.rshift(char_offset);
+                suggestion
+            }));
+        }
+
+        output
+    }
+
     /// Correct a text by first tokenizing, then finding all suggestions and choosing the first replacement of each suggestion.
     pub fn correct(&self, text: &str, tokenizer: &Tokenizer) -> String {
         let suggestions = self.suggest(text, tokenizer);
@@ -234,3 +816,34 @@ impl<'a> Iterator for RulesIterMut<'a> {
             .find(|rule| selector.map_or(true, |s| s.is_match(rule.id())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tag::Tagger;
+    use std::sync::Arc;
+
+    #[test]
+    fn rules_reject_tokenizers_with_other_fingerprint() {
+        let rules = Rules {
+            tagger_fingerprint: Some(1),
+            ..Rules::default()
+        };
+        let tokenizer = Tokenizer {
+            tagger: Arc::new(Tagger {
+                fingerprint: Some(2),
+                ..Tagger::default()
+            }),
+            ..Tokenizer::default()
+        };
+
+        assert!(matches!(
+            rules.check_compatible(&tokenizer),
+            Err(Error::IncompatibleTokenizer {
+                expected: 1,
+                found: 2
+            })
+        ));
+        assert!(rules.check_compatible(&Tokenizer::default()).is_ok());
+    }
+}
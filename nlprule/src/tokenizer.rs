@@ -5,17 +5,29 @@
 //! [DisambiguationRule][crate::rule::DisambiguationRule]s.
 
 use crate::{
-    rule::id::{Index, Selector},
+    binary::{self, BinaryKind, Header},
+    rule::{
+        id::{Index, Selector},
+        TagReads,
+    },
     types::*,
-    utils::{parallelism::MaybeParallelRefIterator, regex::Regex},
-    Error,
+    utils::{
+        budget::Budget,
+        mapped,
+        parallelism::{
+            prefer_outer_parallelism, MaybeParallelIterator, MaybeParallelRefIterator, Parallelism,
+        },
+        regex::{self, Regex},
+    },
+    Error, RegexError,
 };
 use fs_err::File;
 use serde::{Deserialize, Serialize};
 use std::{
-    io::{BufReader, Read},
+    io::{BufReader, Read, Write},
     path::Path,
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Instant,
 };
 
 pub mod chunk;
@@ -24,7 +36,7 @@ pub mod tag;
 
 use chunk::Chunker;
 use multiword::MultiwordTagger;
-use tag::Tagger;
+use tag::{Tagger, TaggerRepresentation};
 
 use crate::rule::DisambiguationRule;
 
@@ -64,6 +76,32 @@ pub fn finalize(tokens: Vec<DisambiguatedToken>) -> Vec<Token> {
 
 /// Options for a tokenizer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenizerOptions {
+    /// Whether to only apply disambiguation rules again if tokens they depend on have changed.
+    /// This does not change the result and should only be turned off for debugging.
+    pub incremental: bool,
+    /// Limits on the work done by disambiguation per sentence.
+    pub limits: Limits,
+    /// How to parallelize disambiguation. Not serialized.
+    #[serde(skip)]
+    pub parallelism: Parallelism,
+    /// How to represent the dictionary of the tagger. Only has an effect when loading a tokenizer.
+    pub tagger: TaggerRepresentation,
+}
+
+impl Default for TokenizerOptions {
+    fn default() -> Self {
+        TokenizerOptions {
+            incremental: true,
+            limits: Limits::default(),
+            parallelism: Parallelism::default(),
+            tagger: TaggerRepresentation::default(),
+        }
+    }
+}
+
+/// Language-dependent options for a tokenizer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct TokenizerLangOptions {
     /// Whether to allow errors while constructing the tokenizer.
     pub allow_errors: bool,
@@ -109,7 +147,10 @@ pub struct Tokenizer {
     pub(crate) sentencizer: srx::Rules,
     pub(crate) multiword_tagger: Option<MultiwordTagger>,
     pub(crate) tagger: Arc<Tagger>,
+    pub(crate) options: TokenizerOptions,
     pub(crate) lang_options: TokenizerLangOptions,
+    #[serde(skip)]
+    pub(crate) header: Option<Header>,
 }
 
 impl Tokenizer {
@@ -117,15 +158,112 @@ impl Tokenizer {
     ///
     /// # Errors
     /// - If the file can not be opened.
-    /// - If the file content can not be deserialized to a rules set.
+    /// - If the file is not a tokenizer binary compatible with this version of nlprule or is corrupted.
+    /// - If the file content can not be deserialized to a tokenizer.
     pub fn new<P: AsRef<Path>>(p: P) -> Result<Self, Error> {
+        Tokenizer::new_with_options(p, TokenizerOptions::default())
+    }
+
+    /// Creates a new tokenizer with options. See [new][Tokenizer::new].
+    pub fn new_with_options<P: AsRef<Path>>(
+        p: P,
+        options: TokenizerOptions,
+    ) -> Result<Self, Error> {
         let reader = BufReader::new(File::open(p.as_ref())?);
-        Ok(bincode::deserialize_from(reader)?)
+        let (header, payload) = binary::read(reader, BinaryKind::Tokenizer)?;
+        let mut tokenizer: Tokenizer = bincode::deserialize(&payload)?;
+
+        tokenizer.header = Some(header);
+        tokenizer.set_options(options);
+        Ok(tokenizer)
+    }
+
+    /// Creates a new tokenizer by memory-mapping the binary at the given path.
+    /// The FSTs of the tagger are queried in place in the mapping instead of being expanded
+    /// into heap structures, so loading is fast and the pages are shared between processes.
+    /// Uses [TaggerRepresentation::Fst] unless the options request otherwise.
+    ///
+    /// # Safety
+    /// The file must not be modified while the tokenizer (or any clone of its tagger) is alive.
+    ///
+    /// # Errors
+    /// - If the file can not be opened or mapped.
+    /// - If the file is not a tokenizer binary compatible with this version of nlprule.
+    ///   Unlike [new][Tokenizer::new], the content is not checked against the checksum so that only the used pages
+    ///   of the file are read.
+    /// - If the file content can not be deserialized to a tokenizer.
+    pub unsafe fn new_mapped<P: AsRef<Path>>(p: P) -> Result<Self, Error> {
+        let options = TokenizerOptions {
+            tagger: TaggerRepresentation::Fst,
+            ..TokenizerOptions::default()
+        };
+        Tokenizer::new_mapped_with_options(p, options)
+    }
+
+    /// Creates a new memory-mapped tokenizer with options. See [new_mapped][Tokenizer::new_mapped].
+    ///
+    /// # Safety
+    /// See [new_mapped][Tokenizer::new_mapped].
+    pub unsafe fn new_mapped_with_options<P: AsRef<Path>>(
+        p: P,
+        options: TokenizerOptions,
+    ) -> Result<Self, Error> {
+        let map = mapped::map(p)?;
+        let (header, offset) = binary::split(&map, BinaryKind::Tokenizer)?;
+        let mut tokenizer: Tokenizer = mapped::deserialize(&map, offset)?;
+
+        tokenizer.header = Some(header);
+        tokenizer.set_options(options);
+        Ok(tokenizer)
+    }
+
+    /// Sets the options of a freshly loaded tokenizer and converts the tagger to the requested representation.
+    pub(crate) fn set_options(&mut self, options: TokenizerOptions) {
+        if self.tagger.representation() != options.tagger {
+            self.tagger = Arc::new(self.tagger.to_representation(options.tagger));
+        }
+
+        self.options = options;
+    }
+
+    /// Gets the options of this tokenizer.
+    pub fn options(&self) -> &TokenizerOptions {
+        &self.options
+    }
+
+    /// Gets the options of this tokenizer (mutable).
+    pub fn options_mut(&mut self) -> &mut TokenizerOptions {
+        &mut self.options
     }
 
     /// Creates a new tokenizer from a reader.
     pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
-        Ok(bincode::deserialize_from(reader)?)
+        let (header, payload) = binary::read(reader, BinaryKind::Tokenizer)?;
+        let mut tokenizer: Tokenizer = bincode::deserialize(&payload)?;
+
+        tokenizer.header = Some(header);
+        let options = tokenizer.options.clone();
+        tokenizer.set_options(options);
+        Ok(tokenizer)
+    }
+
+    /// Writes this tokenizer as a tokenizer binary. Keeps the metadata of the binary it was loaded from, if any.
+    /// The options, including the tagger representation, are stored too and applied by [from_reader][Tokenizer::from_reader].
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let metadata = self
+            .header
+            .as_ref()
+            .map(|header| header.metadata.clone())
+            .unwrap_or_default();
+        binary::write(writer, BinaryKind::Tokenizer, &metadata, self)?;
+
+        Ok(())
+    }
+
+    /// Gets the header of the binary this tokenizer was loaded from.
+    /// `None` if the tokenizer was not loaded from a binary.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
     }
 
     /// Gets all disambigation rules in the order they are applied.
@@ -143,48 +281,204 @@ impl Tokenizer {
         &self.chunker
     }
 
+    /// Compiles all regular expressions used by the disambiguation rules and the tokenization itself.
+    /// See [Rules::warm_up][crate::rules::Rules::warm_up].
+    ///
+    /// Returns an error for every regex which could not be compiled.
+    pub fn warm_up(&self) -> Vec<RegexError> {
+        regex::compile_all(
+            self.rules
+                .iter()
+                .flat_map(DisambiguationRule::regexes)
+                .chain(self.lang_options.extra_join_regexes.iter())
+                .collect(),
+        )
+    }
+
     pub(crate) fn lang_options(&self) -> &TokenizerLangOptions {
         &self.lang_options
     }
 
     pub(crate) fn disambiguate_up_to_id<'t>(
         &'t self,
-        mut tokens: Vec<IncompleteToken<'t>>,
+        tokens: Vec<IncompleteToken<'t>>,
         id: Option<&Index>,
     ) -> Vec<DisambiguatedToken<'t>> {
+        let parallelism = self.options.parallelism.resolve();
+
+        parallelism
+            .install(|| self.disambiguate_with_limits(tokens, id, parallelism.is_parallel(), None))
+            .0
+    }
+
+    /// Disambiguates the tokens while respecting the [Limits] in the options.
+    /// Returns a [Truncation] relative to the sentence if any work was skipped.
+    /// Rules are applied in parallel if `parallel` is set and parallelism is enabled.
+    /// If `trace` is set, the changes to the readings of each token are recorded in it. It must have one entry per token.
+    fn disambiguate_with_limits<'t>(
+        &'t self,
+        mut tokens: Vec<IncompleteToken<'t>>,
+        id: Option<&Index>,
+        parallel: bool,
+        mut trace: Option<&mut DisambiguationTrace>,
+    ) -> (Vec<DisambiguatedToken<'t>>, Option<Truncation>) {
         if tokens.is_empty() {
-            return Vec::new();
+            return (Vec::new(), None);
         }
 
+        let limits = &self.options.limits;
+        let sentence_length = tokens[0].sentence.chars().count();
+        let mut truncation = Truncation {
+            start: 0,
+            end: sentence_length,
+            skipped: false,
+            rules: Vec::new(),
+        };
+
+        if sentence_length > limits.max_sentence_length.unwrap_or(usize::MAX) {
+            truncation.skipped = true;
+            return (
+                tokens.into_iter().map(DisambiguatedToken).collect(),
+                Some(truncation),
+            );
+        }
+
+        let deadline = limits.time_budget.map(|budget| Instant::now() + budget);
         let n = id.map_or(self.rules.len(), |id| {
             self.rules.iter().position(|x| x.id == *id).unwrap()
         });
+
+        let mut finalized = finalize(tokens.iter().cloned().map(DisambiguatedToken).collect());
+        // the tokens each rule read the tags of, for rules which did not change anything the last time they were applied.
+        // these rules can not change anything until the tags of one of the tokens they read change
+        let mut unchanged: Vec<Option<Vec<bool>>> = vec![None; n];
         let mut i = 0;
 
         while i < n {
-            let finalized = finalize(tokens.iter().cloned().map(DisambiguatedToken).collect());
+            let exhausted = Mutex::new(Vec::new());
+            let evaluated = Mutex::new(Vec::new());
+
             let result = self.rules[i..n]
-                .maybe_par_iter()
+                .maybe_par_iter_cond(parallel)
                 .enumerate()
                 .filter_map(|(j, rule)| {
-                    let changes = rule.apply(&finalized, &self);
-                    if changes.is_empty() {
+                    let index = j + i;
+                    if unchanged[index].is_some() {
+                        return None;
+                    }
+
+                    let budget = Budget::new(limits.max_steps, deadline);
+                    let reads = if self.options.incremental {
+                        Some(TagReads::new(finalized.len()))
+                    } else {
+                        None
+                    };
+                    let changes = rule.apply(&finalized, &self, &budget, reads.as_ref());
+
+                    // partial changes of a rule which ran out of budget are discarded
+                    if budget.exhausted() {
+                        exhausted.lock().unwrap().push(index);
+                        None
+                    } else if changes.is_empty() {
+                        if let Some(reads) = reads {
+                            evaluated.lock().unwrap().push((index, reads.into_mask()));
+                        }
                         None
                     } else {
-                        Some((j + i, changes))
+                        Some((index, changes))
                     }
                 })
                 .find_first(|_| true);
 
+            // rules after the first changing one might have been run speculatively, they are run again in the next iteration
+            let end = result.as_ref().map_or(n, |(index, _)| *index);
+            let mut exhausted = exhausted.into_inner().unwrap();
+            exhausted.sort_unstable();
+            truncation.rules.extend(
+                exhausted
+                    .into_iter()
+                    .filter(|index| *index < end)
+                    .map(|index| self.rules[index].id.to_string()),
+            );
+
+            // unless the tokens they read change, speculative results stay valid
+            for (index, reads) in evaluated.into_inner().unwrap() {
+                if index > end {
+                    unchanged[index] = Some(reads);
+                }
+            }
+
             if let Some((index, changes)) = result {
+                let changed = changes.token_mask(&finalized);
+                let before: Option<Vec<Option<Vec<Reading>>>> = trace.as_ref().map(|_| {
+                    tokens
+                        .iter()
+                        .zip(&changed[1..])
+                        .map(|(token, changed)| {
+                            if *changed {
+                                Some(token.word.tags.iter().map(Reading::from).collect())
+                            } else {
+                                None
+                            }
+                        })
+                        .collect()
+                });
+
                 self.rules[index].change(&mut tokens, &self, changes);
+
+                if let (Some(trace), Some(before)) = (trace.as_mut(), before) {
+                    for ((token_changes, token), before) in
+                        trace.tokens.iter_mut().zip(&tokens).zip(before)
+                    {
+                        if let Some(before) = before {
+                            let after: Vec<_> = token.word.tags.iter().map(Reading::from).collect();
+
+                            if after != before {
+                                token_changes.push(TagChange {
+                                    rule: self.rules[index].id.to_string(),
+                                    before,
+                                    after,
+                                });
+                            }
+                        }
+                    }
+                }
+
+                for (finalized_token, (token, changed)) in finalized[1..]
+                    .iter_mut()
+                    .zip(tokens.iter().zip(&changed[1..]))
+                {
+                    if *changed {
+                        *finalized_token = token.clone().into();
+                    }
+                }
+
+                let stale = |reads: &[bool]| {
+                    reads
+                        .iter()
+                        .zip(&changed)
+                        .any(|(read, changed)| *read && *changed)
+                };
+
+                for reads in unchanged[index + 1..].iter_mut() {
+                    if matches!(reads, Some(reads) if stale(reads)) {
+                        *reads = None;
+                    }
+                }
+
                 i = index + 1;
             } else {
                 i = n;
             }
         }
 
-        tokens.into_iter().map(DisambiguatedToken).collect()
+        let tokens = tokens.into_iter().map(DisambiguatedToken).collect();
+
+        if truncation.rules.is_empty() {
+            (tokens, None)
+        } else {
+            (tokens, Some(truncation))
+        }
     }
 
     /// Apply rule-based disambiguation to the tokens.
@@ -196,6 +490,27 @@ impl Tokenizer {
         self.disambiguate_up_to_id(tokens, None)
     }
 
+    /// Apply rule-based disambiguation to the tokens like [disambiguate][Tokenizer::disambiguate].
+    /// Additionally returns a trace of how the readings of each token were changed, aligned with the tokens.
+    /// The truncation of the trace is relative to the sentence.
+    pub fn disambiguate_with_trace<'t>(
+        &'t self,
+        tokens: Vec<IncompleteToken<'t>>,
+    ) -> (Vec<DisambiguatedToken<'t>>, DisambiguationTrace) {
+        let mut trace = DisambiguationTrace {
+            tokens: vec![Vec::new(); tokens.len()],
+            truncation: None,
+        };
+        let parallelism = self.options.parallelism.resolve();
+
+        let (tokens, truncation) = parallelism.install(|| {
+            self.disambiguate_with_limits(tokens, None, parallelism.is_parallel(), Some(&mut trace))
+        });
+        trace.truncation = truncation;
+
+        (tokens, trace)
+    }
+
     fn get_token_strs<'t>(&self, text: &'t str) -> Vec<&'t str> {
         let mut tokens = Vec::new();
 
@@ -311,9 +626,121 @@ impl Tokenizer {
 
     /// Applies the entire tokenization pipeline including sentencization, tagging, chunking and disambiguation.
     pub fn pipe<'t>(&'t self, text: &'t str) -> Vec<Vec<Token<'t>>> {
-        self.sentencize(text)
-            .into_iter()
-            .map(|tokens| finalize(self.disambiguate(tokens)))
-            .collect()
+        self.pipe_with_report(text).0
+    }
+
+    /// Applies the entire tokenization pipeline like [pipe][Tokenizer::pipe].
+    /// Additionally reports the sentences on which disambiguation was cut short because of the [Limits] in the options.
+    /// Sentences which exceed the maximum length are tokenized but not disambiguated.
+    pub fn pipe_with_report<'t>(&'t self, text: &'t str) -> (Vec<Vec<Token<'t>>>, Vec<Truncation>) {
+        let mut sentences = Vec::new();
+        let mut truncations = Vec::new();
+        let mut char_offset = 0;
+        let parallelism = self.options.parallelism.resolve();
+
+        for sentence in self.sentencizer.split(text) {
+            let (tokens, truncation) =
+                parallelism.install(|| self.pipe_sentence(sentence, parallelism.is_parallel()));
+
+            if let Some(mut truncation) = truncation {
+                truncation.rshift(char_offset);
+                truncations.push(truncation);
+            }
+
+            sentences.push(tokens);
+            char_offset += sentence.chars().count();
+        }
+
+        (sentences, truncations)
+    }
+
+    /// Applies the entire tokenization pipeline like [pipe][Tokenizer::pipe].
+    /// Additionally returns a trace of how disambiguation changed the readings of the tokens for each sentence.
+    /// The first entry of each trace belongs to the sentence start token and is always empty.
+    /// Like in [pipe_with_report][Tokenizer::pipe_with_report], the truncations are relative to the text.
+    pub fn pipe_with_trace<'t>(
+        &'t self,
+        text: &'t str,
+    ) -> Vec<(Vec<Token<'t>>, DisambiguationTrace)> {
+        let mut output = Vec::new();
+        let mut char_offset = 0;
+
+        for sentence in self.sentencizer.split(text) {
+            let (tokens, mut trace) = self.disambiguate_with_trace(self.tokenize(sentence));
+            let tokens = finalize(tokens);
+
+            if !tokens.is_empty() {
+                trace.tokens.insert(0, Vec::new());
+            }
+            if let Some(truncation) = trace.truncation.as_mut() {
+                truncation.rshift(char_offset);
+            }
+
+            output.push((tokens, trace));
+            char_offset += sentence.chars().count();
+        }
+
+        output
+    }
+
+    /// Applies the entire tokenization pipeline to multiple texts. The result is the same as calling
+    /// [pipe][Tokenizer::pipe] on each text, but the sentences of all texts are processed in parallel.
+    pub fn pipe_batch<'t, S: AsRef<str> + Sync>(
+        &'t self,
+        texts: &'t [S],
+    ) -> Vec<Vec<Vec<Token<'t>>>> {
+        let sentences = self.batch_sentences(texts);
+        let parallelism = self.options.parallelism.resolve();
+
+        let mut output: Vec<_> = texts.iter().map(|_| Vec::new()).collect();
+        let tokens: Vec<_> = parallelism.install(|| {
+            let parallel = parallelism.is_parallel();
+            let parallel_sentences = parallel && prefer_outer_parallelism(sentences.len());
+            let parallel_rules = parallel && !parallel_sentences;
+
+            sentences
+                .into_maybe_par_iter_cond(parallel_sentences)
+                .map(|(i, _, sentence)| (i, self.pipe_sentence(sentence, parallel_rules).0))
+                .collect()
+        });
+
+        for (i, tokens) in tokens {
+            output[i].push(tokens);
+        }
+
+        output
+    }
+
+    /// Splits each of the texts into sentences.
+    /// Returns the index of the text, the char offset in the text and the sentence itself for each sentence.
+    pub(crate) fn batch_sentences<'t, S: AsRef<str>>(
+        &self,
+        texts: &'t [S],
+    ) -> Vec<(usize, usize, &'t str)> {
+        let mut sentences = Vec::new();
+
+        for (i, text) in texts.iter().enumerate() {
+            let mut char_offset = 0;
+
+            for sentence in self.sentencizer.split(text.as_ref()) {
+                sentences.push((i, char_offset, sentence));
+                char_offset += sentence.chars().count();
+            }
+        }
+
+        sentences
+    }
+
+    /// Applies the entire tokenization pipeline to one sentence.
+    /// Returns a [Truncation] relative to the sentence if disambiguation was cut short.
+    pub(crate) fn pipe_sentence<'t>(
+        &'t self,
+        sentence: &'t str,
+        parallel: bool,
+    ) -> (Vec<Token<'t>>, Option<Truncation>) {
+        let (tokens, truncation) =
+            self.disambiguate_with_limits(self.tokenize(sentence), None, parallel, None);
+
+        (finalize(tokens), truncation)
     }
 }
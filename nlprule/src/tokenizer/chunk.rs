@@ -5,7 +5,10 @@ use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use std::{cmp::Ordering, collections::BinaryHeap};
 
-use crate::types::{DefaultHashMap, DefaultHasher};
+use crate::{
+    types::{DefaultHashMap, DefaultHasher},
+    utils::ordered,
+};
 
 use super::IncompleteToken;
 
@@ -141,7 +144,11 @@ impl From<Model> for ModelFields {
         let mut rows = Vec::new();
         let mut values = Vec::new();
 
-        for (key, context) in model.pmap.iter() {
+        // sorted so the serialized model does not depend on the hasher seed
+        let mut pmap: Vec<_> = model.pmap.iter().collect();
+        pmap.sort_by_key(|(key, _)| **key);
+
+        for (key, context) in pmap {
             assert_eq!(context.outcomes.len(), context.parameters.len());
             assert!(context.outcomes.len() <= std::u8::MAX as usize);
             cols.push((*key, context.outcomes.len() as u8));
@@ -438,6 +445,7 @@ impl MaxentTokenizer {
 #[derive(Serialize, Deserialize)]
 pub(crate) struct MaxentPosTagger {
     pub(crate) model: Model,
+    #[serde(serialize_with = "ordered::map")]
     pub(crate) tagdict: DefaultHashMap<String, Vec<String>>,
 }
 
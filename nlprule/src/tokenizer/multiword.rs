@@ -35,7 +35,7 @@ impl From<MultiwordTaggerFields> for MultiwordTagger {
 pub struct MultiwordTagger {
     #[serde(skip)]
     matcher: AhoCorasick,
-    multiwords: Vec<(String, owned::PosId)>,
+    pub(crate) multiwords: Vec<(String, owned::PosId)>,
 }
 
 impl MultiwordTagger {
@@ -1,12 +1,18 @@
 //! A dictionary-based tagger. The raw format is tuples of the form `(word, lemma, part-of-speech)`
 //! where each word typically has multiple entries with different part-of-speech tags.
 
-use crate::types::*;
+use crate::{
+    types::*,
+    utils::{
+        mapped::{self, Bytes},
+        ordered,
+    },
+};
 use bimap::BiMap;
-use fst::{IntoStreamer, Map, Streamer};
+use fst::{Map, Streamer};
 use indexmap::IndexMap;
 use log::error;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{borrow::Cow, iter::once};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,19 +35,91 @@ impl Default for TaggerLangOptions {
     }
 }
 
+fn encode_tag(inflect_id: WordIdInt, pos_id: PosIdInt) -> u64 {
+    let pos_bytes = pos_id.0.to_be_bytes();
+    let inflect_bytes = inflect_id.0.to_be_bytes();
+
+    u64::from_be_bytes([
+        inflect_bytes[0],
+        inflect_bytes[1],
+        inflect_bytes[2],
+        inflect_bytes[3],
+        0,
+        0,
+        pos_bytes[0],
+        pos_bytes[1],
+    ])
+}
+
+fn decode_tag(value: u64) -> (WordIdInt, PosIdInt) {
+    let value_bytes = value.to_be_bytes();
+    let inflect_id = WordIdInt(u32::from_be_bytes([
+        value_bytes[0],
+        value_bytes[1],
+        value_bytes[2],
+        value_bytes[3],
+    ]));
+    let pos_id = PosIdInt(u16::from_be_bytes([value_bytes[6], value_bytes[7]]));
+
+    (inflect_id, pos_id)
+}
+
+/// Gets the `i`-th element of a table of little-endian `u32`s.
+fn read_u32(table: &[u8], i: usize) -> u32 {
+    let bytes = &table[i * 4..(i + 1) * 4];
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Gets the `i`-th range of a table of end offsets.
+fn read_range(ends: &[u8], i: usize) -> std::ops::Range<usize> {
+    let start = if i == 0 { 0 } else { read_u32(ends, i - 1) };
+    start as usize..read_u32(ends, i) as usize
+}
+
 #[derive(Serialize, Deserialize)]
-struct TaggerFields {
-    tag_fst: Vec<u8>,
-    word_store_fst: Vec<u8>,
+struct TaggerFields<'a> {
+    #[serde(borrow)]
+    tag_fst: Cow<'a, [u8]>,
+    #[serde(borrow)]
+    word_store_fst: Cow<'a, [u8]>,
+    /// The concatenated words ordered by ID, split at the end offsets in `word_ends`.
+    #[serde(borrow)]
+    words: Cow<'a, [u8]>,
+    #[serde(borrow)]
+    word_ends: Cow<'a, [u8]>,
+    /// The concatenated group members ordered by the ID of the group lemma, split at the end offsets in `group_ends`.
+    #[serde(borrow)]
+    group_members: Cow<'a, [u8]>,
+    #[serde(borrow)]
+    group_ends: Cow<'a, [u8]>,
+    #[serde(serialize_with = "ordered::bimap")]
     tag_store: BiMap<String, PosIdInt>,
     lang_options: TaggerLangOptions,
+    fingerprint: Option<u64>,
 }
 
-impl From<Tagger> for TaggerFields {
-    fn from(tagger: Tagger) -> Self {
+impl<'a> From<&'a Tagger> for TaggerFields<'a> {
+    fn from(tagger: &'a Tagger) -> Self {
+        let data = match &tagger.data {
+            TaggerData::Heap(data) => data,
+            TaggerData::Fst(data) => {
+                return TaggerFields {
+                    tag_fst: data.tags.as_fst().as_bytes().into(),
+                    word_store_fst: data.word_store.as_fst().as_bytes().into(),
+                    words: data.words.as_ref().into(),
+                    word_ends: data.word_ends.as_ref().into(),
+                    group_members: data.group_members.as_ref().into(),
+                    group_ends: data.group_ends.as_ref().into(),
+                    tag_store: tagger.tag_store.clone(),
+                    lang_options: tagger.lang_options.clone(),
+                    fingerprint: tagger.fingerprint,
+                };
+            }
+        };
+
         let mut tag_fst_items = Vec::new();
 
-        for (word_id, map) in tagger.tags.iter() {
+        for (word_id, map) in data.tags.iter() {
             let mut i = 0u8;
             let word = tagger.str_for_word_id(word_id);
 
@@ -51,34 +129,21 @@ impl From<Tagger> for TaggerFields {
                     i += 1;
 
                     let key: Vec<u8> = word.as_bytes().iter().chain(once(&i)).copied().collect();
-                    let pos_bytes = pos_id.0.to_be_bytes();
-                    let inflect_bytes = inflect_id.0.to_be_bytes();
-
-                    let value = u64::from_be_bytes([
-                        inflect_bytes[0],
-                        inflect_bytes[1],
-                        inflect_bytes[2],
-                        inflect_bytes[3],
-                        0,
-                        0,
-                        pos_bytes[0],
-                        pos_bytes[1],
-                    ]);
-                    tag_fst_items.push((key, value));
+                    tag_fst_items.push((key, encode_tag(*inflect_id, *pos_id)));
                 }
             }
         }
 
         tag_fst_items.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-        let mut word_store_items: Vec<_> = tagger
+        let mut word_store_items: Vec<_> = data
             .word_store
             .iter()
             .map(|(key, value)| (key.clone(), value.0 as u64))
             .collect();
         word_store_items.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-        let tag_fst = Map::from_iter(tag_fst_items)
+        let tag_fst = Map::from_iter(tag_fst_items.iter().cloned())
             .unwrap()
             .into_fst()
             .as_bytes()
@@ -89,20 +154,89 @@ impl From<Tagger> for TaggerFields {
             .as_bytes()
             .to_vec();
 
+        // groups are stored in the order of the tag FST so they are the same as in a tagger on the heap
+        let mut groups: DefaultHashMap<WordIdInt, Vec<WordIdInt>> = DefaultHashMap::new();
+        for (key, value) in tag_fst_items.iter() {
+            let word = std::str::from_utf8(&key[..key.len() - 1]).unwrap();
+            let word_id = *data.word_store.get_by_left(word).unwrap();
+            let (inflect_id, _) = decode_tag(*value);
+
+            let group = groups.entry(inflect_id).or_insert_with(Vec::new);
+            if !group.contains(&word_id) {
+                group.push(word_id);
+            }
+        }
+
+        let mut words = Vec::new();
+        let mut word_ends = Vec::new();
+        let mut group_members = Vec::new();
+        let mut group_ends = Vec::new();
+
+        for i in 0..data.word_store.len() {
+            let id = WordIdInt(i as u32);
+
+            words.extend(tagger.str_for_word_id(&id).as_bytes());
+            word_ends.extend(&(words.len() as u32).to_le_bytes());
+
+            for member in groups.get(&id).into_iter().flatten() {
+                group_members.extend(&member.0.to_le_bytes());
+            }
+            group_ends.extend(&((group_members.len() / 4) as u32).to_le_bytes());
+        }
+
         TaggerFields {
-            tag_fst,
-            word_store_fst,
-            tag_store: tagger.tag_store,
-            lang_options: tagger.lang_options,
+            tag_fst: tag_fst.into(),
+            word_store_fst: word_store_fst.into(),
+            words: words.into(),
+            word_ends: word_ends.into(),
+            group_members: group_members.into(),
+            group_ends: group_ends.into(),
+            tag_store: tagger.tag_store.clone(),
+            lang_options: tagger.lang_options.clone(),
+            fingerprint: tagger.fingerprint,
         }
     }
 }
 
-impl From<TaggerFields> for Tagger {
-    fn from(data: TaggerFields) -> Self {
-        let word_store_fst = Map::new(data.word_store_fst).unwrap();
-        let word_store: BiMap<String, WordIdInt> = word_store_fst
-            .into_stream()
+impl<'a> From<TaggerFields<'a>> for Tagger {
+    fn from(data: TaggerFields<'a>) -> Self {
+        // fields which were deserialized from the current mapping are kept in place
+        let bytes = |bytes: Cow<[u8]>| match bytes {
+            Cow::Borrowed(borrowed) => mapped::locate(borrowed)
+                .map(Bytes::Mapped)
+                .unwrap_or_else(|| Bytes::owned(borrowed.to_vec())),
+            Cow::Owned(owned) => Bytes::owned(owned),
+        };
+
+        Tagger {
+            data: TaggerData::Fst(FstData {
+                tags: Map::new(bytes(data.tag_fst)).unwrap(),
+                word_store: Map::new(bytes(data.word_store_fst)).unwrap(),
+                words: bytes(data.words),
+                word_ends: bytes(data.word_ends),
+                group_members: bytes(data.group_members),
+                group_ends: bytes(data.group_ends),
+            }),
+            tag_store: data.tag_store,
+            lang_options: data.lang_options,
+            fingerprint: data.fingerprint,
+        }
+    }
+}
+
+/// Tagger data expanded into heap structures.
+#[derive(Default, Clone)]
+pub(crate) struct HeapData {
+    pub(crate) tags: DefaultHashMap<WordIdInt, IndexMap<WordIdInt, Vec<PosIdInt>>>,
+    pub(crate) word_store: BiMap<String, WordIdInt>,
+    pub(crate) groups: DefaultHashMap<WordIdInt, Vec<WordIdInt>>,
+}
+
+impl From<&FstData> for HeapData {
+    fn from(data: &FstData) -> Self {
+        let word_store: BiMap<String, WordIdInt> = data
+            .word_store
+            .stream()
             .into_str_vec()
             .unwrap()
             .into_iter()
@@ -112,21 +246,12 @@ impl From<TaggerFields> for Tagger {
         let mut tags = DefaultHashMap::new();
         let mut groups = DefaultHashMap::new();
 
-        let tag_fst = Map::new(data.tag_fst).unwrap();
-        let mut stream = tag_fst.into_stream();
+        let mut stream = data.tags.stream();
 
         while let Some((key, value)) = stream.next() {
             let word = std::str::from_utf8(&key[..key.len() - 1]).unwrap();
             let word_id = *word_store.get_by_left(word).unwrap();
-
-            let value_bytes = value.to_be_bytes();
-            let inflection_id = WordIdInt(u32::from_be_bytes([
-                value_bytes[0],
-                value_bytes[1],
-                value_bytes[2],
-                value_bytes[3],
-            ]));
-            let pos_id = PosIdInt(u16::from_be_bytes([value_bytes[6], value_bytes[7]]));
+            let (inflection_id, pos_id) = decode_tag(value);
 
             let group = groups.entry(inflection_id).or_insert_with(Vec::new);
             if !group.contains(&word_id) {
@@ -140,49 +265,165 @@ impl From<TaggerFields> for Tagger {
                 .push(pos_id);
         }
 
-        Tagger {
+        HeapData {
             tags,
-            tag_store: data.tag_store,
             word_store,
             groups,
-            lang_options: data.lang_options,
         }
     }
 }
 
+/// Tagger data which is queried in place from FSTs and compact tables of word and group offsets.
+/// The bytes are either owned or part of a memory-mapped binary.
+#[derive(Clone)]
+pub(crate) struct FstData {
+    tags: Map<Bytes>,
+    word_store: Map<Bytes>,
+    words: Bytes,
+    word_ends: Bytes,
+    group_members: Bytes,
+    group_ends: Bytes,
+}
+
+impl FstData {
+    fn str_for_word_id(&self, id: &WordIdInt) -> &str {
+        let range = read_range(self.word_ends.as_ref(), id.0 as usize);
+        std::str::from_utf8(&self.words.as_ref()[range]).expect("words are valid UTF-8")
+    }
+
+    fn group_members(&self, id: &WordIdInt) -> impl Iterator<Item = WordIdInt> + '_ {
+        let members = self.group_members.as_ref();
+
+        read_range(self.group_ends.as_ref(), id.0 as usize)
+            .map(move |i| WordIdInt(read_u32(members, i)))
+    }
+}
+
+/// The data used for lookup by the tagger.
+#[derive(Clone)]
+pub(crate) enum TaggerData {
+    Heap(HeapData),
+    Fst(FstData),
+}
+
+impl Default for TaggerData {
+    fn default() -> Self {
+        TaggerData::Heap(HeapData::default())
+    }
+}
+
+/// How the dictionary of a [Tagger] is represented in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaggerRepresentation {
+    /// The dictionary is expanded into hash maps. Lookup is fastest but memory use is high.
+    Heap,
+    /// The dictionary is queried directly from FSTs. Uses a fraction of the memory at the cost of slower lookup.
+    Fst,
+}
+
+impl Default for TaggerRepresentation {
+    fn default() -> Self {
+        TaggerRepresentation::Heap
+    }
+}
+
 /// The lexical tagger.
-#[derive(Default, Serialize, Deserialize, Clone)]
-#[serde(from = "TaggerFields", into = "TaggerFields")]
+///
+/// The dictionary is either expanded into heap structures or queried from FSTs, see [TaggerRepresentation].
+/// A tagger loaded from a memory-mapped binary (see [Tokenizer::new_mapped][crate::Tokenizer::new_mapped])
+/// queries its FSTs in place in the mapping.
+#[derive(Default, Clone)]
 pub struct Tagger {
-    pub(crate) tags: DefaultHashMap<WordIdInt, IndexMap<WordIdInt, Vec<PosIdInt>>>,
+    pub(crate) data: TaggerData,
     pub(crate) tag_store: BiMap<String, PosIdInt>,
-    pub(crate) word_store: BiMap<String, WordIdInt>,
-    pub(crate) groups: DefaultHashMap<WordIdInt, Vec<WordIdInt>>,
     pub(crate) lang_options: TaggerLangOptions,
+    pub(crate) fingerprint: Option<u64>,
+}
+
+impl Serialize for Tagger {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TaggerFields::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tagger {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(TaggerFields::deserialize(deserializer)?.into())
+    }
 }
 
 impl Tagger {
-    fn get_raw(&self, word: &str) -> Vec<WordData> {
-        if let Some(map) = self
-            .word_store
-            .get_by_left(word)
-            .and_then(|x| self.tags.get(x))
-        {
-            let mut output = Vec::new();
-
-            for (key, value) in map.iter() {
-                for pos_id in value {
-                    output.push(WordData::new(
-                        self.id_word(self.str_for_word_id(key).into()),
-                        self.id_tag(self.str_for_pos_id(pos_id)),
-                    ))
+    /// Gets a fingerprint identifying the dictionary of this tagger.
+    /// Rules store word ids which are only valid for a tagger with the same fingerprint.
+    /// `None` if the tagger was not compiled from a dictionary.
+    pub fn fingerprint(&self) -> Option<u64> {
+        self.fingerprint
+    }
+
+    /// Gets the representation of the dictionary of this tagger.
+    pub fn representation(&self) -> TaggerRepresentation {
+        match &self.data {
+            TaggerData::Heap(_) => TaggerRepresentation::Heap,
+            TaggerData::Fst(_) => TaggerRepresentation::Fst,
+        }
+    }
+
+    /// Converts this tagger to the given representation. Clones the tagger if it already has the representation.
+    pub fn to_representation(&self, representation: TaggerRepresentation) -> Tagger {
+        match (&self.data, representation) {
+            (TaggerData::Fst(data), TaggerRepresentation::Heap) => Tagger {
+                data: TaggerData::Heap(data.into()),
+                tag_store: self.tag_store.clone(),
+                lang_options: self.lang_options.clone(),
+                fingerprint: self.fingerprint,
+            },
+            (TaggerData::Heap(_), TaggerRepresentation::Fst) => TaggerFields::from(self).into(),
+            _ => self.clone(),
+        }
+    }
+
+    fn get_raw(&self, word: &str) -> Vec<WordData<'_>> {
+        let mut output = Vec::new();
+        let mut push = |inflect_id: &WordIdInt, pos_id: &PosIdInt| {
+            output.push(WordData::new(
+                WordId(self.str_for_word_id(inflect_id).into(), Some(*inflect_id)),
+                self.id_tag(self.str_for_pos_id(pos_id)),
+            ))
+        };
+
+        match &self.data {
+            TaggerData::Heap(data) => {
+                if let Some(map) = data
+                    .word_store
+                    .get_by_left(word)
+                    .and_then(|x| data.tags.get(x))
+                {
+                    for (key, value) in map.iter() {
+                        for pos_id in value {
+                            push(key, pos_id);
+                        }
+                    }
                 }
             }
+            TaggerData::Fst(data) => {
+                // keys are the word followed by the index of the entry, starting at one
+                let mut key = word.as_bytes().to_vec();
+                key.push(0);
+
+                for i in 1..=u8::MAX {
+                    key[word.len()] = i;
 
-            output
-        } else {
-            Vec::new()
+                    if let Some(value) = data.tags.get(&key) {
+                        let (inflect_id, pos_id) = decode_tag(value);
+                        push(&inflect_id, &pos_id);
+                    } else {
+                        break;
+                    }
+                }
+            }
         }
+
+        output
     }
 
     fn get_strict_tags(
@@ -204,23 +445,59 @@ impl Tagger {
         tags
     }
 
-    #[allow(dead_code)] // used by compile module
     pub(crate) fn tag_store(&self) -> &BiMap<String, PosIdInt> {
         &self.tag_store
     }
 
     #[allow(dead_code)] // used by compile module
     pub(crate) fn word_store(&self) -> &BiMap<String, WordIdInt> {
-        &self.word_store
+        match &self.data {
+            TaggerData::Heap(data) => &data.word_store,
+            TaggerData::Fst(_) => {
+                panic!("the word store is only available for taggers on the heap")
+            }
+        }
     }
 
-    fn str_for_word_id(&self, id: &WordIdInt) -> &str {
-        self.word_store
-            .get_by_right(id)
-            .expect("only valid word ids are created")
+    /// Gets the number of words in the dictionary.
+    pub(crate) fn n_words(&self) -> usize {
+        match &self.data {
+            TaggerData::Heap(data) => data.word_store.len(),
+            TaggerData::Fst(data) => data.word_store.len(),
+        }
     }
 
-    fn str_for_pos_id(&self, id: &PosIdInt) -> &str {
+    /// Gets the number of `(word, lemma, part-of-speech)` entries in the dictionary.
+    pub(crate) fn n_entries(&self) -> usize {
+        match &self.data {
+            TaggerData::Heap(data) => data
+                .tags
+                .values()
+                .flat_map(|map| map.values())
+                .map(Vec::len)
+                .sum(),
+            TaggerData::Fst(data) => data.tags.len(),
+        }
+    }
+
+    fn word_id(&self, word: &str) -> Option<WordIdInt> {
+        match &self.data {
+            TaggerData::Heap(data) => data.word_store.get_by_left(word).copied(),
+            TaggerData::Fst(data) => data.word_store.get(word).map(|x| WordIdInt(x as u32)),
+        }
+    }
+
+    pub(crate) fn str_for_word_id(&self, id: &WordIdInt) -> &str {
+        match &self.data {
+            TaggerData::Heap(data) => data
+                .word_store
+                .get_by_right(id)
+                .expect("only valid word ids are created"),
+            TaggerData::Fst(data) => data.str_for_word_id(id),
+        }
+    }
+
+    pub(crate) fn str_for_pos_id(&self, id: &PosIdInt) -> &str {
         self.tag_store
             .get_by_right(id)
             .expect("only valid pos ids are created")
@@ -244,7 +521,7 @@ impl Tagger {
     /// Tags the given text.
     /// Unknown words will not get a numerical id.
     pub fn id_word<'t>(&'t self, text: Cow<'t, str>) -> WordId<'t> {
-        let id = self.word_store.get_by_left(text.as_ref()).copied();
+        let id = self.word_id(text.as_ref());
         WordId(text, id)
     }
 
@@ -324,10 +601,21 @@ impl Tagger {
 
     /// Get the words with the same lemma as the given lemma.
     pub fn get_group_members(&self, lemma: &str) -> Vec<&str> {
-        self.word_store
-            .get_by_left(lemma)
-            .and_then(|x| self.groups.get(x))
-            .map(|vec| vec.iter().map(|x| self.str_for_word_id(x)).collect())
-            .unwrap_or_else(Vec::new)
+        match &self.data {
+            TaggerData::Heap(data) => data
+                .word_store
+                .get_by_left(lemma)
+                .and_then(|x| data.groups.get(x))
+                .map(|vec| vec.iter().map(|x| self.str_for_word_id(x)).collect())
+                .unwrap_or_else(Vec::new),
+            TaggerData::Fst(data) => self
+                .word_id(lemma)
+                .map(|id| {
+                    data.group_members(&id)
+                        .map(|x| self.str_for_word_id(&x))
+                        .collect()
+                })
+                .unwrap_or_else(Vec::new),
+        }
     }
 }
@@ -4,7 +4,8 @@ use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
-    collections::{hash_map, HashMap, HashSet},
+    collections::{hash_map, BTreeMap, HashMap, HashSet},
+    time::Duration,
 };
 
 use crate::tokenizer::tag::Tagger;
@@ -326,3 +327,128 @@ impl Suggestion {
         self.end += offset;
     }
 }
+
+/// Limits on the work done per sentence to protect against very long or adversarial input.
+/// Work exceeding a limit is skipped and reported as a [Truncation]. By default there are no limits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Limits {
+    /// Sentences with more characters than this are not processed at all.
+    pub max_sentence_length: Option<usize>,
+    /// The maximum number of matching steps a single rule may take on one sentence.
+    /// A rule exceeding this is stopped and does not produce any further matches for the sentence.
+    pub max_steps: Option<usize>,
+    /// The maximum time spent on one sentence. Rules which are still running or have not started
+    /// when the time is up are skipped.
+    pub time_budget: Option<Duration>,
+}
+
+/// Work on a sentence which was cut short because it exceeded the [Limits].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Truncation {
+    /// The start character index of the sentence (inclusive).
+    pub start: usize,
+    /// The end character index of the sentence (exclusive).
+    pub end: usize,
+    /// Whether the sentence exceeded the maximum length and was skipped entirely.
+    pub skipped: bool,
+    /// IDs of the rules which were stopped or skipped on this sentence.
+    pub rules: Vec<String>,
+}
+
+impl Truncation {
+    /// Shift `start` and `end` to the right by the specified amount.
+    pub fn rshift(&mut self, offset: usize) {
+        self.start += offset;
+        self.end += offset;
+    }
+}
+
+/// A reading of a word i. e. a lemma and a part-of-speech tag.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Reading {
+    /// The lemma.
+    pub lemma: String,
+    /// The part-of-speech tag.
+    pub pos: String,
+}
+
+impl<'t> From<&WordData<'t>> for Reading {
+    fn from(data: &WordData<'t>) -> Self {
+        Reading {
+            lemma: data.lemma.as_ref().to_owned(),
+            pos: data.pos.as_ref().to_owned(),
+        }
+    }
+}
+
+impl From<&owned::WordData> for Reading {
+    fn from(data: &owned::WordData) -> Self {
+        Reading {
+            lemma: data.lemma.as_ref().to_owned(),
+            pos: data.pos.as_ref().to_owned(),
+        }
+    }
+}
+
+/// A change of the readings of a token by a disambiguation rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagChange {
+    /// The ID of the disambiguation rule.
+    pub rule: String,
+    /// The readings before the rule was applied.
+    pub before: Vec<Reading>,
+    /// The readings after the rule was applied.
+    pub after: Vec<Reading>,
+}
+
+/// The changes disambiguation rules made to the tokens of a sentence.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DisambiguationTrace {
+    /// The changes of each token in the order the rules were applied. Only rules which changed
+    /// the readings of a token are recorded. Aligned with the tokens the trace is returned with.
+    pub tokens: Vec<Vec<TagChange>>,
+    /// Whether disambiguation was cut short because of the [Limits]. Rules which were stopped or skipped
+    /// are missing from the trace.
+    pub truncation: Option<Truncation>,
+}
+
+/// Statistics of one rule, see [Profile].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleProfile {
+    /// The total time spent applying the rule.
+    pub time: Duration,
+    /// The number of sentences the rule was applied to. Sentences skipped by the prefilter are not counted.
+    pub evaluations: usize,
+    /// The number of matches of the pattern, including those which did not produce a suggestion e.g. because of unification.
+    pub matches: usize,
+    /// The number of suggestions, including those dropped because they overlap the suggestion of another rule.
+    pub suggestions: usize,
+}
+
+impl RuleProfile {
+    /// Adds the statistics of `other` to these statistics.
+    pub fn merge(&mut self, other: &RuleProfile) {
+        self.time += other.time;
+        self.evaluations += other.evaluations;
+        self.matches += other.matches;
+        self.suggestions += other.suggestions;
+    }
+}
+
+/// Per-rule statistics collected by [Rules::apply_with_profile][crate::rules::Rules::apply_with_profile]
+/// and [Rules::suggest_with_profile][crate::rules::Rules::suggest_with_profile].
+/// A profile can be passed to multiple calls to accumulate the statistics over a corpus.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// The statistics of each rule by rule ID. Rules which were never applied are not included.
+    pub rules: BTreeMap<String, RuleProfile>,
+}
+
+impl Profile {
+    /// Adds the statistics of `other` to this profile.
+    pub fn merge(&mut self, other: &Profile) {
+        for (id, rule) in &other.rules {
+            self.rules.entry(id.clone()).or_default().merge(rule);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Bookkeeping for the [Limits][crate::types::Limits] on the work done per sentence.
+
+use std::{cell::Cell, time::Instant};
+
+/// The clock is only checked every `CLOCK_INTERVAL` steps since getting the time is comparatively expensive.
+const CLOCK_INTERVAL: usize = 1024;
+
+/// Tracks the work done by one rule on one sentence.
+/// Once the budget is exhausted, all matching done with it stops and reports no further matches.
+#[derive(Debug, Default)]
+pub struct Budget {
+    max_steps: Option<usize>,
+    deadline: Option<Instant>,
+    steps: Cell<usize>,
+    exhausted: Cell<bool>,
+}
+
+impl Budget {
+    /// Creates a new budget. If the deadline has already passed, the budget is exhausted from the start.
+    pub fn new(max_steps: Option<usize>, deadline: Option<Instant>) -> Self {
+        let exhausted = matches!(deadline, Some(deadline) if Instant::now() >= deadline);
+
+        Budget {
+            max_steps,
+            deadline,
+            steps: Cell::new(0),
+            exhausted: Cell::new(exhausted),
+        }
+    }
+
+    /// Records `n` steps of work. Returns `false` if the budget is exhausted, in which case the work must be stopped.
+    pub fn step(&self, n: usize) -> bool {
+        if self.exhausted.get() {
+            return false;
+        }
+
+        let prev = self.steps.get();
+        let steps = prev + n;
+        self.steps.set(steps);
+
+        let exhausted = steps > self.max_steps.unwrap_or(usize::MAX)
+            || (prev / CLOCK_INTERVAL != steps / CLOCK_INTERVAL
+                && matches!(self.deadline, Some(deadline) if Instant::now() >= deadline));
+
+        self.exhausted.set(exhausted);
+        !exhausted
+    }
+
+    /// Whether the budget is exhausted.
+    pub fn exhausted(&self) -> bool {
+        self.exhausted.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn steps_are_limited() {
+        let budget = Budget::new(Some(10), None);
+
+        assert!(budget.step(4));
+        assert!(budget.step(6));
+        assert!(!budget.step(1));
+        assert!(budget.exhausted());
+        assert!(!budget.step(0));
+    }
+
+    #[test]
+    fn deadline_is_checked() {
+        let budget = Budget::new(None, Some(Instant::now() - Duration::from_secs(1)));
+        assert!(budget.exhausted());
+
+        let budget = Budget::new(None, Some(Instant::now() + Duration::from_secs(3600)));
+        assert!(budget.step(CLOCK_INTERVAL * 4));
+        assert!(!budget.exhausted());
+    }
+
+    #[test]
+    fn default_is_unlimited() {
+        let budget = Budget::default();
+        assert!(budget.step(usize::MAX / 2));
+        assert!(!budget.exhausted());
+    }
+}
@@ -0,0 +1,177 @@
+//! Values which are deserialized on first use.
+//! A [Lazy] value is serialized as a nested bincode blob so deserializing the surrounding structure
+//! only copies (or, for memory-mapped binaries, references) the bytes of the value.
+
+use crate::utils::mapped::{self, Bytes};
+use once_cell::sync::OnceCell;
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// A value which is deserialized from its bytes the first time it is accessed.
+pub(crate) struct Lazy<T> {
+    bytes: Option<Bytes>,
+    value: OnceCell<T>,
+}
+
+impl<T> Lazy<T> {
+    /// Creates a lazy value which is already loaded.
+    pub(crate) fn new(value: T) -> Self {
+        Lazy {
+            bytes: None,
+            value: OnceCell::from(value),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Lazy<T> {
+    /// Gets the value, deserializing it if it has not been accessed yet.
+    ///
+    /// # Panics
+    /// If the bytes can not be deserialized. Containers check their lazy values with a checksum
+    /// or by [forcing][Lazy::force] them when they are loaded, so this only happens for unchecked data.
+    pub(crate) fn get(&self) -> &T {
+        self.try_get()
+            .expect("lazy values are checked when their container is loaded")
+    }
+
+    /// Gets the value like [get][Lazy::get], but returns an error if the bytes can not be deserialized.
+    pub(crate) fn try_get(&self) -> Result<&T, bincode::Error> {
+        self.value.get_or_try_init(|| {
+            let bytes = self
+                .bytes
+                .as_ref()
+                .expect("lazy value without bytes must be loaded");
+            bincode::deserialize(bytes.as_ref())
+        })
+    }
+
+    /// Gets the value (mutable), deserializing it if it has not been accessed yet.
+    #[cfg(feature = "compile")]
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        self.get();
+        self.bytes = None;
+        self.value.get_mut().unwrap()
+    }
+
+    /// Deserializes the value if it has not been accessed yet and releases its bytes.
+    pub(crate) fn force(&mut self) -> Result<(), bincode::Error> {
+        self.try_get()?;
+        self.bytes = None;
+        Ok(())
+    }
+
+    /// Consumes the lazy value, returning the deserialized value.
+    pub(crate) fn into_inner(mut self) -> Result<T, bincode::Error> {
+        self.force()?;
+        Ok(self.value.into_inner().unwrap())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.get() {
+            Some(value) => value.fmt(f),
+            None => f.write_str("<not loaded>"),
+        }
+    }
+}
+
+/// Serializes a reference to a value in the same format as a [Lazy] value.
+pub(crate) struct LazyRef<'a, T>(pub &'a T);
+
+impl<'a, T: Serialize> Serialize for LazyRef<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = bincode::serialize(self.0).map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<T: Serialize> Serialize for Lazy<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.value.get(), &self.bytes) {
+            (Some(value), _) => LazyRef(value).serialize(serializer),
+            (None, Some(bytes)) => serializer.serialize_bytes(bytes.as_ref()),
+            (None, None) => unreachable!("lazy value is either loaded or has bytes"),
+        }
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the bytes of a serialized value")
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        // bytes in a memory-mapped binary are kept in place
+        Ok(mapped::locate(v)
+            .map(Bytes::Mapped)
+            .unwrap_or_else(|| Bytes::owned(v.to_vec())))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Bytes::owned(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Bytes::owned(v))
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Lazy<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Lazy {
+            bytes: Some(deserializer.deserialize_bytes(BytesVisitor)?),
+            value: OnceCell::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_is_loaded_on_first_use() {
+        let bytes = bincode::serialize(&Lazy::new(vec![1u32, 2, 3])).unwrap();
+        let lazy: Lazy<Vec<u32>> = bincode::deserialize(&bytes).unwrap();
+
+        assert!(lazy.value.get().is_none());
+        assert_eq!(lazy.get(), &vec![1, 2, 3]);
+        assert!(lazy.value.get().is_some());
+    }
+
+    #[test]
+    fn invalid_bytes_are_reported() {
+        let bytes = bincode::serialize(&Lazy::new(vec![1u32, 2, 3])).unwrap();
+        // the length of the nested blob is kept, the length of the vector is corrupted
+        let mut corrupt = bytes.clone();
+        corrupt[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        let mut lazy: Lazy<Vec<u32>> = bincode::deserialize(&corrupt).unwrap();
+
+        assert!(lazy.try_get().is_err());
+        assert!(lazy.force().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compile")]
+    fn changed_value_is_serialized() {
+        let bytes = bincode::serialize(&Lazy::new(vec![1u32, 2, 3])).unwrap();
+        let mut lazy: Lazy<Vec<u32>> = bincode::deserialize(&bytes).unwrap();
+
+        lazy.get_mut().push(4);
+        assert_eq!(
+            bincode::deserialize::<Lazy<Vec<u32>>>(&bincode::serialize(&lazy).unwrap())
+                .unwrap()
+                .into_inner()
+                .unwrap(),
+            vec![1, 2, 3, 4]
+        );
+    }
+}
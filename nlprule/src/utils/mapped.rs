@@ -0,0 +1,100 @@
+//! Support for loading binaries from memory-mapped files.
+//! Structures which are large and can be queried in place (e. g. the FSTs of the [Tagger][crate::tokenizer::tag::Tagger])
+//! keep references into the mapping instead of copying their data to the heap.
+
+use fs_err::File;
+use memmap2::Mmap;
+use serde::Deserialize;
+use std::{cell::RefCell, io, ops::Range, path::Path, sync::Arc};
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Mmap>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous mapping when dropped, also if deserialization panics.
+struct CurrentGuard(Option<Arc<Mmap>>);
+
+impl Drop for CurrentGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|x| *x.borrow_mut() = previous);
+    }
+}
+
+/// A range of bytes in a memory-mapped file. Keeps the mapping alive.
+#[derive(Clone)]
+pub(crate) struct MappedBytes {
+    map: Arc<Mmap>,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for MappedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.map[self.range.clone()]
+    }
+}
+
+/// Bytes which are either owned or part of a memory-mapped file.
+#[derive(Clone)]
+pub(crate) enum Bytes {
+    Owned(Arc<[u8]>),
+    Mapped(MappedBytes),
+}
+
+impl Bytes {
+    pub(crate) fn owned(bytes: Vec<u8>) -> Self {
+        Bytes::Owned(bytes.into())
+    }
+}
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Mapped(bytes) => bytes.as_ref(),
+        }
+    }
+}
+
+/// Maps the file at the given path into memory.
+///
+/// # Safety
+/// The file must not be modified while it is mapped, see [Mmap::map].
+pub(crate) unsafe fn map<P: AsRef<Path>>(p: P) -> io::Result<Arc<Mmap>> {
+    let file = File::open(p.as_ref())?;
+    Ok(Arc::new(Mmap::map(file.file())?))
+}
+
+/// Deserializes a value from the mapping, starting at the given offset. Deserialization of the value can
+/// [locate] borrowed bytes in the mapping to keep them in place.
+pub(crate) fn deserialize<'de, T: Deserialize<'de>>(
+    map: &'de Arc<Mmap>,
+    offset: usize,
+) -> Result<T, bincode::Error> {
+    let previous = CURRENT.with(|x| x.borrow_mut().replace(Arc::clone(map)));
+    let _guard = CurrentGuard(previous);
+
+    bincode::deserialize(&map[offset..])
+}
+
+/// Finds the given bytes in the mapping which is currently being deserialized.
+/// Returns `None` if no mapping is being deserialized or the bytes are not part of it.
+pub(crate) fn locate(bytes: &[u8]) -> Option<MappedBytes> {
+    CURRENT.with(|x| {
+        let current = x.borrow();
+        let map = current.as_ref()?;
+
+        let base = map.as_ptr() as usize;
+        let start = bytes.as_ptr() as usize;
+
+        if start < base || start + bytes.len() > base + map.len() {
+            return None;
+        }
+
+        let start = start - base;
+        Some(MappedBytes {
+            map: Arc::clone(map),
+            range: start..start + bytes.len(),
+        })
+    })
+}
@@ -1,5 +1,9 @@
 use lazy_static::lazy_static;
 
+pub mod budget;
+pub mod lazy;
+pub mod mapped;
+pub mod ordered;
 pub mod parallelism;
 pub mod regex;
 
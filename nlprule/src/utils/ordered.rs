@@ -0,0 +1,87 @@
+//! Serializers for hashed collections which write the entries in sorted order.
+//! The iteration order of hashed collections depends on the random seed of the hasher, so serializing them
+//! directly would make binaries compiled from the same sources differ. Use with `#[serde(serialize_with = "..")]`.
+//! The format is the same as the one of the collection itself so deserialization does not change.
+
+use bimap::BiMap;
+use serde::{Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::Hash,
+};
+
+struct SortedSet<'a, T, H>(&'a HashSet<T, H>);
+
+impl<'a, T: Ord + Serialize, H> Serialize for SortedSet<'a, T, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut items: Vec<_> = self.0.iter().collect();
+        items.sort();
+        serializer.collect_seq(items)
+    }
+}
+
+/// Serializes a hash map with the entries sorted by key.
+pub(crate) fn map<K, V, H, S>(map: &HashMap<K, V, H>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// Serializes an optional hash set with the items sorted.
+pub(crate) fn option_set<T, H, S>(
+    set: &Option<HashSet<T, H>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: Ord + Serialize,
+    S: Serializer,
+{
+    set.as_ref().map(SortedSet).serialize(serializer)
+}
+
+/// Serializes a bidirectional map with the entries sorted by the left value.
+pub(crate) fn bimap<L, R, S>(map: &BiMap<L, R>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    L: Ord + Hash + Eq + Serialize,
+    R: Hash + Eq + Serialize,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize)]
+    struct Collections {
+        #[serde(serialize_with = "map")]
+        map: HashMap<u32, u32>,
+        #[serde(serialize_with = "option_set")]
+        set: Option<HashSet<u32>>,
+    }
+
+    #[test]
+    fn output_does_not_depend_on_hasher_seed() {
+        let serialize = || {
+            bincode::serialize(&Collections {
+                map: (0..100).map(|i| (i, i * 2)).collect(),
+                set: Some((0..100).collect()),
+            })
+            .unwrap()
+        };
+
+        let bytes = serialize();
+        for _ in 0..10 {
+            assert_eq!(serialize(), bytes);
+        }
+
+        let collections: Collections = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(collections.map.len(), 100);
+        assert_eq!(collections.set.unwrap().len(), 100);
+    }
+}
@@ -1,25 +1,75 @@
 //!
 //! Defines helpers to allow optional Rayon usage.
-//! Copied from huggingface/tokenizers v0.1.1. Only change is "TOKENIZERS_PARALLELISM" to "NLPRULE_PARALLELISM"
+//! Copied from huggingface/tokenizers v0.1.1 with "TOKENIZERS_PARALLELISM" changed to "NLPRULE_PARALLELISM".
+//! Extended by [Parallelism] to configure parallelism per tokenizer / rule set instead of only through the environment.
 //!
 
 use rayon::iter::IterBridge;
 use rayon::prelude::*;
+use rayon::ThreadPool;
 use rayon_cond::CondIterator;
+use std::{cell::RefCell, sync::Arc};
 
 pub const ENV_VARIABLE: &str = "NLPRULE_PARALLELISM";
 
-// Reading/Writing this variable should always happen on the main thread
-static mut USED_PARALLELISM: bool = false;
+/// How the work done by a [Tokenizer][crate::Tokenizer] or [Rules][crate::Rules] is parallelized.
+#[derive(Debug, Clone, Default)]
+pub enum Parallelism {
+    /// Run in parallel on the global rayon thread pool unless the `NLPRULE_PARALLELISM` environment variable disables it.
+    #[default]
+    Auto,
+    /// Always run serially on the calling thread.
+    Serial,
+    /// Run in parallel on the given thread pool, regardless of the environment.
+    Pool(Arc<ThreadPool>),
+}
+
+thread_local! {
+    static OVERRIDE: RefCell<Option<Parallelism>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous override when dropped, also if the operation panics.
+struct OverrideGuard(Option<Parallelism>);
 
-/// Check if the NLPRULE_PARALLELISM env variable has been explicitly set
-pub fn _is_parallelism_configured() -> bool {
-    std::env::var(ENV_VARIABLE).is_ok()
+impl Drop for OverrideGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        OVERRIDE.with(|x| *x.borrow_mut() = previous);
+    }
+}
+
+/// Runs `op` with the given parallelism instead of the one set in the options of each tokenizer and rule set.
+/// Applies to all calls into nlprule made by `op` on the current thread.
+pub fn with_parallelism<R>(parallelism: Parallelism, op: impl FnOnce() -> R) -> R {
+    let _guard = OverrideGuard(OVERRIDE.with(|x| x.replace(Some(parallelism))));
+    op()
 }
 
-/// Check if at some point we used a parallel iterator
-pub fn _has_parallelism_been_used() -> bool {
-    unsafe { USED_PARALLELISM }
+impl Parallelism {
+    /// Gets the parallelism to use for a call, which is the override set by [with_parallelism] if there is one
+    /// and `self` otherwise.
+    pub(crate) fn resolve(&self) -> Parallelism {
+        OVERRIDE
+            .with(|x| x.borrow().clone())
+            .unwrap_or_else(|| self.clone())
+    }
+
+    /// Whether work should be done in parallel.
+    pub(crate) fn is_parallel(&self) -> bool {
+        match self {
+            Parallelism::Auto => get_parallelism(),
+            Parallelism::Serial => false,
+            Parallelism::Pool(_) => true,
+        }
+    }
+
+    /// Runs `op` such that parallel iterators in it use the configured thread pool.
+    pub(crate) fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match self {
+            Parallelism::Pool(pool) => pool.install(op),
+            Parallelism::Auto | Parallelism::Serial => op(),
+        }
+    }
 }
 
 /// Get the currently set value for `NLPRULE_PARALLELISM` env variable
@@ -33,9 +83,11 @@ pub fn get_parallelism() -> bool {
     }
 }
 
-/// Set the value for `NLPRULE_PARALLELISM` for the current process
-pub fn _set_parallelism(val: bool) {
-    std::env::set_var(ENV_VARIABLE, if val { "true" } else { "false" })
+/// Whether to parallelize over the `n` items of a batch instead of within each item.
+/// Parallelizing on both levels at once only adds overhead, so the outer level is preferred once
+/// there are enough items to keep all threads busy.
+pub fn prefer_outer_parallelism(n: usize) -> bool {
+    n >= rayon::current_num_threads()
 }
 
 /// Allows to convert into an iterator that can be executed either parallelly or serially.
@@ -54,8 +106,7 @@ where
     /// based solely on the `NLPRULE_PARALLELISM` environment variable
     fn into_maybe_par_iter(self) -> CondIterator<P, S>;
     /// Convert ourself in a CondIterator, that will be executed either in parallel or serially,
-    /// based on both the `NLPRULE_PARALLELISM` environment variable and the provided bool.
-    /// Both must be true to run with parallelism activated.
+    /// based solely on the provided bool. Use [Parallelism::is_parallel] to respect the configuration.
     fn into_maybe_par_iter_cond(self, cond: bool) -> CondIterator<P, S>;
 }
 
@@ -66,19 +117,11 @@ where
     S: Iterator<Item = P::Item>,
 {
     fn into_maybe_par_iter(self) -> CondIterator<P, S> {
-        let parallelism = get_parallelism();
-        if parallelism {
-            unsafe { USED_PARALLELISM = true };
-        }
-        CondIterator::new(self, parallelism)
+        CondIterator::new(self, get_parallelism())
     }
 
     fn into_maybe_par_iter_cond(self, cond: bool) -> CondIterator<P, S> {
-        if cond {
-            self.into_maybe_par_iter()
-        } else {
-            CondIterator::from_serial(self)
-        }
+        CondIterator::new(self, cond)
     }
 }
 
@@ -157,7 +200,6 @@ where
         let iter = CondIterator::from_serial(self);
 
         if get_parallelism() {
-            unsafe { USED_PARALLELISM = true };
             CondIterator::from_parallel(iter.into_parallel().right().unwrap())
         } else {
             iter
@@ -165,10 +207,12 @@ where
     }
 
     fn maybe_par_bridge_cond(self, cond: bool) -> CondIterator<IterBridge<S>, S> {
+        let iter = CondIterator::from_serial(self);
+
         if cond {
-            self.maybe_par_bridge()
+            CondIterator::from_parallel(iter.into_parallel().right().unwrap())
         } else {
-            CondIterator::from_serial(self)
+            iter
         }
     }
 }
@@ -194,4 +238,28 @@ mod tests {
         assert_eq!(v.maybe_par_iter().sum::<u32>(), 42);
         assert_eq!(v.into_maybe_par_iter().sum::<u32>(), 42);
     }
+
+    #[test]
+    fn override_is_scoped() {
+        assert!(matches!(Parallelism::Auto.resolve(), Parallelism::Auto));
+
+        with_parallelism(Parallelism::Serial, || {
+            assert!(matches!(Parallelism::Auto.resolve(), Parallelism::Serial));
+            assert!(!Parallelism::Auto.resolve().is_parallel());
+        });
+
+        assert!(matches!(Parallelism::Auto.resolve(), Parallelism::Auto));
+    }
+
+    #[test]
+    fn pool_is_used() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let parallelism = Parallelism::Pool(Arc::new(pool));
+
+        assert!(parallelism.is_parallel());
+        assert_eq!(parallelism.install(rayon::current_num_threads), 2);
+    }
 }
@@ -3,27 +3,78 @@
 //! Adapts the approach from https://github.com/trishume/syntect/pull/270 with feature flags for the
 //! different backends.
 
+use crate::{
+    types::{DefaultHashMap, DefaultHashSet},
+    utils::parallelism::MaybeParallelIterator,
+    RegexError,
+};
+use lazy_static::lazy_static;
 use lazycell::AtomicLazyCell;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex, Weak},
+};
 
 pub use regex_impl::{CaptureMatches, Captures, Match, Matches};
 
-#[derive(Debug)]
-pub struct Regex {
-    regex_str: String,
-    regex: AtomicLazyCell<regex_impl::Regex>,
+type RegexCell = AtomicLazyCell<regex_impl::Regex>;
+
+/// Keeps track of all live regex cells so that regexes with the same pattern share one compiled instance.
+/// Only weak references are stored, so a regex is dropped as soon as the last structure using it is dropped.
+#[derive(Default)]
+struct Interner {
+    cells: DefaultHashMap<String, Weak<RegexCell>>,
+    prune_at: usize,
 }
 
-impl Clone for Regex {
-    fn clone(&self) -> Self {
-        Regex {
-            regex_str: self.regex_str.clone(),
-            regex: AtomicLazyCell::new(),
+impl Interner {
+    fn get(&mut self, regex_str: &str) -> Arc<RegexCell> {
+        if let Some(cell) = self.cells.get(regex_str).and_then(Weak::upgrade) {
+            return cell;
+        }
+
+        // remove dead entries once in a while so the map does not grow indefinitely
+        if self.cells.len() >= self.prune_at {
+            self.cells.retain(|_, cell| cell.strong_count() > 0);
+            self.prune_at = (self.cells.len() * 2).max(1024);
         }
+
+        let cell = Arc::new(RegexCell::new());
+        self.cells
+            .insert(regex_str.to_owned(), Arc::downgrade(&cell));
+        cell
     }
 }
 
+lazy_static! {
+    static ref INTERNER: Mutex<Interner> = Mutex::new(Interner::default());
+}
+
+/// Compiles all given regexes. Regexes which share a compiled instance are only compiled once.
+/// Compilation happens in parallel if parallelism is enabled.
+///
+/// Returns an error for each regex which failed to compile.
+pub(crate) fn compile_all(regexes: Vec<&Regex>) -> Vec<RegexError> {
+    let mut seen = DefaultHashSet::default();
+
+    let unique: Vec<_> = regexes
+        .into_iter()
+        .filter(|regex| seen.insert(Arc::as_ptr(&regex.regex)))
+        .collect();
+
+    unique
+        .into_maybe_par_iter()
+        .filter_map(|regex| regex.compile().err())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    regex_str: String,
+    regex: Arc<RegexCell>,
+}
+
 impl Serialize for Regex {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -52,13 +103,34 @@ impl Hash for Regex {
 impl Regex {
     /// Create a new regex from the pattern string.
     ///
-    /// Note that the regex compilation happens on first use, which is why this method does not
-    /// return a result.
+    /// Note that the regex compilation happens on first use (or when calling [compile][Regex::compile]),
+    /// which is why this method does not return a result. Regexes with the same pattern share one compiled instance.
     pub fn new(regex_str: String) -> Self {
-        Self {
-            regex_str,
-            regex: AtomicLazyCell::new(),
+        let regex = INTERNER
+            .lock()
+            .expect("regex interner is never poisoned")
+            .get(&regex_str);
+
+        Self { regex_str, regex }
+    }
+
+    /// Gets the pattern string of this regex.
+    pub fn as_str(&self) -> &str {
+        &self.regex_str
+    }
+
+    /// Compiles the regex if it has not been compiled yet.
+    pub fn compile(&self) -> Result<(), RegexError> {
+        if self.regex.borrow().is_none() {
+            let regex = regex_impl::Regex::new(&self.regex_str).map_err(|error| RegexError {
+                regex: self.regex_str.clone(),
+                message: error.to_string(),
+            })?;
+            // another thread might have filled the cell in the meantime, which is fine
+            self.regex.fill(regex).ok();
         }
+
+        Ok(())
     }
 
     /// Check whether the pattern compiles as a valid regex.
@@ -71,10 +143,8 @@ impl Regex {
         if let Some(regex) = self.regex.borrow() {
             regex
         } else {
-            let regex = regex_impl::Regex::new(&self.regex_str).unwrap_or_else(|_| {
-                panic!("regex string should be pre-tested: {}", self.regex_str)
-            });
-            self.regex.fill(regex).ok();
+            self.compile()
+                .unwrap_or_else(|error| panic!("regex string should be pre-tested: {}", error));
             self.regex.borrow().unwrap()
         }
     }
@@ -495,3 +565,31 @@ cfg_if::cfg_if! {
         use regex_impl_fancy as regex_impl;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_patterns_share_compiled_regex() {
+        let a = Regex::new(r"\d+ apples".into());
+        let b = Regex::new(r"\d+ apples".into());
+        let c = Regex::new(r"\d+ pears".into());
+
+        assert!(Arc::ptr_eq(&a.regex, &b.regex));
+        assert!(!Arc::ptr_eq(&a.regex, &c.regex));
+
+        assert!(compile_all(vec![&a, &b, &c]).is_empty());
+        assert!(b.regex.borrow().is_some());
+    }
+
+    #[test]
+    fn compile_errors_are_reported() {
+        let valid = Regex::new("valid".into());
+        let invalid = Regex::new("(invalid".into());
+
+        let errors = compile_all(vec![&valid, &invalid, &invalid.clone()]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].regex, "(invalid");
+    }
+}
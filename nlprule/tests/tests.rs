@@ -1,7 +1,23 @@
-use std::convert::TryInto;
+use std::{convert::TryInto, sync::Arc};
 
 use lazy_static::lazy_static;
-use nlprule::{rule::id::Category, Rules, Tokenizer};
+use nlprule::{
+    binary::BinaryKind,
+    diff::{self, ChangeKind, DiffOptions},
+    dsl::{self, RuleSpec},
+    evaluate::{self, EvaluationOptions},
+    inspect,
+    pattern::{Pattern, TokenSpec},
+    rule::{
+        explain::{Outcome, SynthesisPart},
+        id::{Category, Selector},
+        report::ExampleOutput,
+    },
+    rules::RulesOptions,
+    tokenizer::{tag::TaggerRepresentation, TokenizerOptions},
+    types::{Limits, Profile, Reading, Word},
+    with_parallelism, Error, Language, Parallelism, Rules, Tokenizer,
+};
 use quickcheck_macros::quickcheck;
 
 const TOKENIZER_PATH: &str = "../storage/en_tokenizer.bin";
@@ -55,3 +71,721 @@ fn rules_can_be_disabled_enabled() {
     // enabled now
     assert!(!rules.suggest("I can not go", &*TOKENIZER).is_empty());
 }
+
+#[test]
+fn prefilter_does_not_change_suggestions() {
+    let mut unfiltered = Rules::new(RULES_PATH).unwrap();
+    unfiltered.options_mut().prefilter = false;
+
+    for rule in RULES.rules() {
+        for example in rule.examples() {
+            let text = example.text();
+
+            assert_eq!(
+                format!("{:?}", RULES.suggest(text, &*TOKENIZER)),
+                format!("{:?}", unfiltered.suggest(text, &*TOKENIZER)),
+                "suggestions differ for {:?}",
+                text
+            );
+        }
+    }
+}
+
+#[test]
+fn limits_truncate_long_sentences() {
+    let mut rules = Rules::new(RULES_PATH).unwrap();
+    rules.options_mut().limits.max_sentence_length = Some(20);
+
+    let text = "I can due his homework. This is a much longer sentence which is not checked, I can due his homework.";
+    let (suggestions, truncations) = rules.suggest_with_report(text, &*TOKENIZER);
+
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(truncations.len(), 1);
+    assert!(truncations[0].skipped);
+    assert_eq!(truncations[0].end, text.chars().count());
+
+    rules.options_mut().limits = Limits {
+        max_steps: Some(0),
+        ..Limits::default()
+    };
+
+    let (suggestions, truncations) = rules.suggest_with_report(text, &*TOKENIZER);
+    assert!(suggestions.is_empty());
+    assert!(!truncations.is_empty() && truncations.iter().all(|x| !x.rules.is_empty()));
+}
+
+#[test]
+fn incremental_disambiguation_does_not_change_tokens() {
+    let mut tokenizer = Tokenizer::new(TOKENIZER_PATH).unwrap();
+    tokenizer.options_mut().incremental = false;
+
+    let texts = RULES
+        .rules()
+        .iter()
+        .flat_map(|rule| rule.examples().iter().map(|example| example.text()));
+
+    for text in texts {
+        assert_eq!(
+            format!("{:?}", TOKENIZER.pipe(text)),
+            format!("{:?}", tokenizer.pipe(text)),
+            "tokens differ for {:?}",
+            text
+        );
+    }
+}
+
+#[test]
+fn batch_apis_match_single_texts() {
+    let texts: Vec<_> = RULES
+        .rules()
+        .iter()
+        .flat_map(|rule| rule.examples().iter().map(|example| example.text()))
+        .take(200)
+        .chain(vec!["", "I can due his homework. I can due his homework."])
+        .collect();
+
+    let suggestions = RULES.suggest_batch(&texts, &*TOKENIZER);
+    let tokens = TOKENIZER.pipe_batch(&texts);
+
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(
+            format!("{:?}", suggestions[i]),
+            format!("{:?}", RULES.suggest(text, &*TOKENIZER))
+        );
+        assert_eq!(
+            format!("{:?}", tokens[i]),
+            format!("{:?}", TOKENIZER.pipe(text))
+        );
+    }
+}
+
+#[test]
+fn parallelism_can_be_configured() {
+    let text = "I can due his homework. I can due his homework.";
+    let expected = format!("{:?}", RULES.suggest(text, &*TOKENIZER));
+
+    let mut rules = Rules::new(RULES_PATH).unwrap();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap();
+    rules.options_mut().parallelism = Parallelism::Pool(Arc::new(pool));
+
+    assert_eq!(format!("{:?}", rules.suggest(text, &*TOKENIZER)), expected);
+
+    let serial = with_parallelism(Parallelism::Serial, || rules.suggest(text, &*TOKENIZER));
+    assert_eq!(format!("{:?}", serial), expected);
+}
+
+#[test]
+fn mapped_binaries_match_deserialized_ones() {
+    let tokenizer = unsafe { Tokenizer::new_mapped(TOKENIZER_PATH) }.unwrap();
+    let rules = unsafe { Rules::new_mapped(RULES_PATH) }.unwrap();
+
+    let texts = RULES
+        .rules()
+        .iter()
+        .flat_map(|rule| rule.examples().iter().map(|example| example.text()));
+
+    for text in texts {
+        assert_eq!(
+            format!("{:?}", TOKENIZER.pipe(text)),
+            format!("{:?}", tokenizer.pipe(text)),
+            "tokens differ for {:?}",
+            text
+        );
+        assert_eq!(
+            format!("{:?}", RULES.suggest(text, &*TOKENIZER)),
+            format!("{:?}", rules.suggest(text, &tokenizer)),
+            "suggestions differ for {:?}",
+            text
+        );
+    }
+
+    for lemma in &["be", "house", "go"] {
+        assert_eq!(
+            TOKENIZER.tagger().get_group_members(lemma),
+            tokenizer.tagger().get_group_members(lemma)
+        );
+    }
+}
+
+#[test]
+fn corrupt_mapped_binaries_are_rejected() {
+    let mut bytes = std::fs::read(RULES_PATH).unwrap();
+    let index = bytes.len() * 3 / 4;
+    bytes[index] = !bytes[index];
+
+    let path = std::env::temp_dir().join("nlprule_corrupt_rules.bin");
+    std::fs::write(&path, &bytes).unwrap();
+
+    let options = RulesOptions {
+        lazy: true,
+        ..RulesOptions::default()
+    };
+    let result = unsafe { Rules::new_mapped_with_options(&path, options) };
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(Error::ChecksumMismatch { .. })));
+}
+
+#[test]
+fn tagger_representations_match() {
+    let options = TokenizerOptions {
+        tagger: TaggerRepresentation::Fst,
+        ..TokenizerOptions::default()
+    };
+    let tokenizer = Tokenizer::new_with_options(TOKENIZER_PATH, options).unwrap();
+    assert_eq!(
+        tokenizer.tagger().representation(),
+        TaggerRepresentation::Fst
+    );
+
+    let heap = tokenizer
+        .tagger()
+        .to_representation(TaggerRepresentation::Heap);
+
+    for word in &["be", "was", "Houses", "going", "nonexistentword"] {
+        assert_eq!(
+            format!("{:?}", TOKENIZER.tagger().get_tags(word)),
+            format!("{:?}", tokenizer.tagger().get_tags(word))
+        );
+        assert_eq!(
+            format!("{:?}", heap.get_tags(word)),
+            format!("{:?}", tokenizer.tagger().get_tags(word))
+        );
+        assert_eq!(
+            TOKENIZER.tagger().get_group_members(word),
+            tokenizer.tagger().get_group_members(word)
+        );
+    }
+}
+
+#[test]
+fn written_binaries_keep_the_tagger_representation() {
+    for representation in &[TaggerRepresentation::Heap, TaggerRepresentation::Fst] {
+        let options = TokenizerOptions {
+            tagger: *representation,
+            ..TokenizerOptions::default()
+        };
+        let tokenizer = Tokenizer::new_with_options(TOKENIZER_PATH, options).unwrap();
+
+        let mut bytes = Vec::new();
+        tokenizer.write(&mut bytes).unwrap();
+        let loaded = Tokenizer::from_reader(&bytes[..]).unwrap();
+
+        assert_eq!(loaded.tagger().representation(), *representation);
+        assert_eq!(loaded.header().unwrap().metadata.lang_code, "en");
+    }
+
+    let mut bytes = Vec::new();
+    RULES.write(&mut bytes).unwrap();
+    let loaded = Rules::from_reader(&bytes[..]).unwrap();
+
+    assert_eq!(loaded.rules().len(), RULES.rules().len());
+}
+
+#[test]
+fn rules_can_be_loaded_partially_and_lazily() {
+    let selector: Selector = Category::new("confused_words").into();
+    let options = RulesOptions {
+        lazy: true,
+        selectors: Some(vec![selector.clone()]),
+        ..RulesOptions::default()
+    };
+    let rules = Rules::new_with_options(RULES_PATH, options).unwrap();
+
+    assert!(!rules.rules().is_empty());
+    assert!(rules
+        .rules()
+        .iter()
+        .all(|rule| selector.is_match(rule.id())));
+    assert_eq!(rules.rules().len(), RULES.select(&selector).count());
+
+    let text = "I can due his homework. I can not go.";
+    let expected: Vec<_> = RULES
+        .suggest(text, &*TOKENIZER)
+        .into_iter()
+        .filter(|suggestion| {
+            rules
+                .rules()
+                .iter()
+                .any(|rule| rule.id().to_string() == suggestion.source)
+        })
+        .collect();
+
+    assert!(!expected.is_empty());
+    assert_eq!(
+        format!("{:?}", rules.suggest(text, &*TOKENIZER)),
+        format!("{:?}", expected)
+    );
+}
+
+#[test]
+fn binaries_have_valid_headers() {
+    let header = TOKENIZER.header().unwrap();
+    assert_eq!(header.kind, BinaryKind::Tokenizer);
+    assert_eq!(header.metadata.lang_code, "en");
+
+    assert_eq!(
+        RULES.header().unwrap().metadata,
+        TOKENIZER.header().unwrap().metadata
+    );
+
+    assert!(matches!(
+        Rules::new(TOKENIZER_PATH),
+        Err(Error::WrongBinaryKind { .. })
+    ));
+}
+
+#[test]
+fn rules_reject_incompatible_tokenizers() {
+    assert!(TOKENIZER.tagger().fingerprint().is_some());
+    assert_eq!(RULES.tagger_fingerprint(), TOKENIZER.tagger().fingerprint());
+    assert!(RULES.check_compatible(&*TOKENIZER).is_ok());
+
+    // compatibility checks against an unknown fingerprint always pass
+    assert!(RULES.check_compatible(&Tokenizer::default()).is_ok());
+}
+
+#[test]
+fn bundles_can_be_written_and_loaded() {
+    let tokenizer = Tokenizer::new(TOKENIZER_PATH).unwrap();
+    let rules = Rules::new(RULES_PATH).unwrap();
+    let metadata = tokenizer.header().unwrap().metadata.clone();
+
+    let mut language = Language::new(tokenizer, rules, metadata).unwrap();
+    language.extra_mut().insert("source".into(), "tests".into());
+
+    let mut bytes = Vec::new();
+    language.write(&mut bytes).unwrap();
+    let loaded = Language::from_reader(&bytes[..]).unwrap();
+
+    assert_eq!(loaded.metadata().lang_code, "en");
+    assert_eq!(loaded.header().unwrap().kind, BinaryKind::Bundle);
+    assert_eq!(
+        loaded.extra().get("source").map(String::as_str),
+        Some("tests")
+    );
+
+    let text = "She was not been here since Monday.";
+    assert_eq!(
+        format!("{:?}", loaded.suggest(text)),
+        format!("{:?}", RULES.suggest(text, &*TOKENIZER))
+    );
+
+    // bundles are not accepted in place of the individual binaries
+    assert!(matches!(
+        Rules::from_reader(&bytes[..]),
+        Err(Error::WrongBinaryKind { .. })
+    ));
+}
+
+#[test]
+fn reserialized_binaries_are_identical() {
+    // every deserialization creates hashed collections with new random seeds
+    let serialize = || {
+        let tokenizer = Tokenizer::new(TOKENIZER_PATH).unwrap();
+        let rules = Rules::new(RULES_PATH).unwrap();
+
+        (
+            bincode::serialize(&tokenizer).unwrap(),
+            bincode::serialize(&rules).unwrap(),
+        )
+    };
+
+    assert!(serialize() == serialize());
+}
+
+#[test]
+fn dumps_resolve_ids_to_strings() {
+    let tokenizer_dump = inspect::dump_tokenizer(&*TOKENIZER);
+    assert!(tokenizer_dump.tagger.n_words > 0);
+    assert!(tokenizer_dump.tagger.n_entries >= tokenizer_dump.tagger.n_words);
+    assert!(tokenizer_dump.tagger.pos_tags.iter().any(|x| x == "NN"));
+    assert_eq!(tokenizer_dump.rules.len(), TOKENIZER.rules().len());
+
+    let fst_tokenizer = Tokenizer::new_with_options(
+        TOKENIZER_PATH,
+        TokenizerOptions {
+            tagger: TaggerRepresentation::Fst,
+            ..TokenizerOptions::default()
+        },
+    )
+    .unwrap();
+    let fst_tagger_dump = inspect::dump_tagger(fst_tokenizer.tagger());
+    assert_eq!(fst_tagger_dump.n_words, tokenizer_dump.tagger.n_words);
+    assert_eq!(fst_tagger_dump.n_entries, tokenizer_dump.tagger.n_entries);
+
+    let rules_dump = inspect::dump_rules(&*RULES, &*TOKENIZER).unwrap();
+    assert_eq!(rules_dump.rules.len(), RULES.rules().len());
+
+    let json = serde_json::to_string(&rules_dump).unwrap();
+    assert!(json.contains(r#""pos":["#));
+    assert_eq!(
+        json,
+        serde_json::to_string(&inspect::dump_rules(&*RULES, &fst_tokenizer).unwrap()).unwrap()
+    );
+    serde_json::to_string(&tokenizer_dump).unwrap();
+}
+
+#[test]
+fn patterns_find_matches_and_captures() {
+    let pattern = Pattern::builder()
+        .token(TokenSpec::new().lemma("be"))
+        .optional(TokenSpec::new().pos_regex("RB.*"))
+        .token(TokenSpec::new().pos_regex("JJ.*"))
+        .named("adjective")
+        .build(&*TOKENIZER)
+        .unwrap();
+
+    let text = "The dog was happy. The cat is very happy.";
+    let matches = pattern.find(text, &*TOKENIZER);
+
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].span().text(text), "was happy");
+    assert_eq!(matches[1].span().text(text), "is very happy");
+    assert_eq!(matches[1].span().char_span, (27, 40));
+    assert_eq!(matches[1].part(1).unwrap().text(text), "very");
+    assert_eq!(matches[1].capture("adjective").unwrap().text(text), "happy");
+    assert_eq!(matches[0].part(1), None);
+
+    let sentences = TOKENIZER.pipe("It is not.");
+    assert_eq!(pattern.apply(&sentences[0]).count(), 0);
+}
+
+#[test]
+fn rules_can_be_defined_with_specs() {
+    let spec: RuleSpec = serde_json::from_str(
+        r#"{
+            "id": "VERY_HAPPY",
+            "pattern": [
+                { "lemma": "be" },
+                { "text": "very", "marker": true },
+                { "pos": "/JJ.*/", "marker": true, "name": "adjective" }
+            ],
+            "message": "Consider a stronger word than 'very \\{adjective}'.",
+            "suggestions": ["delighted", "\\{adjective:upper}"],
+            "examples": [
+                { "text": "The cat is <marker>very happy</marker>.", "correction": ["delighted"] },
+                { "text": "The cat is happy." }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let compact: RuleSpec = serde_json::from_str(
+        r#"{
+            "id": "VERY_HAPPY",
+            "pattern": "[lemma=be] <very [pos=/JJ.*/ name=adjective]>",
+            "message": "Consider a stronger word than 'very \\{adjective}'.",
+            "suggestions": ["delighted", "\\{adjective:upper}"],
+            "examples": [
+                { "text": "The cat is <marker>very happy</marker>.", "correction": ["delighted"] },
+                { "text": "The cat is happy." }
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(compact, spec);
+
+    let rule = spec.compile(&*TOKENIZER).unwrap();
+    assert_eq!(rule.id().to_string(), "CUSTOM/VERY_HAPPY/0");
+    assert!(rule.test(&*TOKENIZER));
+
+    let rules = dsl::compile_rules(&[spec.clone()], &*TOKENIZER).unwrap();
+    let suggestions = rules.suggest("She is very happy.", &*TOKENIZER);
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!((suggestions[0].start, suggestions[0].end), (7, 17));
+    assert_eq!(suggestions[0].replacements, vec!["delighted", "HAPPY"]);
+    assert_eq!(
+        suggestions[0].message,
+        "Consider a stronger word than 'very happy'."
+    );
+
+    let mut invalid = spec;
+    invalid.suggestions = vec!["\\3".into()];
+    assert!(matches!(
+        invalid.compile(&*TOKENIZER),
+        Err(Error::InvalidRule { .. })
+    ));
+}
+
+#[test]
+fn explanations_show_why_rules_match() {
+    let spec: RuleSpec = serde_json::from_str(
+        r#"{
+            "id": "VERY_ADJECTIVE",
+            "pattern": [
+                { "text": "very" },
+                { "pos": "/JJ.*/", "name": "adjective" }
+            ],
+            "antipatterns": [[{ "text": "very" }, { "text": "good" }]],
+            "message": "Consider a stronger word.",
+            "suggestions": ["\\{adjective:upper}"]
+        }"#,
+    )
+    .unwrap();
+    let rule = spec.compile(&*TOKENIZER).unwrap();
+
+    let text = "She is very happy. It is very good.";
+    let explanations = rule.explain(text, &*TOKENIZER);
+    assert_eq!(explanations.len(), 2);
+    assert_eq!(explanations[0].pattern.len(), 2);
+
+    let happy = explanations[0]
+        .tokens
+        .iter()
+        .find(|x| x.text == "happy")
+        .unwrap();
+    assert_eq!(happy.parts, vec![Some(false), Some(true)]);
+
+    let candidate = &explanations[0].candidates[0];
+    assert_eq!(candidate.outcome, Outcome::Suggested);
+    assert_eq!(
+        candidate.suggestion.as_ref().unwrap().replacements,
+        vec!["HAPPY"]
+    );
+    assert!(matches!(
+        &candidate.suggesters[0].parts[0],
+        SynthesisPart::Match { text, output: Some(output), .. } if text == "happy" && output == "HAPPY"
+    ));
+
+    // spans are relative to the text, not the sentence
+    let antipattern = &explanations[1].antipatterns[0];
+    assert_eq!(antipattern.text, "very good");
+    assert_eq!(antipattern.char_span.0, text.find("very good").unwrap());
+    assert_eq!(
+        explanations[1].candidates[0].outcome,
+        Outcome::Antipattern(0)
+    );
+
+    let rules = dsl::compile_rules(&[spec], &*TOKENIZER).unwrap();
+    let explanations = rules.explain(text, &*TOKENIZER);
+    assert_eq!(explanations.len(), 2);
+    assert_eq!(
+        explanations[0].candidates[0]
+            .suggestion
+            .as_ref()
+            .unwrap()
+            .start,
+        rules.suggest(text, &*TOKENIZER)[0].start
+    );
+}
+
+#[test]
+fn grammar_examples_can_be_reported() {
+    let mut n_reports = 0;
+
+    for rule in RULES.rules().iter().take(100) {
+        let reports = rule.test_with_report(&*TOKENIZER);
+        assert_eq!(reports.len(), rule.examples().len());
+        assert_eq!(reports.iter().all(|x| x.passed), rule.test(&*TOKENIZER));
+
+        for (i, report) in reports.iter().enumerate() {
+            assert_eq!(report.index, i);
+            assert_eq!(report.rule, rule.id().to_string());
+            assert_eq!(report.text, rule.examples()[i].text());
+            assert!(report.known_failure.is_none());
+            assert!(matches!(report.expected, ExampleOutput::Suggestions(_)));
+            assert!(matches!(report.actual, ExampleOutput::Suggestions(_)));
+        }
+
+        n_reports += reports.len();
+    }
+
+    assert!(n_reports > 0);
+}
+
+#[test]
+fn disambiguation_examples_can_be_reported() {
+    let mut n_reports = 0;
+
+    for rule in TOKENIZER.rules().iter().take(100) {
+        let reports = rule.test_with_report(&*TOKENIZER);
+        assert_eq!(reports.iter().all(|x| x.passed), rule.test(&*TOKENIZER));
+
+        for (i, report) in reports.iter().enumerate() {
+            assert_eq!(report.index, i);
+            assert_eq!(report.rule, rule.id().to_string());
+            assert!(report.known_failure.is_some());
+            assert!(matches!(report.expected, ExampleOutput::Readings(_)));
+            assert!(matches!(report.actual, ExampleOutput::Readings(_)));
+        }
+
+        n_reports += reports.len();
+    }
+
+    assert!(n_reports > 0);
+}
+
+#[test]
+fn disambiguation_can_be_traced() {
+    let readings = |word: &Word| word.tags.iter().map(Reading::from).collect::<Vec<_>>();
+    let dump = inspect::dump_tokenizer(&*TOKENIZER);
+    let mut n_changes = 0;
+
+    for rule in dump.rules.iter().take(100) {
+        for example in &rule.examples {
+            let text = match example {
+                inspect::DisambiguationExampleDump::Changed { text, .. } => text,
+                _ => continue,
+            };
+
+            for tokens in TOKENIZER.sentencize(text) {
+                let (disambiguated, trace) = TOKENIZER.disambiguate_with_trace(tokens.clone());
+                assert_eq!(disambiguated, TOKENIZER.disambiguate(tokens.clone()));
+                assert_eq!(trace.tokens.len(), tokens.len());
+
+                for ((initial, token), changes) in
+                    tokens.iter().zip(&disambiguated).zip(&trace.tokens)
+                {
+                    match (changes.first(), changes.last()) {
+                        (Some(first), Some(last)) => {
+                            assert_eq!(first.before, readings(&initial.word));
+                            assert_eq!(last.after, readings(&token.0.word));
+                            n_changes += changes.len();
+                        }
+                        _ => assert_eq!(readings(&initial.word), readings(&token.0.word)),
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(n_changes > 0);
+
+    for (tokens, trace) in TOKENIZER.pipe_with_trace("I can due his homework. It is good.") {
+        assert_eq!(tokens.len(), trace.tokens.len());
+        assert!(trace.tokens[0].is_empty());
+        assert!(trace.truncation.is_none());
+    }
+
+    let mut tokenizer = Tokenizer::new(TOKENIZER_PATH).unwrap();
+    tokenizer.options_mut().limits.max_sentence_length = Some(20);
+
+    let text = "It is good. This is a much longer sentence which is not disambiguated.";
+    let truncations: Vec<_> = tokenizer
+        .pipe_with_trace(text)
+        .into_iter()
+        .filter_map(|(_, trace)| trace.truncation)
+        .collect();
+    assert_eq!(truncations, tokenizer.pipe_with_report(text).1);
+    assert_eq!(truncations.len(), 1);
+}
+
+#[test]
+fn rule_sets_can_be_diffed() {
+    let mut rules = Rules::new(RULES_PATH).unwrap();
+    rules
+        .select_mut(
+            &Category::new("confused_words")
+                .join("confusion_due_do")
+                .into(),
+        )
+        .for_each(|x| x.disable());
+
+    let documents = [
+        "I can due his homework. It is good.",
+        "She was not been here since Monday.",
+    ];
+    let report = diff::diff(
+        (&*TOKENIZER, &*RULES),
+        (&*TOKENIZER, &rules),
+        &documents,
+        &DiffOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(report.documents, 2);
+    assert!(report.tags.is_empty());
+    assert!(!report.rules.is_empty());
+    assert_eq!(
+        report.suggestions_before - report.suggestions_after,
+        report.rules.iter().map(|x| x.removed).sum::<usize>()
+    );
+
+    for rule in &report.rules {
+        assert!(rule
+            .rule
+            .to_lowercase()
+            .starts_with("confused_words/confusion_due_do"));
+        assert_eq!((rule.added, rule.changed), (0, 0));
+
+        for example in &rule.examples {
+            assert_eq!(example.kind, ChangeKind::Removed);
+            assert_eq!(example.sentence.trim(), "I can due his homework.");
+            assert!(example.before.is_some() && example.after.is_none());
+        }
+    }
+
+    let report = diff::diff(
+        (&*TOKENIZER, &*RULES),
+        (&*TOKENIZER, &*RULES),
+        &documents,
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert!(report.rules.is_empty() && report.tags.is_empty());
+}
+
+#[test]
+fn rules_can_be_evaluated_on_m2() {
+    let sentences = evaluate::parse_m2(
+        "S I can due his homework .
+A 2 3|||R:VERB|||do|||REQUIRED|||-NONE-|||0
+
+S It is good .
+A -1 -1|||noop|||-NONE-|||REQUIRED|||-NONE-|||0
+",
+    )
+    .unwrap();
+
+    let evaluation = evaluate::evaluate(
+        &sentences,
+        &*RULES,
+        &*TOKENIZER,
+        &EvaluationOptions {
+            replacements: 3,
+            ..EvaluationOptions::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(evaluation.sentences, 2);
+    assert_eq!(evaluation.overall.true_positives, 1);
+    assert_eq!(evaluation.overall.false_negatives, 0);
+    assert_eq!(evaluation.error_types["R:VERB"].true_positives, 1);
+    assert!(evaluation.rules.iter().any(|(id, scores)| id
+        .to_lowercase()
+        .starts_with("confused_words/confusion_due_do")
+        && scores.true_positives == 1));
+}
+
+#[test]
+fn rules_can_be_profiled() {
+    let text = "I can due his homework. She was not been here since Monday.";
+    let mut profile = Profile::default();
+
+    let suggestions = RULES.suggest_with_profile(text, &*TOKENIZER, &mut profile);
+    assert_eq!(
+        format!("{:?}", suggestions),
+        format!("{:?}", RULES.suggest(text, &*TOKENIZER))
+    );
+
+    assert!(!profile.rules.is_empty());
+    for rule in profile.rules.values() {
+        assert!(rule.evaluations >= 1 && rule.evaluations <= 2);
+        assert!(rule.matches >= rule.suggestions);
+    }
+    assert!(profile.rules.values().map(|x| x.suggestions).sum::<usize>() >= suggestions.len());
+    for suggestion in &suggestions {
+        assert!(profile.rules[&suggestion.source].suggestions >= 1);
+    }
+
+    let mut total = profile.clone();
+    total.merge(&profile);
+    for (id, rule) in &total.rules {
+        assert_eq!(rule.matches, profile.rules[id].matches * 2);
+    }
+}
@@ -5,21 +5,70 @@ use nlprule::{
     tokenizer::tag::Tagger,
     tokenizer::Tokenizer,
     types::*,
+    Language,
 };
 use parking_lot::{
     MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
 };
 use pyo3::prelude::*;
-use pyo3::types::PyString;
+use pyo3::types::{PyDict, PyString};
 use pyo3::{exceptions::PyValueError, types::PyBytes};
+use serde::Serialize;
 use std::{
     convert::TryFrom,
     fs,
     io::{Cursor, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
+/// Converts a serializable value to the equivalent Python object built from dicts, lists and primitives.
+fn to_py<T: Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    fn convert(py: Python, value: serde_json::Value) -> PyResult<PyObject> {
+        Ok(match value {
+            serde_json::Value::Null => py.None(),
+            serde_json::Value::Bool(x) => x.to_object(py),
+            serde_json::Value::Number(x) => {
+                if let Some(x) = x.as_u64() {
+                    x.to_object(py)
+                } else if let Some(x) = x.as_i64() {
+                    x.to_object(py)
+                } else {
+                    x.as_f64().to_object(py)
+                }
+            }
+            serde_json::Value::String(x) => x.to_object(py),
+            serde_json::Value::Array(x) => x
+                .into_iter()
+                .map(|x| convert(py, x))
+                .collect::<PyResult<Vec<_>>>()?
+                .to_object(py),
+            serde_json::Value::Object(x) => {
+                let dict = PyDict::new(py);
+                for (key, value) in x {
+                    dict.set_item(key, convert(py, value)?)?;
+                }
+                dict.to_object(py)
+            }
+        })
+    }
+
+    let value = serde_json::to_value(value)
+        .map_err(|x| PyValueError::new_err(format!("error converting value: {}", x)))?;
+    convert(py, value)
+}
+
+/// Loads a language bundle if `code_or_path` is a path to an existing file.
+fn load_bundle(code_or_path: &str) -> PyResult<Option<Language>> {
+    if !Path::new(code_or_path).is_file() {
+        return Ok(None);
+    }
+
+    Language::load(code_or_path)
+        .map(Some)
+        .map_err(|x| PyValueError::new_err(format!("error loading bundle: {}", x)))
+}
+
 fn get_resource(lang_code: &str, name: &str) -> PyResult<impl Read> {
     let version = env!("CARGO_PKG_VERSION");
     let mut cache_path: Option<PathBuf> = None;
@@ -305,6 +354,7 @@ impl From<Suggestion> for PySuggestion {
 /// ```
 /// When created from a language code, the binary is downloaded from the internet the first time.
 /// Then it is stored at your cache and loaded from there.
+/// `load` also accepts a path to a language bundle containing both the tokenizer and the rules.
 #[pyclass(name = "Tokenizer", module = "nlprule")]
 #[text_signature = "(path, sentence_splitter=None)"]
 #[derive(Default)]
@@ -323,10 +373,15 @@ impl PyTokenizer {
     #[text_signature = "(code, sentence_splitter=None)"]
     #[staticmethod]
     fn load(lang_code: &str) -> PyResult<Self> {
+        if let Some(language) = load_bundle(lang_code)? {
+            let (tokenizer, _) = language.into_parts();
+            return Ok(PyTokenizer { tokenizer });
+        }
+
         let bytes = get_resource(lang_code, "tokenizer.bin.gz")?;
 
-        let tokenizer: Tokenizer = bincode::deserialize_from(bytes)
-            .map_err(|x| PyValueError::new_err(format!("{}", x)))?;
+        let tokenizer =
+            Tokenizer::from_reader(bytes).map_err(|x| PyValueError::new_err(format!("{}", x)))?;
         Ok(PyTokenizer { tokenizer })
     }
 
@@ -383,9 +438,9 @@ impl PyTokenizer {
     pub fn __setstate__(&mut self, py: Python, state: PyObject) -> PyResult<()> {
         match state.extract::<&PyBytes>(py) {
             Ok(s) => {
-                self.tokenizer = bincode::deserialize(s.as_bytes()).map_err(|_| {
-                    PyValueError::new_err("deserializing state with `bincode` failed")
-                })?;
+                // reading a binary applies the stored options, e. g. the tagger representation
+                self.tokenizer = Tokenizer::from_reader(s.as_bytes())
+                    .map_err(|x| PyValueError::new_err(format!("error reading state: {}", x)))?;
                 Ok(())
             }
             Err(e) => Err(e),
@@ -393,12 +448,12 @@ impl PyTokenizer {
     }
 
     pub fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
-        Ok(PyBytes::new(
-            py,
-            &bincode::serialize(&self.tokenizer)
-                .map_err(|_| PyValueError::new_err("serializing state with `bincode` failed"))?,
-        )
-        .to_object(py))
+        let mut state = Vec::new();
+        self.tokenizer
+            .write(&mut state)
+            .map_err(|x| PyValueError::new_err(format!("error writing state: {}", x)))?;
+
+        Ok(PyBytes::new(py, &state).to_object(py))
     }
 }
 
@@ -534,6 +589,21 @@ impl PyRule {
     fn disable(&self) {
         self.rule_mut().disable();
     }
+
+    /// Explains how this rule is applied to each sentence of the text.
+    /// Shows which parts of the pattern match each token, which matches were blocked by antipatterns,
+    /// overlaps or unification and how the message and suggestions were synthesized.
+    ///
+    /// Arguments:
+    ///     text (str): The text to explain the rule for.
+    ///     tokenizer (Tokenizer): The tokenizer the rules were loaded with.
+    ///
+    /// Returns:
+    ///     explanations (List[dict]): One explanation per sentence.
+    #[text_signature = "(text, tokenizer)"]
+    fn explain(&self, py: Python, text: &str, tokenizer: PyRef<PyTokenizer>) -> PyResult<PyObject> {
+        to_py(py, &self.rule().explain(text, tokenizer.tokenizer()))
+    }
 }
 
 /// The grammatical rules.
@@ -548,6 +618,12 @@ impl PyRule {
 /// ```
 /// When created from a language code, the binary is downloaded from the internet the first time.
 /// Then it is stored at your cache and loaded from there.
+/// `load` also accepts a path to a language bundle containing both the tokenizer and the rules.
+/// If no tokenizer is passed, the tokenizer of the bundle (or of the language code) is used:
+/// ```python
+/// rules = Rules.load("/path/to/en.bin")
+/// tokenizer = rules.tokenizer
+/// ```
 #[pyclass(name = "Rules", module = "nlprule")]
 #[text_signature = "(path, tokenizer, sentence_splitter=None)"]
 struct PyRules {
@@ -557,12 +633,31 @@ struct PyRules {
 
 #[pymethods]
 impl PyRules {
-    #[text_signature = "(code, tokenizer, sentence_splitter=None)"]
+    #[text_signature = "(code, tokenizer=None, sentence_splitter=None)"]
     #[staticmethod]
-    fn load(lang_code: &str, tokenizer: Py<PyTokenizer>) -> PyResult<Self> {
-        let bytes = get_resource(lang_code, "rules.bin.gz")?;
+    fn load(py: Python, lang_code: &str, tokenizer: Option<Py<PyTokenizer>>) -> PyResult<Self> {
+        let (rules, tokenizer) = if let Some(language) = load_bundle(lang_code)? {
+            let (bundle_tokenizer, rules) = language.into_parts();
+            let tokenizer = match tokenizer {
+                Some(tokenizer) => tokenizer,
+                None => Py::new(py, PyTokenizer::from(bundle_tokenizer))?,
+            };
+
+            (rules, tokenizer)
+        } else {
+            let tokenizer = match tokenizer {
+                Some(tokenizer) => tokenizer,
+                None => Py::new(py, PyTokenizer::load(lang_code)?)?,
+            };
+
+            let bytes = get_resource(lang_code, "rules.bin.gz")?;
+            let rules =
+                Rules::from_reader(bytes).map_err(|x| PyValueError::new_err(format!("{}", x)))?;
 
-        let rules: Rules = bincode::deserialize_from(bytes)
+            (rules, tokenizer)
+        };
+        rules
+            .check_compatible(tokenizer.borrow(py).tokenizer())
             .map_err(|x| PyValueError::new_err(format!("{}", x)))?;
         Ok(PyRules {
             rules: Arc::from(RwLock::from(rules)),
@@ -583,6 +678,9 @@ impl PyRules {
         } else {
             Py::new(py, PyTokenizer::default())?
         };
+        rules
+            .check_compatible(tokenizer.borrow(py).tokenizer())
+            .map_err(|x| PyValueError::new_err(format!("{}", x)))?;
 
         Ok(PyRules {
             rules: Arc::from(RwLock::from(rules)),
@@ -590,6 +688,15 @@ impl PyRules {
         })
     }
 
+    /// Get the tokenizer these rules are applied with.
+    ///
+    /// Returns:
+    ///     tokenizer (Tokenizer): The tokenizer.
+    #[getter]
+    fn tokenizer(&self, py: Python) -> Py<PyTokenizer> {
+        self.tokenizer.clone_ref(py)
+    }
+
     #[getter]
     fn rules(&self) -> Vec<PyRule> {
         self.rules
@@ -640,6 +747,22 @@ impl PyRules {
         })
     }
 
+    /// Explains how the enabled rules are applied to each sentence of the text.
+    /// Only rules with at least one match in a sentence are explained.
+    ///
+    /// Arguments:
+    ///     text (str): The text to explain the rules for.
+    ///
+    /// Returns:
+    ///     explanations (List[dict]): One explanation per sentence and matching rule.
+    #[text_signature = "(text)"]
+    fn explain(&self, py: Python, text: &str) -> PyResult<PyObject> {
+        let tokenizer = self.tokenizer.borrow(py);
+        let tokenizer = tokenizer.tokenizer();
+
+        to_py(py, &self.rules.read().explain(text, tokenizer))
+    }
+
     /// Correct the given text(s).
     ///
     /// Arguments:
@@ -691,13 +814,18 @@ impl PyRules {
     pub fn __setstate__(&mut self, py: Python, state: PyObject) -> PyResult<()> {
         match state.extract::<&PyBytes>(py) {
             Ok(s) => {
-                let state: (Rules, Tokenizer) =
+                let state: (Vec<u8>, Vec<u8>) =
                     bincode::deserialize(s.as_bytes()).map_err(|_| {
                         PyValueError::new_err("deserializing state with `bincode` failed")
                     })?;
+                let rules = Rules::from_reader(&state.0[..])
+                    .map_err(|x| PyValueError::new_err(format!("error reading state: {}", x)))?;
+                let tokenizer = Tokenizer::from_reader(&state.1[..])
+                    .map_err(|x| PyValueError::new_err(format!("error reading state: {}", x)))?;
+
                 // a roundtrip through pickle can not preserve references so we need to create a new Arc<RwLock<..>>
-                self.rules = Arc::from(RwLock::from(state.0));
-                self.tokenizer = Py::new(py, PyTokenizer::from(state.1))?;
+                self.rules = Arc::from(RwLock::from(rules));
+                self.tokenizer = Py::new(py, PyTokenizer::from(tokenizer))?;
                 Ok(())
             }
             Err(e) => Err(e),
@@ -706,8 +834,16 @@ impl PyRules {
 
     pub fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
         let tokenizer = self.tokenizer.borrow(py);
-        // rwlock is serialized the same way as the inner type
-        let state = (&self.rules, tokenizer.tokenizer());
+        let mut state = (Vec::new(), Vec::new());
+
+        self.rules
+            .read()
+            .write(&mut state.0)
+            .map_err(|x| PyValueError::new_err(format!("error writing state: {}", x)))?;
+        tokenizer
+            .tokenizer()
+            .write(&mut state.1)
+            .map_err(|x| PyValueError::new_err(format!("error writing state: {}", x)))?;
 
         Ok(PyBytes::new(
             py,
@@ -107,6 +107,12 @@ def test_pickle_roundtrip_works(tokenizer_and_rules):
     assert len(rules.rules) > 0
 
 
+def test_rules_keep_their_tokenizer(tokenizer_and_rules):
+    (tokenizer, rules) = tokenizer_and_rules
+
+    assert rules.tokenizer is tokenizer
+
+
 def test_invalid_selector_fails(tokenizer_and_rules):
     (tokenizer, rules) = tokenizer_and_rules
 
@@ -138,3 +144,26 @@ def test_rules_can_be_disabled(tokenizer_and_rules):
         rule.disable()
 
     assert len(rules.suggest("I can due his homework")) == 0
+
+
+def test_explain(tokenizer_and_rules):
+    (tokenizer, rules) = tokenizer_and_rules
+
+    text = "I can due his homework."
+    suggestion = rules.suggest(text)[0]
+
+    explanations = [
+        x for x in rules.explain(text) if x["rule"] == suggestion.source
+    ]
+    assert len(explanations) == 1
+
+    candidates = explanations[0]["candidates"]
+    assert any(
+        x["outcome"] == "Suggested"
+        and (x["suggestion"]["start"], x["suggestion"]["end"])
+        == (suggestion.start, suggestion.end)
+        for x in candidates
+    )
+
+    rule = rules.select(suggestion.source)[0]
+    assert len(rule.explain(text, tokenizer)) == 1